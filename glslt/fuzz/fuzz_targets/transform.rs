@@ -0,0 +1,31 @@
+//! Feed arbitrary GLSLT sources through both transform modes
+//!
+//! Inputs which fail to parse are ignored, transform errors are expected but panics are not.
+
+#![no_main]
+
+use glslt::glsl_lang::{ast::TranslationUnit, parse::IntoParseBuilderExt};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let src = match std::str::from_utf8(data) {
+        Ok(src) => src,
+        Err(_) => return,
+    };
+
+    let tu: TranslationUnit = match src
+        .builder()
+        .context(&glslt::parse::make_parse_context(None))
+        .parse()
+    {
+        Ok((tu, _, _)) => tu,
+        Err(_) => return,
+    };
+
+    let _ = glslt::transform(std::iter::once(&tu), Default::default());
+    let _ = glslt::transform_min(
+        std::iter::once(&tu),
+        std::iter::once("main"),
+        Default::default(),
+    );
+});
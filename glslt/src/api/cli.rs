@@ -37,6 +37,18 @@ pub struct Opts {
     /// Identifier prefix for generated code
     #[arg(short, long)]
     prefix: Option<String>,
+
+    /// Maximum size of template instances to inline at call sites
+    #[arg(long)]
+    inline_threshold: Option<usize>,
+
+    /// Maximum number of nested template instantiations
+    #[arg(long)]
+    max_instantiation_depth: Option<usize>,
+
+    /// Keep function prototypes as forward declarations unless they are used as pointer types
+    #[arg(long)]
+    preserve_forward_declarations: bool,
 }
 
 /// Entry point for the glsltc front-end
@@ -77,6 +89,15 @@ pub fn main(opts: Opts) -> Result<(), Box<dyn std::error::Error>> {
         if let Some(prefix) = &opts.prefix {
             builder.prefix(prefix.to_owned());
         }
+        if let Some(threshold) = opts.inline_threshold {
+            builder.inline_threshold(threshold);
+        }
+        if let Some(depth) = opts.max_instantiation_depth {
+            builder.max_instantiation_depth(depth);
+        }
+        if opts.preserve_forward_declarations {
+            builder.preserve_forward_declarations(true);
+        }
         builder.build()
     };
 
@@ -1,4 +1,4 @@
-use std::path::Path;
+use std::{collections::HashSet, path::Path};
 
 use glsl_lang::{ast, lexer::full::fs::PreprocessorExt, parse::IntoParseBuilderExt};
 
@@ -8,29 +8,43 @@ pub fn parse_inputs_as_tu(
     include: impl IntoIterator<Item = impl AsRef<Path>>,
     inputs: impl IntoIterator<Item = impl AsRef<Path>>,
 ) -> Result<ast::TranslationUnit, Box<dyn std::error::Error>> {
-    let mut processor = glsl_lang_pp::processor::fs::StdProcessor::new();
+    let mut processor = glsl_lang_pp::processor::fs::Processor::<crate::parse::PragmaOnceFs>::new();
     processor
         .system_paths_mut()
         .extend(include.into_iter().map(|path| path.as_ref().to_owned()));
 
+    let state = glsl_lang_pp::processor::ProcessorState::builder().extension(
+        ext_name!("GL_GOOGLE_include_directive"),
+        ExtensionBehavior::Enable,
+    );
+
+    // Find the functions defined in all inputs first, so they can be forward-declared anywhere
+    let inputs: Vec<_> = inputs.into_iter().collect();
+    let mut defined_functions = HashSet::new();
+    for input in &inputs {
+        defined_functions.extend(crate::parse::defined_functions::<
+            glsl_lang::lexer::full::fs::Lexer<_>,
+        >(
+            processor.open(input.as_ref())?.with_state(state.clone())
+        ));
+    }
+
     // Join translation units
     let mut external_decls = Vec::new();
-    let mut context = crate::parse::make_parse_context(None);
+    let mut context = crate::parse::make_parse_context_with_functions(None, defined_functions);
 
     for input in inputs {
         let (mut tu, ctx, lexer): (ast::TranslationUnit, _, _) = processor
             .open(input.as_ref())?
-            .with_state(
-                glsl_lang_pp::processor::ProcessorState::builder().extension(
-                    ext_name!("GL_GOOGLE_include_directive"),
-                    ExtensionBehavior::Enable,
-                ),
-            )
+            .with_state(state.clone())
             .builder()
             .context(&context)
             .parse()?;
         context = ctx;
         lexer.into_directives().inject(&mut tu);
+
+        let dir = input.as_ref().parent().unwrap_or_else(|| Path::new("."));
+        crate::parse::resolve_imports(&mut processor, &state.clone().finish(), dir, &mut tu)?;
         external_decls.extend(tu.0.into_iter());
     }
 
@@ -42,7 +56,10 @@ pub fn parse_inputs_as_tu(
 pub fn parse_string(
     source: impl AsRef<str>,
 ) -> Result<ast::TranslationUnit, Box<dyn std::error::Error>> {
-    let context = crate::parse::make_parse_context(None);
+    let context = crate::parse::make_parse_context_with_functions(
+        None,
+        crate::parse::defined_functions::<glsl_lang::lexer::min::str::Lexer>(source.as_ref()),
+    );
     let (mut tu, _, lexer) = source.as_ref().builder().context(&context).parse()?;
     lexer.into_directives().inject(&mut tu);
     Ok(tu)
@@ -38,45 +38,26 @@ impl PyTranslationUnit {
     }
 }
 
-trait HasTransformUnit {
-    fn unit(&self) -> &dyn TransformUnit;
-    fn unit_mut(&mut self) -> &mut dyn TransformUnit;
-}
-
-trait HasTransformUnitExt {
-    fn add_unit(&mut self, unit: PyTranslationUnit) -> PyResult<()>;
+/// Translation units added to a transform unit, transformed together
+///
+/// Declarations are collected across all the added units before any of them is parsed, so the
+/// order in which units are added doesn't matter.
+#[derive(Default, Debug, Clone)]
+struct PendingUnits {
+    units: Vec<TranslationUnit>,
 }
 
-impl<T: HasTransformUnit> HasTransformUnitExt for T {
-    fn add_unit(&mut self, unit: PyTranslationUnit) -> PyResult<()> {
-        for decl in unit.tu.0.into_iter() {
-            self.unit_mut()
-                .parse_external_declaration(decl)
-                .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
-        }
-
-        Ok(())
+impl PendingUnits {
+    fn add_unit(&mut self, unit: PyTranslationUnit) {
+        self.units.push(unit.tu);
     }
-}
 
-macro_rules! impl_unit {
-    ($pyunit:ident => $unit:ident) => {
-        impl From<$unit> for $pyunit {
-            fn from(unit: $unit) -> Self {
-                Self { unit }
-            }
-        }
-
-        impl HasTransformUnit for $pyunit {
-            fn unit(&self) -> &dyn TransformUnit {
-                &self.unit
-            }
-
-            fn unit_mut(&mut self) -> &mut dyn TransformUnit {
-                &mut self.unit
-            }
-        }
-    };
+    fn transform<T: TransformUnit + Clone>(&self, unit: &T) -> PyResult<T> {
+        let mut unit = unit.clone();
+        crate::transform::transform_unit(self.units.iter(), &mut unit)
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        Ok(unit)
+    }
 }
 
 /// Represents a GLSLT transform unit
@@ -84,10 +65,9 @@ macro_rules! impl_unit {
 #[derive(Default, Debug, Clone)]
 pub struct PyUnit {
     unit: Unit,
+    pending: PendingUnits,
 }
 
-impl_unit!(PyUnit => Unit);
-
 #[pymethods]
 impl PyUnit {
     /// Create a new transform unit
@@ -99,14 +79,15 @@ impl PyUnit {
     /// Add a translation unit's declarations to the current transform unit
     #[pyo3(text_signature = "($self, unit, /)")]
     pub fn add_unit(&mut self, unit: PyTranslationUnit) -> PyResult<()> {
-        <Self as HasTransformUnitExt>::add_unit(self, unit)
+        self.pending.add_unit(unit);
+        Ok(())
     }
 
     /// Transform this unit into a translation unit (GLSL syntax tree)
     #[pyo3(text_signature = "($self, /)")]
     pub fn to_translation_unit(&self) -> PyResult<PyTranslationUnit> {
-        self.unit
-            .clone()
+        self.pending
+            .transform(&self.unit)?
             .into_translation_unit()
             .map_err(|e| PyRuntimeError::new_err(e.to_string()))
             .map(Into::into)
@@ -118,10 +99,9 @@ impl PyUnit {
 #[derive(Default, Debug, Clone)]
 pub struct PyMinUnit {
     unit: MinUnit,
+    pending: PendingUnits,
 }
 
-impl_unit!(PyMinUnit => MinUnit);
-
 #[pymethods]
 impl PyMinUnit {
     /// Create a new transform unit
@@ -133,7 +113,8 @@ impl PyMinUnit {
     /// Add a translation unit's declarations to the current transform unit
     #[pyo3(text_signature = "($self, unit, /)")]
     pub fn add_unit(&mut self, unit: PyTranslationUnit) -> PyResult<()> {
-        <Self as HasTransformUnitExt>::add_unit(self, unit)
+        self.pending.add_unit(unit);
+        Ok(())
     }
 
     /// Transform this unit into a translation unit (GLSL syntax tree)
@@ -143,8 +124,8 @@ impl PyMinUnit {
     /// * `wanted`: list of function names to be included in the dependency tree
     #[pyo3(text_signature = "($self, wanted, /)")]
     pub fn to_translation_unit(&self, wanted: Vec<String>) -> PyResult<PyTranslationUnit> {
-        self.unit
-            .clone()
+        self.pending
+            .transform(&self.unit)?
             .into_translation_unit(wanted.iter().map(|s| s.as_str()))
             .map_err(|e| PyRuntimeError::new_err(e.to_string()))
             .map(Into::into)
@@ -184,6 +165,19 @@ fn glslt(_py: Python, m: &Bound<PyModule>) -> PyResult<()> {
             .map_err(|e| PyRuntimeError::new_err(e.to_string()))
     }
 
+    /// Transform a translation unit, following its `#pragma glslt` directives
+    ///
+    /// # Parameters
+    ///
+    /// * `unit`: translation unit to transform
+    #[pyfn(m)]
+    #[pyo3(name = "transform", text_signature = "(unit, /)")]
+    pub fn transform_py(_py: Python, unit: PyTranslationUnit) -> PyResult<PyTranslationUnit> {
+        crate::transform(std::iter::once(&unit.tu), Default::default())
+            .map(Into::into)
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+    }
+
     /// glsltc entry point
     #[pyfn(m)]
     #[pyo3(name = "main")]
@@ -12,6 +12,16 @@ pub enum Error {
         /// Source code of the previous declaration
         previous_declaration: String,
     },
+    /// Function or struct defined in several input files
+    #[error("duplicate definition of {name} at {}, previously defined at {}", span_to_string(.span), span_to_string(.previous_span))]
+    DuplicateDefinition {
+        /// Name of the duplicated function or struct
+        name: SmolStr,
+        /// Location of the duplicate definition
+        span: Option<NodeSpan>,
+        /// Location of the previous definition
+        previous_span: Option<NodeSpan>,
+    },
     /// Template parameter declared with an array specifier
     #[error("template {name} parameter #{index} cannot have an array specifier")]
     ArrayedTemplateParameter {
@@ -20,9 +30,161 @@ pub enum Error {
         /// 0-based position of the parameter in the function declaration
         index: usize,
     },
+    /// Template parameter without a default value following one with a default value
+    #[error("template {name} parameter #{index} must have a default value")]
+    MissingDefaultParameter {
+        /// Name of the template
+        name: SmolStr,
+        /// 0-based position of the parameter in the function declaration
+        index: usize,
+    },
+    /// Named template argument which doesn't match any template parameter
+    #[error("template {name} has no template parameter named {argument}")]
+    UnknownTemplateArgument {
+        /// Name of the template
+        name: SmolStr,
+        /// Name of the argument
+        argument: SmolStr,
+    },
+    /// Template parameter bound by several arguments
+    #[error("template {name} parameter {argument} is bound more than once")]
+    DuplicateTemplateArgument {
+        /// Name of the template
+        name: SmolStr,
+        /// Name of the argument
+        argument: SmolStr,
+    },
+    /// Type template parameter whose type can't be inferred from the call arguments
+    #[error("cannot infer type parameter {parameter} of template {name}")]
+    UnresolvedTypeParameter {
+        /// Name of the template
+        name: SmolStr,
+        /// Name of the type parameter
+        parameter: SmolStr,
+    },
+    /// Type template parameter bound to different types by the call arguments
+    #[error("conflicting types for type parameter {parameter} of template {name}")]
+    ConflictingTypeParameter {
+        /// Name of the template
+        name: SmolStr,
+        /// Name of the type parameter
+        parameter: SmolStr,
+    },
+    /// Argument bound to a constant template parameter is not a constant expression
+    #[error("argument for constant parameter {parameter} of template {name} is not a constant expression")]
+    NonConstantTemplateArgument {
+        /// Name of the template
+        name: SmolStr,
+        /// Name of the constant parameter
+        parameter: SmolStr,
+    },
+    /// Explicit specialization parameters do not match the specialized template
+    #[error("specialization of {name} parameter #{index} does not match the template parameters")]
+    InvalidSpecialization {
+        /// Name of the specialized template
+        name: SmolStr,
+        /// 0-based position of the parameter in the function declaration
+        index: usize,
+    },
+    /// Explicit specialization parameter which is not named after a known function
+    #[error("specialization of {name} parameter #{index} binds unknown function {function}")]
+    UnknownSpecializedFunction {
+        /// Name of the specialized template
+        name: SmolStr,
+        /// 0-based position of the parameter in the function declaration
+        index: usize,
+        /// Name of the parameter
+        function: SmolStr,
+    },
+    /// Argument for an interface parameter which is not a constructor expression of the interface
+    #[error("template {name} parameter #{index} expects a {interface}(...) expression")]
+    InvalidInterfaceArgument {
+        /// Name of the template
+        name: SmolStr,
+        /// Name of the interface of the parameter
+        interface: SmolStr,
+        /// 0-based position of the parameter in the function declaration
+        index: usize,
+    },
+    /// Parameter pack followed by other template parameters
+    #[error(
+        "template {name} parameter #{index} must be the last template parameter to be variadic"
+    )]
+    MisplacedParameterPack {
+        /// Name of the template
+        name: SmolStr,
+        /// 0-based position of the parameter in the function declaration
+        index: usize,
+    },
+    /// Use of a parameter pack which can't be expanded for the arguments of a call
+    #[error("cannot expand parameter pack {parameter} of template {name}")]
+    InvalidPackExpansion {
+        /// Name of the template
+        name: SmolStr,
+        /// Name of the parameter pack
+        parameter: SmolStr,
+    },
+    /// Template which instantiates itself with the same arguments
+    #[error("recursive template instantiation: {}", .chain.join(" -> "))]
+    RecursiveTemplate {
+        /// Names of the nested templates, from the outermost instantiation to the recursive one
+        chain: Vec<SmolStr>,
+    },
+    /// Template instantiations nested deeper than
+    /// [max_instantiation_depth](crate::TransformConfig::max_instantiation_depth)
+    #[error("template instantiation depth exceeds {limit}: {}", .chain.join(" -> "))]
+    InstantiationDepthExceeded {
+        /// Maximum number of nested instantiations
+        limit: usize,
+        /// Names of the nested templates, from the outermost instantiation to the deepest one
+        chain: Vec<SmolStr>,
+    },
+    /// Error raised while instantiating the body of a template
+    ///
+    /// Any other variant may be wrapped in this one when it is raised inside a template: use
+    /// [Error::root_cause] to match on the underlying error.
+    #[error("{error}{}", .backtrace.iter().map(|note| format!("\n  instantiated from {}", note)).collect::<String>())]
+    Instantiation {
+        /// Error raised in the template body
+        error: Box<Error>,
+        /// Template instantiations which led to the error, innermost first
+        backtrace: Vec<InstantiationNote>,
+    },
+    /// Template call which matches several overloads equally well
+    #[error("ambiguous call to overloaded template {0}")]
+    AmbiguousTemplateCall(SmolStr),
+    /// Lambda expression which writes to a read-only variable
+    #[error("lambda expression writes to read-only variable {0}")]
+    ReadOnlyCapture(SmolStr),
     /// Unsupported preprocessor directive in minifying mode
     #[error("unsupported preprocessor directive in minifying mode: {0:?}")]
     UnsupportedPreprocessor(Box<Preprocessor>),
+    /// Malformed or unknown `#pragma glslt` directive
+    #[error("invalid directive #pragma {command} at {}", span_to_string(.span))]
+    InvalidPragma {
+        /// Text of the directive
+        command: String,
+        /// Location of the directive
+        span: Option<NodeSpan>,
+    },
+    /// `#pragma glslt import` directive which wasn't replaced with the imported declarations
+    #[error("unresolved import of {path} at {}", span_to_string(.span))]
+    UnresolvedImport {
+        /// Path of the imported file
+        path: String,
+        /// Location of the directive
+        span: Option<NodeSpan>,
+    },
+    /// Qualified name which doesn't refer to a declaration of its namespace
+    #[error("{namespace} has no declaration named {name}")]
+    UnknownQualifiedName {
+        /// Namespace of the name
+        namespace: SmolStr,
+        /// Name in the namespace
+        name: SmolStr,
+        /// Location of the qualified name
+        span: Option<NodeSpan>,
+    },
     /// Unsupported declarator list in minifying mode
     #[error("unsupported declarator list in minifying mode: {0:?}")]
     UnsupportedIdl(Box<InitDeclaratorList>),
@@ -35,11 +197,65 @@ pub enum Error {
     /// Invalid parameter in template argument call
     #[error("invalid argument at index {0}")]
     InvalidParameter(usize),
+    /// Expression processed as a function call which isn't one
+    #[error("expected a function call expression")]
+    ExpectedFunctionCall {
+        /// Location of the expression
+        span: Option<NodeSpan>,
+    },
+    /// Function which calls itself
+    #[error("recursive call to function {name}")]
+    RecursiveFunction {
+        /// Name of the recursive function
+        name: SmolStr,
+        /// Location of the recursive call
+        span: Option<NodeSpan>,
+    },
 }
 
 /// GLSLT Result
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// Template instantiation in the backtrace of an [Error::Instantiation]
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstantiationNote {
+    /// Name of the instantiated template
+    pub template: SmolStr,
+    /// Source code of the call arguments
+    pub arguments: Vec<String>,
+    /// Location of the call which instantiated the template
+    pub span: Option<NodeSpan>,
+}
+
+impl InstantiationNote {
+    /// Create a new instantiation note
+    ///
+    /// # Parameters
+    ///
+    /// * `template`: name of the instantiated template
+    /// * `arguments`: call arguments
+    /// * `span`: location of the call
+    pub fn new(template: SmolStr, arguments: &[Expr], span: Option<NodeSpan>) -> Self {
+        Self {
+            template,
+            arguments: arguments.iter().map(expr_to_string).collect(),
+            span,
+        }
+    }
+}
+
+impl std::fmt::Display for InstantiationNote {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}({})", self.template, self.arguments.join(", "))?;
+
+        if let Some(span) = self.span {
+            write!(f, " at {}", span.start())?;
+        }
+
+        Ok(())
+    }
+}
+
 fn prototype_to_string(prototype: &FunctionPrototype) -> String {
     let mut s = String::new();
     glsl_lang::transpiler::glsl::show_function_prototype(
@@ -51,6 +267,24 @@ fn prototype_to_string(prototype: &FunctionPrototype) -> String {
     s
 }
 
+fn span_to_string(span: &Option<NodeSpan>) -> String {
+    match span {
+        Some(span) => span.start().to_string(),
+        None => "unknown location".to_owned(),
+    }
+}
+
+fn expr_to_string(expr: &Expr) -> String {
+    let mut s = String::new();
+    glsl_lang::transpiler::glsl::show_expr(
+        &mut s,
+        expr,
+        &mut glsl_lang::transpiler::glsl::FormattingState::default(),
+    )
+    .unwrap();
+    s
+}
+
 impl Error {
     /// Create a new [DuplicatePointerDefinition](#variant.DuplicatePointerDefinition)
     ///
@@ -67,4 +301,33 @@ impl Error {
             previous_declaration: prototype_to_string(previous),
         }
     }
+
+    /// Add a template instantiation to the backtrace of this error
+    ///
+    /// # Parameters
+    ///
+    /// * `note`: instantiation of the template whose body raised this error
+    pub fn instantiated_from(self, note: InstantiationNote) -> Self {
+        match self {
+            Error::Instantiation {
+                error,
+                mut backtrace,
+            } => {
+                backtrace.push(note);
+                Error::Instantiation { error, backtrace }
+            }
+            error => Error::Instantiation {
+                error: Box::new(error),
+                backtrace: vec![note],
+            },
+        }
+    }
+
+    /// Return the underlying error, without its instantiation backtrace
+    pub fn root_cause(&self) -> &Error {
+        match self {
+            Error::Instantiation { error, .. } => error,
+            error => error,
+        }
+    }
 }
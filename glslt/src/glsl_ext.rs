@@ -51,3 +51,166 @@ impl FunIdentifierExt for ast::FunIdentifier {
         }
     }
 }
+
+/// Extensions for [`glsl_lang::ast::FunctionParameterDeclaration`]
+pub trait FunctionParameterDeclarationExt {
+    /// Return the type of the parameter, ignoring its name and qualifiers
+    ///
+    /// The array specifier is the one declared after the parameter name, if any.
+    fn parameter_type(&self) -> (&ast::TypeSpecifier, Option<&ast::ArraySpecifier>);
+
+    /// Return `true` if the parameter may be written to by the function, i.e. it is declared
+    /// `out` or `inout`
+    fn is_output(&self) -> bool;
+}
+
+impl FunctionParameterDeclarationExt for ast::FunctionParameterDeclaration {
+    fn parameter_type(&self) -> (&ast::TypeSpecifier, Option<&ast::ArraySpecifier>) {
+        match &**self {
+            ast::FunctionParameterDeclarationData::Named(_, d) => {
+                (&d.ty, d.ident.array_spec.as_ref())
+            }
+            ast::FunctionParameterDeclarationData::Unnamed(_, t) => (t, None),
+        }
+    }
+
+    fn is_output(&self) -> bool {
+        let qualifier = match &**self {
+            ast::FunctionParameterDeclarationData::Named(q, _) => q,
+            ast::FunctionParameterDeclarationData::Unnamed(q, _) => q,
+        };
+
+        qualifier.as_ref().is_some_and(|q| {
+            q.qualifiers.iter().any(|spec| match &**spec {
+                ast::TypeQualifierSpecData::Storage(sq) => {
+                    matches!(
+                        &**sq,
+                        ast::StorageQualifierData::Out | ast::StorageQualifierData::InOut
+                    )
+                }
+                _ => false,
+            })
+        })
+    }
+}
+
+/// Extensions for [`glsl_lang::ast::FunctionPrototype`]
+pub trait FunctionPrototypeExt {
+    /// Return `true` if both prototypes have the same parameter types, i.e. one can't be an
+    /// overload of the other
+    fn same_parameter_types(&self, other: &ast::FunctionPrototype) -> bool;
+
+    /// Return the parameter types of this prototype as a string, which identifies an overload
+    /// among the functions with the same name
+    fn signature(&self) -> SmolStr;
+
+    /// Return `true` if this prototype declares a type template parameter instead of a pointer
+    /// type, i.e. it is written `layout(typename) void T();`
+    fn is_type_parameter(&self) -> bool;
+
+    /// Return `true` if this prototype is explicitly marked as a pointer type, i.e. it is written
+    /// `layout(pointer) float f(vec3 p);`
+    fn is_pointer_type(&self) -> bool;
+
+    /// Return `true` if this prototype is marked as private to its file, i.e. it is written
+    /// `layout(private) float f(vec3 p);`
+    fn is_private(&self) -> bool;
+}
+
+impl FunctionPrototypeExt for ast::FunctionPrototype {
+    fn same_parameter_types(&self, other: &ast::FunctionPrototype) -> bool {
+        self.parameters.len() == other.parameters.len()
+            && self
+                .parameters
+                .iter()
+                .zip(other.parameters.iter())
+                .all(|(a, b)| a.parameter_type() == b.parameter_type())
+    }
+
+    fn signature(&self) -> SmolStr {
+        let mut sbuf = String::new();
+
+        for (index, parameter) in self.parameters.iter().enumerate() {
+            if index > 0 {
+                sbuf.push(',');
+            }
+
+            let (ty, array) = parameter.parameter_type();
+            glsl_lang::transpiler::glsl::show_type_specifier(
+                &mut sbuf,
+                ty,
+                &mut glsl_lang::transpiler::glsl::FormattingState::default(),
+            )
+            .unwrap();
+
+            if let Some(array) = array {
+                glsl_lang::transpiler::glsl::show_array_spec(
+                    &mut sbuf,
+                    array,
+                    &mut glsl_lang::transpiler::glsl::FormattingState::default(),
+                )
+                .unwrap();
+            }
+        }
+
+        sbuf.into()
+    }
+
+    fn is_type_parameter(&self) -> bool {
+        self.parameters.is_empty()
+            && self
+                .ty
+                .qualifier
+                .as_ref()
+                .is_some_and(|q| q.has_layout_flag("typename"))
+    }
+
+    fn is_pointer_type(&self) -> bool {
+        self.ty
+            .qualifier
+            .as_ref()
+            .is_some_and(|q| q.has_layout_flag("pointer"))
+    }
+
+    fn is_private(&self) -> bool {
+        self.ty
+            .qualifier
+            .as_ref()
+            .is_some_and(|q| q.has_layout_flag("private"))
+    }
+}
+
+/// Extensions for [`glsl_lang::ast::TypeQualifier`]
+pub trait TypeQualifierExt {
+    /// Return `true` if variables declared with this qualifier can't be written to
+    fn is_read_only(&self) -> bool;
+
+    /// Return `true` if this qualifier contains a layout identifier without a value, such as
+    /// `layout(typename)`
+    fn has_layout_flag(&self, name: &str) -> bool;
+}
+
+impl TypeQualifierExt for ast::TypeQualifier {
+    fn is_read_only(&self) -> bool {
+        self.qualifiers.iter().any(|spec| match &**spec {
+            ast::TypeQualifierSpecData::Storage(sq) => matches!(
+                &**sq,
+                ast::StorageQualifierData::Const
+                    | ast::StorageQualifierData::In
+                    | ast::StorageQualifierData::Uniform
+                    | ast::StorageQualifierData::ReadOnly
+                    | ast::StorageQualifierData::Attribute
+            ),
+            _ => false,
+        })
+    }
+
+    fn has_layout_flag(&self, name: &str) -> bool {
+        self.qualifiers.iter().any(|spec| match &**spec {
+            ast::TypeQualifierSpecData::Layout(layout) => layout.ids.iter().any(|id| {
+                matches!(&**id, ast::LayoutQualifierSpecData::Identifier(ident, None) if ident.0 == name)
+            }),
+            _ => false,
+        })
+    }
+}
@@ -43,6 +43,9 @@
 //! // Create the transform unit
 //! let mut unit = Unit::new();
 //!
+//! // Collect the declarations of all the inputs
+//! unit.collect_declarations(&[&tu]).expect("failed to collect declarations");
+//!
 //! // Parse declarations
 //! for decl in tu.0.into_iter() {
 //!     unit.parse_external_declaration(decl).expect("failed to parse declaration");
@@ -95,17 +98,32 @@ pub use glsl_lang;
 /// Prefix for generated names for functions and captured parameters
 pub const DEFAULT_PREFIX: &str = "_glslt_";
 
+/// Maximum number of nested template instantiations
+pub const DEFAULT_MAX_INSTANTIATION_DEPTH: usize = 64;
+
 /// Global trasnform parameters
 #[derive(Debug, Clone)]
 pub struct TransformConfig {
     /// Identifier prefix for code generation
     pub prefix: String,
+    /// Maximum size (in expression nodes) of single-return template instances to inline at their
+    /// call sites. Instances are never inlined if `None`.
+    pub inline_threshold: Option<usize>,
+    /// Maximum number of nested template instantiations. Deeper instantiations are assumed to
+    /// be unbounded recursion.
+    pub max_instantiation_depth: usize,
+    /// Keep function prototypes as forward declarations, unless they are marked with
+    /// `layout(pointer)` or used as the type of a template parameter
+    pub preserve_forward_declarations: bool,
 }
 
 impl Default for TransformConfig {
     fn default() -> Self {
         Self {
             prefix: DEFAULT_PREFIX.to_owned(),
+            inline_threshold: None,
+            max_instantiation_depth: DEFAULT_MAX_INSTANTIATION_DEPTH,
+            preserve_forward_declarations: false,
         }
     }
 }
@@ -115,6 +133,12 @@ impl Default for TransformConfig {
 pub struct TransformConfigBuilder {
     /// Identifier prefix for code generation
     prefix: Option<String>,
+    /// Maximum size of template instances to inline
+    inline_threshold: Option<usize>,
+    /// Maximum number of nested template instantiations
+    max_instantiation_depth: Option<usize>,
+    /// Keep function prototypes as forward declarations
+    preserve_forward_declarations: Option<bool>,
 }
 
 impl TransformConfigBuilder {
@@ -126,6 +150,30 @@ impl TransformConfigBuilder {
         new
     }
 
+    /// Maximum size of template instances to inline
+    #[allow(unused_mut)]
+    pub fn inline_threshold(&mut self, value: usize) -> &mut Self {
+        let mut new = self;
+        new.inline_threshold = Some(value);
+        new
+    }
+
+    /// Maximum number of nested template instantiations
+    #[allow(unused_mut)]
+    pub fn max_instantiation_depth(&mut self, value: usize) -> &mut Self {
+        let mut new = self;
+        new.max_instantiation_depth = Some(value);
+        new
+    }
+
+    /// Keep function prototypes as forward declarations
+    #[allow(unused_mut)]
+    pub fn preserve_forward_declarations(&mut self, value: bool) -> &mut Self {
+        let mut new = self;
+        new.preserve_forward_declarations = Some(value);
+        new
+    }
+
     ///Builds a new `TransformConfig`.
     ///
     ///# Errors
@@ -137,6 +185,12 @@ impl TransformConfigBuilder {
                 Some(value) => value,
                 None => DEFAULT_PREFIX.to_owned(),
             },
+            inline_threshold: self.inline_threshold,
+            max_instantiation_depth: match self.max_instantiation_depth {
+                Some(value) => value,
+                None => DEFAULT_MAX_INSTANTIATION_DEPTH,
+            },
+            preserve_forward_declarations: self.preserve_forward_declarations.unwrap_or(false),
         }
     }
 }
@@ -151,6 +205,8 @@ pub mod glsl_ext;
 #[cfg(feature = "parse")]
 pub mod parse;
 
+pub mod pragma;
+
 pub mod transform;
 pub use transform::{transform, transform_min};
 
@@ -1,9 +1,32 @@
 //! Parsing utilities
 
-use glsl_lang::lexer::{ParseContext, ParseContextData};
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-struct GlsltPolicy;
+use glsl_lang::{
+    ast::{
+        ExternalDeclarationData, NodeContent, PreprocessorData, PreprocessorPragmaData, SmolStr,
+        TranslationUnit,
+    },
+    lexer::{LangLexer, ParseContext, ParseContextData, ParseOptions, Token},
+};
+use glsl_lang_pp::{
+    processor::{
+        fs::{FileSystem, Processor, Std},
+        ProcessorState,
+    },
+    types::path::{ParsedPath, PathType},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+struct GlsltPolicy {
+    /// Names of the functions defined in the input, which are never pointer types
+    defined_functions: HashSet<SmolStr>,
+}
 
 impl glsl_lang::lexer::TypeTablePolicy for GlsltPolicy {
     fn promote_to_type_name(
@@ -11,6 +34,11 @@ impl glsl_lang::lexer::TypeTablePolicy for GlsltPolicy {
         name: &glsl_lang::ast::Identifier,
         ctx: glsl_lang::lexer::IdentifierContext,
     ) -> bool {
+        if self.defined_functions.contains(&name.0) {
+            trace!("glslt parsing: {} is a forward declaration", name);
+            return false;
+        }
+
         trace!("glslt parsing: promoting {} to type name", name);
         ctx == glsl_lang::lexer::IdentifierContext::FunctionPrototype
     }
@@ -22,9 +50,317 @@ impl glsl_lang::lexer::TypeTablePolicy for GlsltPolicy {
 ///
 /// * `existing`: optional existing parsing context to reuse
 pub fn make_parse_context(existing: Option<&ParseContext>) -> ParseContext {
+    make_parse_context_with_functions(existing, HashSet::new())
+}
+
+/// Create a parsing context that follows GLSLT's type rules, and parses prototypes of the given
+/// functions as forward declarations
+///
+/// Function prototypes are usually pointer types, so their names become type names. This prevents
+/// the parsing of a function definition with the same name, so the names returned by
+/// [defined_functions] should be given here for forward declarations to be supported.
+///
+/// # Parameters
+///
+/// * `existing`: optional existing parsing context to reuse
+/// * `defined_functions`: names of the functions defined in the input
+pub fn make_parse_context_with_functions(
+    existing: Option<&ParseContext>,
+    defined_functions: HashSet<SmolStr>,
+) -> ParseContext {
+    let policy = GlsltPolicy { defined_functions };
+
     if let Some(existing) = existing {
-        existing.with_policy(GlsltPolicy)
+        existing.with_policy(policy)
     } else {
-        ParseContext::new_with_context(ParseContextData::with_comments_and_policy(GlsltPolicy))
+        ParseContext::new_with_context(ParseContextData::with_comments_and_policy(policy))
+    }
+}
+
+/// Collect the names of the functions defined at the top level of a source
+///
+/// This only runs the lexer, so it can be used before parsing to build a parsing context with
+/// [make_parse_context_with_functions]. Lexical errors are ignored, they will be reported by the
+/// parser.
+///
+/// # Parameters
+///
+/// * `source`: input for the lexer `L`
+pub fn defined_functions<'i, L: LangLexer<'i>>(source: L::Input) -> HashSet<SmolStr> {
+    let mut functions = HashSet::new();
+
+    // Without a GLSLT policy, all the names declared in the source are identifiers
+    let tokens = L::new(source, &ParseOptions::default())
+        .run(ParseContext::default())
+        .filter_map(|item| item.ok().map(|(_, token, _)| token));
+
+    // A definition is an identifier followed by a parameter list and a block, outside of any block
+    let mut depth = 0usize;
+    let mut parens = 0usize;
+    let mut candidate = None;
+    let mut previous = None;
+
+    for token in tokens {
+        match &token {
+            Token::LeftBrace => {
+                if depth == 0 && parens == 0 {
+                    if let Some(name) = candidate.take() {
+                        functions.insert(name);
+                    }
+                }
+
+                depth += 1;
+            }
+            Token::RightBrace => {
+                depth = depth.saturating_sub(1);
+            }
+            Token::LeftParen if depth == 0 => {
+                if parens == 0 {
+                    candidate = match previous {
+                        Some(Token::Identifier(name)) => Some(name),
+                        _ => None,
+                    };
+                }
+
+                parens += 1;
+            }
+            Token::RightParen if depth == 0 => {
+                parens = parens.saturating_sub(1);
+            }
+            _ if depth == 0 && parens == 0 => {
+                candidate = None;
+            }
+            _ => {}
+        }
+
+        previous = Some(token);
     }
+
+    functions
+}
+
+/// File system for the preprocessor which turns `#pragma once` directives into include guards
+///
+/// The preprocessor doesn't support `#pragma once`, so a library file included twice would have
+/// all its declarations repeated. Files read through this file system are wrapped in an
+/// `#ifndef`/`#endif` pair instead, keyed on their path, without changing their line numbers.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct PragmaOnceFs<F = Std> {
+    fs: F,
+}
+
+impl<F> PragmaOnceFs<F> {
+    /// Create a new file system which reads files from `fs`
+    ///
+    /// # Parameters
+    ///
+    /// * `fs`: underlying file system
+    pub fn new(fs: F) -> Self {
+        Self { fs }
+    }
+}
+
+impl<F: FileSystem> FileSystem for PragmaOnceFs<F> {
+    type Error = F::Error;
+
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, Self::Error> {
+        self.fs.canonicalize(path)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.fs.exists(path)
+    }
+
+    fn read(&self, path: &Path) -> Result<Cow<'_, str>, Self::Error> {
+        self.fs
+            .read(path)
+            .map(|source| guard_pragma_once(path, source))
+    }
+}
+
+/// Replace the `#pragma once` directive of a source with an include guard
+///
+/// Sources without a `#pragma once` directive are returned as-is.
+///
+/// # Parameters
+///
+/// * `path`: path of the source, which identifies the include guard
+/// * `source`: contents of the source
+pub fn guard_pragma_once<'s>(path: &Path, source: Cow<'s, str>) -> Cow<'s, str> {
+    let is_pragma_once = |line: &str| {
+        line.trim()
+            .strip_prefix('#')
+            .and_then(|directive| directive.trim_start().strip_prefix("pragma"))
+            .is_some_and(|pragma| {
+                pragma.starts_with(char::is_whitespace) && pragma.trim() == "once"
+            })
+    };
+
+    if !source.lines().any(is_pragma_once) {
+        return source;
+    }
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    path.hash(&mut hasher);
+    let guard = format!("_GLSLT_ONCE_{:016x}", hasher.finish());
+
+    // Only the directive line is replaced, so line numbers are preserved
+    let mut guarded = String::with_capacity(source.len() + 2 * guard.len() + 32);
+    let mut replaced = false;
+    for line in source.lines() {
+        if !replaced && is_pragma_once(line) {
+            guarded.push_str("#ifndef ");
+            guarded.push_str(&guard);
+            replaced = true;
+        } else {
+            guarded.push_str(line);
+        }
+
+        guarded.push('\n');
+    }
+
+    guarded.push_str("#define ");
+    guarded.push_str(&guard);
+    guarded.push_str("\n#endif\n");
+
+    guarded.into()
+}
+
+/// Replace the `#pragma glslt import "path" as namespace` directives of a translation unit with
+/// the declarations of the imported files
+///
+/// Each imported file is parsed on its own, along with its own imports, so the names it declares
+/// don't conflict with the type names of the importing file or of other libraries. Its
+/// declarations are enclosed in `#pragma glslt namespace(...)` and `#pragma glslt end_namespace`
+/// directives, which make the transform prefix them with the namespace. A file imported several
+/// times under the same namespace is only included once.
+///
+/// # Parameters
+///
+/// * `processor`: preprocessor for the imported files and their includes
+/// * `state`: initial preprocessor state for the imported files
+/// * `dir`: directory of the importing file, which relative import paths are resolved against
+/// * `tu`: translation unit to resolve the imports of
+pub fn resolve_imports<F: FileSystem>(
+    processor: &mut Processor<F>,
+    state: &ProcessorState,
+    dir: &Path,
+    tu: &mut TranslationUnit,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    F::Error: std::error::Error + 'static,
+{
+    resolve_imports_in(
+        processor,
+        state,
+        dir,
+        None,
+        tu,
+        &mut HashSet::new(),
+        &mut Vec::new(),
+    )
+}
+
+fn resolve_imports_in<F: FileSystem>(
+    processor: &mut Processor<F>,
+    state: &ProcessorState,
+    dir: &Path,
+    namespace: Option<&str>,
+    tu: &mut TranslationUnit,
+    imported: &mut HashSet<(PathBuf, String)>,
+    importing: &mut Vec<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    F::Error: std::error::Error + 'static,
+{
+    use glsl_lang::{lexer::full::fs::PreprocessorExt, parse::IntoParseBuilderExt};
+
+    let mut resolved = Vec::with_capacity(tu.0.len());
+
+    for extdecl in std::mem::take(&mut tu.0) {
+        let Some(import) = crate::pragma::parse_import(&extdecl) else {
+            resolved.push(extdecl);
+            continue;
+        };
+
+        let import = import?;
+        let path = processor
+            .resolve_relative_to_path(
+                dir,
+                &ParsedPath {
+                    path: import.path.clone(),
+                    ty: if import.system {
+                        PathType::Angle
+                    } else {
+                        PathType::Quote
+                    },
+                },
+            )
+            .ok_or_else(|| format!("imported file not found: {}", import.path))?;
+        let path =
+            std::fs::canonicalize(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+
+        let namespace = match namespace {
+            Some(parent) => format!("{}_{}", parent, import.namespace),
+            None => import.namespace.to_string(),
+        };
+
+        if importing.contains(&path) {
+            return Err(format!("{} imports itself", path.display()).into());
+        }
+
+        if !imported.insert((path.clone(), namespace.clone())) {
+            continue;
+        }
+
+        // Each import gets its own file id, so private functions of a file imported under several
+        // namespaces don't conflict
+        let source =
+            std::fs::read_to_string(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        let source = guard_pragma_once(&path, source.into());
+        let parent = path.parent().unwrap_or(dir);
+
+        let defined_functions = defined_functions::<glsl_lang::lexer::full::fs::Lexer<_>>(
+            processor
+                .open_source(&source, parent)
+                .with_state(state.clone()),
+        );
+
+        let (mut library, _, lexer): (TranslationUnit, _, _) = processor
+            .open_source(&source, parent)
+            .with_state(state.clone())
+            .builder()
+            .context(&make_parse_context_with_functions(None, defined_functions))
+            .parse()?;
+        lexer.into_directives().inject(&mut library);
+
+        importing.push(path.clone());
+        resolve_imports_in(
+            processor,
+            state,
+            parent,
+            Some(&namespace),
+            &mut library,
+            imported,
+            importing,
+        )?;
+        importing.pop();
+
+        let pragma = |command: String| {
+            let mut pragma = ExternalDeclarationData::Preprocessor(
+                PreprocessorData::Pragma(PreprocessorPragmaData { command }.into_node())
+                    .into_node(),
+            )
+            .into_node();
+            pragma.span = extdecl.span;
+            pragma
+        };
+
+        resolved.push(pragma(format!("glslt namespace({})", import.namespace)));
+        resolved.extend(library.0);
+        resolved.push(pragma("glslt end_namespace".to_owned()));
+    }
+
+    tu.0 = resolved;
+    Ok(())
 }
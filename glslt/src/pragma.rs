@@ -0,0 +1,311 @@
+//! In-source configuration with `#pragma glslt` directives
+//!
+//! The following directives are recognized in the input, and removed from the output:
+//!
+//! * `#pragma glslt prefix(_p_)`: identifier prefix for generated code
+//! * `#pragma glslt inline_threshold(16)`: maximum size of template instances to inline
+//! * `#pragma glslt max_instantiation_depth(32)`: maximum number of nested template instantiations
+//! * `#pragma glslt preserve_forward_declarations`: keep function prototypes as forward
+//!   declarations
+//! * `#pragma glslt minify`: only output the transitive dependencies of the kept functions, which
+//!   default to `main`
+//! * `#pragma glslt keep(mainImage, ...)`: functions to keep in minifying mode, which implies
+//!   `minify`
+//!
+//! Settings from the input take precedence over the [TransformConfig] given by the caller, so a
+//! shader compiles the same way from any front-end.
+//!
+//! The declarations between `#pragma glslt namespace(sdf)` and `#pragma glslt end_namespace`
+//! belong to the `sdf` namespace. `#pragma glslt import "sdf.glsl" as sdf` directives are replaced
+//! with such a block by `glslt::parse::resolve_imports`, and can't be transformed on their
+//! own.
+
+use glsl_lang::ast::*;
+
+use crate::{Error, Result, TransformConfig};
+
+/// Configuration read from the `#pragma glslt` directives of the input
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct SourceConfig {
+    /// Identifier prefix for code generation
+    pub prefix: Option<String>,
+    /// Maximum size of template instances to inline
+    pub inline_threshold: Option<usize>,
+    /// Maximum number of nested template instantiations
+    pub max_instantiation_depth: Option<usize>,
+    /// Keep function prototypes as forward declarations
+    pub preserve_forward_declarations: bool,
+    /// Only output the transitive dependencies of the kept functions
+    pub minify: bool,
+    /// Functions to keep in minifying mode
+    pub keep_fns: Vec<String>,
+}
+
+impl SourceConfig {
+    /// Read the configuration directives of a set of translation units
+    ///
+    /// # Parameters
+    ///
+    /// * `asts`: translation units to read the directives of
+    ///
+    /// # Errors
+    ///
+    /// Return [Error::InvalidPragma] if a `#pragma glslt` directive is malformed.
+    pub fn from_translation_units<'a>(
+        asts: impl IntoIterator<Item = &'a TranslationUnit>,
+    ) -> Result<Self> {
+        let mut config = Self::default();
+
+        for extdecl in asts.into_iter().flat_map(|ast| ast.0.iter()) {
+            config.parse_external_declaration(extdecl)?;
+        }
+
+        Ok(config)
+    }
+
+    /// Update this configuration from a declaration, if it is a `#pragma glslt` directive
+    ///
+    /// Returns `true` if the declaration was a configuration directive.
+    ///
+    /// # Parameters
+    ///
+    /// * `extdecl`: declaration to parse
+    ///
+    /// # Errors
+    ///
+    /// Return [Error::InvalidPragma] if the directive is malformed.
+    pub fn parse_external_declaration(&mut self, extdecl: &ExternalDeclaration) -> Result<bool> {
+        let Some(pragma) = glslt_pragma(extdecl) else {
+            return Ok(false);
+        };
+
+        // Imports are resolved while parsing, and namespaces are handled by the transform
+        if let Some(import) = parse_import(extdecl) {
+            return Err(Error::UnresolvedImport {
+                path: import?.path,
+                span: pragma.span,
+            });
+        }
+
+        if parse_namespace_directive(extdecl).transpose()?.is_some() {
+            return Ok(false);
+        }
+
+        let invalid = || invalid_pragma(pragma);
+        let directive = glslt_directive(pragma);
+
+        // Directives are either a single name or a name followed by a list of arguments
+        let (name, arguments) = match directive.split_once('(') {
+            Some((name, arguments)) => {
+                let arguments = arguments
+                    .trim_end()
+                    .strip_suffix(')')
+                    .ok_or_else(invalid)?
+                    .split(',')
+                    .map(str::trim)
+                    .collect::<Vec<_>>();
+
+                if arguments.iter().any(|argument| argument.is_empty()) {
+                    return Err(invalid());
+                }
+
+                (name.trim(), Some(arguments))
+            }
+            None => (directive, None),
+        };
+
+        let single = |arguments: Option<Vec<&str>>| match arguments.as_deref() {
+            Some([argument]) => Ok(argument.to_string()),
+            _ => Err(invalid()),
+        };
+
+        let number = |arguments| single(arguments)?.parse().map_err(|_| invalid());
+
+        match name {
+            "prefix" => self.prefix = Some(single(arguments)?),
+            "inline_threshold" => self.inline_threshold = Some(number(arguments)?),
+            "max_instantiation_depth" => self.max_instantiation_depth = Some(number(arguments)?),
+            "preserve_forward_declarations" if arguments.is_none() => {
+                self.preserve_forward_declarations = true
+            }
+            "minify" if arguments.is_none() => self.minify = true,
+            "keep" => {
+                for name in arguments.ok_or_else(invalid)? {
+                    if !self.keep_fns.iter().any(|kept| kept == name) {
+                        self.keep_fns.push(name.to_owned());
+                    }
+                }
+                self.minify = true;
+            }
+            _ => return Err(invalid()),
+        }
+
+        Ok(true)
+    }
+
+    /// Override the settings of a transform configuration with the ones of this configuration
+    ///
+    /// # Parameters
+    ///
+    /// * `config`: configuration to update
+    pub fn apply(&self, config: &mut TransformConfig) {
+        if let Some(prefix) = &self.prefix {
+            config.prefix = prefix.clone();
+        }
+
+        if let Some(threshold) = self.inline_threshold {
+            config.inline_threshold = Some(threshold);
+        }
+
+        if let Some(depth) = self.max_instantiation_depth {
+            config.max_instantiation_depth = depth;
+        }
+
+        if self.preserve_forward_declarations {
+            config.preserve_forward_declarations = true;
+        }
+    }
+}
+
+/// Return the `#pragma glslt` directive of a declaration, if it is one
+///
+/// # Parameters
+///
+/// * `extdecl`: declaration to inspect
+pub fn glslt_pragma(extdecl: &ExternalDeclaration) -> Option<&PreprocessorPragma> {
+    match &extdecl.content {
+        ExternalDeclarationData::Preprocessor(pp) => match &pp.content {
+            PreprocessorData::Pragma(pragma)
+                if pragma
+                    .command
+                    .trim()
+                    .strip_prefix("glslt")
+                    .is_some_and(|rest| {
+                        rest.is_empty() || rest.starts_with(char::is_whitespace)
+                    }) =>
+            {
+                Some(pragma)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Return the text of a `#pragma glslt` directive, without the `glslt` prefix
+fn glslt_directive(pragma: &PreprocessorPragma) -> &str {
+    pragma
+        .command
+        .trim()
+        .strip_prefix("glslt")
+        .unwrap_or_default()
+        .trim()
+}
+
+fn invalid_pragma(pragma: &PreprocessorPragma) -> Error {
+    Error::InvalidPragma {
+        command: pragma.command.clone(),
+        span: pragma.span,
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Start or end of a namespace block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NamespaceDirective {
+    /// `#pragma glslt namespace(name)`: the following declarations belong to the `name`
+    /// namespace, nested in the current one
+    Begin(SmolStr),
+    /// `#pragma glslt end_namespace`: end of the current namespace
+    End,
+}
+
+/// Return the namespace directive of a declaration, if it is one
+///
+/// # Parameters
+///
+/// * `extdecl`: declaration to inspect
+///
+/// # Errors
+///
+/// Return [Error::InvalidPragma] if the directive is malformed.
+pub fn parse_namespace_directive(
+    extdecl: &ExternalDeclaration,
+) -> Option<Result<NamespaceDirective>> {
+    let pragma = glslt_pragma(extdecl)?;
+    let directive = glslt_directive(pragma);
+
+    if directive == "end_namespace" {
+        return Some(Ok(NamespaceDirective::End));
+    }
+
+    let name = directive
+        .strip_prefix("namespace")
+        .filter(|rest| rest.trim_start().starts_with('('))?;
+
+    Some(
+        name.trim()
+            .strip_prefix('(')
+            .and_then(|name| name.strip_suffix(')'))
+            .map(str::trim)
+            .filter(|name| is_identifier(name))
+            .map(|name| NamespaceDirective::Begin(name.into()))
+            .ok_or_else(|| invalid_pragma(pragma)),
+    )
+}
+
+/// Library imported by a `#pragma glslt import "path" as namespace` directive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Import {
+    /// Path of the imported file, as written in the directive
+    pub path: String,
+    /// `true` if the path is written between angle brackets, i.e. it is looked up in the include
+    /// paths instead of relative to the importing file
+    pub system: bool,
+    /// Namespace of the imported declarations
+    pub namespace: SmolStr,
+}
+
+/// Return the library imported by a declaration, if it is a `#pragma glslt import` directive
+///
+/// # Parameters
+///
+/// * `extdecl`: declaration to inspect
+///
+/// # Errors
+///
+/// Return [Error::InvalidPragma] if the directive is malformed.
+pub fn parse_import(extdecl: &ExternalDeclaration) -> Option<Result<Import>> {
+    let pragma = glslt_pragma(extdecl)?;
+    let rest = glslt_directive(pragma).strip_prefix("import")?;
+
+    let import = || {
+        let rest = rest.trim_start();
+        let (close, system) = match rest.chars().next()? {
+            '"' => ('"', false),
+            '<' => ('>', true),
+            _ => return None,
+        };
+
+        let (path, rest) = rest[1..].split_once(close)?;
+        let namespace = rest
+            .strip_prefix(char::is_whitespace)?
+            .trim_start()
+            .strip_prefix("as")?
+            .trim();
+
+        (!path.is_empty() && is_identifier(namespace)).then(|| Import {
+            path: path.to_owned(),
+            system,
+            namespace: namespace.into(),
+        })
+    };
+
+    Some(import().ok_or_else(|| invalid_pragma(pragma)))
+}
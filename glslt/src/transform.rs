@@ -4,23 +4,42 @@ use glsl_lang::ast::*;
 
 use crate::Result;
 
+mod alias;
+
+mod dispatch;
+
 mod fn_ref;
 pub use fn_ref::*;
 
 mod global_scope;
 pub(crate) use global_scope::*;
 
+mod inline;
+
 mod instantiate;
 
+mod interface;
+pub use interface::Interface;
+
 mod local_scope;
 pub(crate) use local_scope::*;
 
 mod min_unit;
 pub use min_unit::*;
 
+mod namespace;
+
+mod overload;
+
+mod pack;
+
+mod private;
+
 mod scope;
 pub(crate) use scope::*;
 
+mod shadow;
+
 pub mod template;
 
 mod transform_unit;
@@ -29,12 +48,17 @@ pub use transform_unit::*;
 mod unit;
 pub use unit::*;
 
-use crate::TransformConfig;
+use crate::{pragma::SourceConfig, TransformConfig};
 
-fn transform_unit<'a, T: TransformUnit>(
+/// Collect the declarations of all the inputs into a transform unit, then parse them
+pub(crate) fn transform_unit<'a, T: TransformUnit>(
     asts: impl std::iter::Iterator<Item = &'a TranslationUnit>,
     inst: &mut T,
 ) -> Result<()> {
+    // Collect all the declarations first, so the input order doesn't matter
+    let asts: Vec<_> = asts.collect();
+    inst.collect_declarations(&asts)?;
+
     for ast in asts {
         // We clone all declarations since they all have somewhere to go
         for extdecl in ast.0.iter().cloned() {
@@ -47,6 +71,9 @@ fn transform_unit<'a, T: TransformUnit>(
 
 /// Transform a GLSLT AST to an instantiated GLSL AST
 ///
+/// If the input contains a `#pragma glslt minify` or `#pragma glslt keep` directive, this is
+/// equivalent to [transform_min] with no additional entry points.
+///
 /// # Parameters
 ///
 /// * `asts`: iterator of translation units to be concatenated and transformed
@@ -58,9 +85,14 @@ pub fn transform<'a>(
     asts: impl std::iter::Iterator<Item = &'a TranslationUnit>,
     config: TransformConfig,
 ) -> Result<TranslationUnit> {
+    let asts: Vec<_> = asts.collect();
+    if SourceConfig::from_translation_units(asts.iter().copied())?.minify {
+        return transform_min(asts.into_iter(), std::iter::empty(), config);
+    }
+
     let mut inst = Unit::with_config(config);
 
-    transform_unit(asts, &mut inst)?;
+    transform_unit(asts.into_iter(), &mut inst)?;
 
     inst.into_translation_unit()
 }
@@ -71,7 +103,8 @@ pub fn transform<'a>(
 /// # Parameters
 ///
 /// * `asts`: iterator of translation units to be concatenated and transformed
-/// * `wanted`: list of entry points to keep in the output
+/// * `wanted`: list of entry points to keep in the output, in addition to the ones listed by
+///   `#pragma glslt keep` directives
 ///
 /// # Errors
 ///
@@ -0,0 +1,74 @@
+//! Named lambda aliases
+
+use glsl_lang::ast::*;
+
+use indexmap::IndexMap;
+
+/// A named lambda expression, declared as a variable of a pointer type
+///
+/// ```glsl
+/// sdf3d bigSphere = sdSphere(_p / 2.) * 2.;
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct LambdaAlias {
+    /// Name of the pointer type of the alias
+    pub pointer_type: SmolStr,
+    /// Lambda expression the alias stands for
+    pub expr: Expr,
+}
+
+/// Extract the lambda aliases declared by a declarator list
+///
+/// # Parameters
+///
+/// * `idl`: declarator list to inspect
+/// * `pointer_types`: known pointer types
+///
+/// # Returns
+///
+/// The list of declared aliases, or `None` if this declarator list doesn't declare aliases, i.e.
+/// its type is not a pointer type or some declarators lack a lambda expression initializer.
+pub fn parse_lambda_aliases(
+    idl: &InitDeclaratorList,
+    pointer_types: &IndexMap<SmolStr, Vec<FunctionPrototype>>,
+) -> Option<Vec<(SmolStr, LambdaAlias)>> {
+    let pointer_type = match &*idl.head.ty.ty.ty {
+        TypeSpecifierNonArrayData::TypeName(tn) if pointer_types.contains_key(tn.0.as_str()) => {
+            tn.0.clone()
+        }
+        _ => return None,
+    };
+
+    let simple_initializer = |initializer: &Option<Initializer>| match initializer.as_deref() {
+        Some(InitializerData::Simple(expr)) => Some((**expr).clone()),
+        _ => None,
+    };
+
+    let mut aliases = Vec::with_capacity(1 + idl.tail.len());
+
+    if let Some(name) = &idl.head.name {
+        aliases.push((
+            name.0.clone(),
+            LambdaAlias {
+                pointer_type: pointer_type.clone(),
+                expr: simple_initializer(&idl.head.initializer)?,
+            },
+        ));
+    }
+
+    for tail in &idl.tail {
+        aliases.push((
+            tail.ident.ident.0.clone(),
+            LambdaAlias {
+                pointer_type: pointer_type.clone(),
+                expr: simple_initializer(&tail.initializer)?,
+            },
+        ));
+    }
+
+    if aliases.is_empty() {
+        None
+    } else {
+        Some(aliases)
+    }
+}
@@ -0,0 +1,318 @@
+//! Runtime dispatch over a table of functions
+
+use glsl_lang::ast::*;
+
+use indexmap::IndexMap;
+
+/// A table of candidate functions selected at runtime, declared as an array of a pointer type
+///
+/// ```glsl
+/// sdf3d shapes[] = { sdSphere, sdBox(_p, vec3(1.)) };
+/// ```
+///
+/// `shapes[i]` can then be passed to templates or called like a function: it stands for a call to
+/// a generated dispatcher, which selects the candidate to call with a `switch` on `i`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DispatchTable {
+    /// Name of the pointer type of the candidates
+    pub pointer_type: SmolStr,
+    /// Candidate functions or lambda expressions, in selector order
+    pub candidates: Vec<Expr>,
+}
+
+impl DispatchTable {
+    /// Build the call to the dispatcher of this table which stands for `table[selector](args)`
+    ///
+    /// # Parameters
+    ///
+    /// * `dispatcher`: name of the dispatcher template
+    /// * `selector`: index of the candidate to call
+    /// * `args`: arguments of the candidate call
+    pub fn dispatch_call(&self, dispatcher: &SmolStr, selector: Expr, args: Vec<Expr>) -> Expr {
+        let span = selector.span;
+
+        let mut call_args = self.candidates.clone();
+        call_args.push(selector);
+        call_args.extend(args);
+
+        Node::new(
+            ExprData::FunCall(
+                FunIdentifierData::Expr(Box::new(variable(dispatcher.clone()))).into_node(),
+                call_args,
+            ),
+            span,
+        )
+    }
+}
+
+/// Extract the dispatch tables declared by a declarator list
+///
+/// # Parameters
+///
+/// * `idl`: declarator list to inspect
+/// * `pointer_types`: known pointer types
+///
+/// # Returns
+///
+/// The list of declared tables, or `None` if this declarator list doesn't declare dispatch tables,
+/// i.e. its type is not a pointer type or some declarators lack a list initializer of
+/// candidates.
+pub fn parse_dispatch_tables(
+    idl: &InitDeclaratorList,
+    pointer_types: &IndexMap<SmolStr, Vec<FunctionPrototype>>,
+) -> Option<Vec<(SmolStr, DispatchTable)>> {
+    let pointer_type = match &*idl.head.ty.ty.ty {
+        TypeSpecifierNonArrayData::TypeName(tn) if pointer_types.contains_key(tn.0.as_str()) => {
+            tn.0.clone()
+        }
+        _ => return None,
+    };
+
+    let candidates = |initializer: &Option<Initializer>| match initializer.as_deref() {
+        Some(InitializerData::List(list)) if !list.is_empty() => list
+            .iter()
+            .map(|candidate| match &**candidate {
+                InitializerData::Simple(expr) => Some((**expr).clone()),
+                InitializerData::List(_) => None,
+            })
+            .collect(),
+        _ => None,
+    };
+
+    let mut tables = Vec::with_capacity(1 + idl.tail.len());
+
+    if let Some(name) = &idl.head.name {
+        tables.push((
+            name.0.clone(),
+            DispatchTable {
+                pointer_type: pointer_type.clone(),
+                candidates: candidates(&idl.head.initializer)?,
+            },
+        ));
+    }
+
+    for tail in &idl.tail {
+        tables.push((
+            tail.ident.ident.0.clone(),
+            DispatchTable {
+                pointer_type: pointer_type.clone(),
+                candidates: candidates(&tail.initializer)?,
+            },
+        ));
+    }
+
+    if tables.is_empty() {
+        None
+    } else {
+        Some(tables)
+    }
+}
+
+/// Return the number of arguments a call through a pointer type forwards to its candidates
+///
+/// This is the number of parameters of the pointer type, except for `void` parameter lists.
+pub fn forwarded_parameter_count(pointer_type: &FunctionPrototype) -> usize {
+    pointer_type
+        .parameters
+        .iter()
+        .filter(|parameter| !is_void(parameter))
+        .count()
+}
+
+/// Return the name of the dispatcher over `count` candidates of a pointer type
+///
+/// Dispatchers for the overloads of a pointer type are overloads of the same template. The name
+/// is prefixed so it can't collide with user functions.
+///
+/// # Parameters
+///
+/// * `prefix`: identifier prefix for generated code
+/// * `pointer_type`: name of the pointer type of the candidates
+/// * `count`: number of candidates
+pub fn dispatcher_name(prefix: &str, pointer_type: &str, count: usize) -> SmolStr {
+    format!("{}dispatch_{}_{}", prefix, pointer_type, count).into()
+}
+
+/// Generate the definition of a dispatcher template
+///
+/// For a pointer type `float sdf3d(vec3 p)` and two candidates, this generates:
+///
+/// ```glsl
+/// float _glslt_dispatch_sdf3d_2(in sdf3d _glslt_c0, in sdf3d _glslt_c1, int _glslt_selector, vec3 p) {
+///     switch (_glslt_selector) {
+///     case 0:
+///         return _glslt_c0(p);
+///     default:
+///         return _glslt_c1(p);
+///     }
+/// }
+/// ```
+///
+/// Out of range selectors call the last candidate.
+///
+/// # Parameters
+///
+/// * `prefix`: identifier prefix for generated code
+/// * `name`: name of the dispatcher, see [dispatcher_name]
+/// * `pointer_type`: prototype of the pointer type of the candidates
+/// * `count`: number of candidates
+pub fn dispatcher_definition(
+    prefix: &str,
+    name: SmolStr,
+    pointer_type: &FunctionPrototype,
+    count: usize,
+) -> FunctionDefinition {
+    let candidate = |i: usize| SmolStr::from(format!("{}c{}", prefix, i));
+    let selector = SmolStr::from(format!("{}selector", prefix));
+
+    let mut parameters = Vec::with_capacity(count + 1 + pointer_type.parameters.len());
+
+    // Candidates, as template parameters
+    for i in 0..count {
+        parameters.push(named_parameter(
+            Some(
+                TypeQualifierData {
+                    qualifiers: vec![TypeQualifierSpecData::Storage(
+                        StorageQualifierData::In.into_node(),
+                    )
+                    .into_node()],
+                }
+                .into_node(),
+            ),
+            TypeSpecifierData {
+                ty: TypeSpecifierNonArrayData::TypeName(
+                    TypeNameData(pointer_type.name.0.clone()).into_node(),
+                )
+                .into_node(),
+                array_specifier: None,
+            }
+            .into_node(),
+            candidate(i),
+            None,
+        ));
+    }
+
+    parameters.push(named_parameter(
+        None,
+        TypeSpecifierData {
+            ty: TypeSpecifierNonArrayData::Int.into_node(),
+            array_specifier: None,
+        }
+        .into_node(),
+        selector.clone(),
+        None,
+    ));
+
+    // Parameters forwarded to the candidates, named if the pointer type doesn't name them
+    let mut arguments = Vec::with_capacity(pointer_type.parameters.len());
+    for (i, parameter) in pointer_type.parameters.iter().enumerate() {
+        // `void` parameter lists have no parameters to forward
+        if is_void(parameter) {
+            continue;
+        }
+
+        let (qualifier, ty, name, array_spec) = match &**parameter {
+            FunctionParameterDeclarationData::Named(qualifier, declarator) => (
+                qualifier.clone(),
+                declarator.ty.clone(),
+                declarator.ident.ident.0.clone(),
+                declarator.ident.array_spec.clone(),
+            ),
+            FunctionParameterDeclarationData::Unnamed(qualifier, ty) => (
+                qualifier.clone(),
+                ty.clone(),
+                SmolStr::from(format!("{}a{}", prefix, i)),
+                None,
+            ),
+        };
+
+        arguments.push(variable(name.clone()));
+        parameters.push(named_parameter(qualifier, ty, name, array_spec));
+    }
+
+    // One case per candidate, the last one being the default
+    let returns = !matches!(*pointer_type.ty.ty.ty, TypeSpecifierNonArrayData::Void);
+    let mut body = Vec::with_capacity(3 * count);
+    for i in 0..count {
+        let label = if i + 1 == count {
+            CaseLabelData::Def
+        } else {
+            CaseLabelData::Case(Box::new(ExprData::IntConst(i as _).into_node()))
+        };
+
+        body.push(StatementData::CaseLabel(label.into_node()).into_node());
+
+        let call = ExprData::FunCall(
+            FunIdentifierData::Expr(Box::new(variable(candidate(i)))).into_node(),
+            arguments.clone(),
+        )
+        .into_node();
+
+        if returns {
+            body.push(
+                StatementData::Jump(JumpStatementData::Return(Some(Box::new(call))).into_node())
+                    .into_node(),
+            );
+        } else {
+            body.push(
+                StatementData::Expression(ExprStatementData(Some(call)).into_node()).into_node(),
+            );
+            body.push(StatementData::Jump(JumpStatementData::Break.into_node()).into_node());
+        }
+    }
+
+    FunctionDefinitionData {
+        prototype: FunctionPrototypeData {
+            ty: pointer_type.ty.clone(),
+            name: IdentifierData(name).into_node(),
+            parameters,
+        }
+        .into_node(),
+        statement: CompoundStatementData {
+            statement_list: vec![StatementData::Switch(
+                SwitchStatementData {
+                    head: Box::new(variable(selector)),
+                    body,
+                }
+                .into_node(),
+            )
+            .into_node()],
+        }
+        .into_node(),
+    }
+    .into_node()
+}
+
+fn is_void(parameter: &FunctionParameterDeclaration) -> bool {
+    let ty = match &**parameter {
+        FunctionParameterDeclarationData::Named(_, declarator) => &declarator.ty,
+        FunctionParameterDeclarationData::Unnamed(_, ty) => ty,
+    };
+
+    matches!(*ty.ty, TypeSpecifierNonArrayData::Void)
+}
+
+fn variable(name: SmolStr) -> Expr {
+    ExprData::Variable(IdentifierData(name).into_node()).into_node()
+}
+
+fn named_parameter(
+    qualifier: Option<TypeQualifier>,
+    ty: TypeSpecifier,
+    name: SmolStr,
+    array_spec: Option<ArraySpecifier>,
+) -> FunctionParameterDeclaration {
+    FunctionParameterDeclarationData::Named(
+        qualifier,
+        FunctionParameterDeclaratorData {
+            ty,
+            ident: ArrayedIdentifierData {
+                ident: IdentifierData(name).into_node(),
+                array_spec,
+            }
+            .into_node(),
+        }
+        .into_node(),
+    )
+    .into_node()
+}
@@ -1,14 +1,23 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use glsl_lang::ast::*;
 
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
 
+use super::alias::{parse_lambda_aliases, LambdaAlias};
+use super::dispatch::{
+    dispatcher_definition, dispatcher_name, parse_dispatch_tables, DispatchTable,
+};
+use super::interface::{parse_interface, Interface};
 use super::template::{TemplateDefinition, TryTemplate};
 use super::{ResolvedArgument, ResolvedArgumentExpr, Scope};
 
-use crate::{Error, Result, TransformConfig};
+use crate::{
+    glsl_ext::{FunctionParameterDeclarationExt, FunctionPrototypeExt, TypeQualifierExt},
+    pragma::{glslt_pragma, parse_namespace_directive, NamespaceDirective, SourceConfig},
+    Error, Result, TransformConfig,
+};
 
 /// Result of parsing an ExternalDeclaration
 pub enum ParsedDeclaration {
@@ -16,23 +25,63 @@ pub enum ParsedDeclaration {
     ConsumedAsType,
     /// The declaration was a function and was merged into the global scope as a template
     ConsumedAsTemplate(Arc<TemplateDefinition>),
+    /// The declaration was a `#pragma glslt` configuration directive
+    ConsumedAsPragma,
     /// The declaration was something else and is to be processed by the caller
     Unparsed(Arc<ExternalDeclaration>),
 }
 
+/// Global variable declared at the top level of a shader
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlobalVariable {
+    /// `true` if the variable can't be written to, such as a uniform
+    pub read_only: bool,
+}
+
 /// GLSLT template definition global scope
 #[derive(Default, Debug, Clone)]
 pub struct GlobalScope {
     /// Transform config
     config: TransformConfig,
-    /// Known pointer types
-    declared_pointer_types: IndexMap<SmolStr, FunctionPrototype>,
-    /// Known GLSLT template functions
-    declared_templates: IndexMap<SmolStr, Arc<TemplateDefinition>>,
-    /// Identifiers of function declarations
-    known_functions: IndexMap<SmolStr, FunctionPrototype>,
+    /// Configuration read from the `#pragma glslt` directives of the input
+    source_config: SourceConfig,
+    /// Known pointer types, with all their overloads
+    declared_pointer_types: IndexMap<SmolStr, Vec<FunctionPrototype>>,
+    /// Known type template parameters
+    declared_type_parameters: IndexSet<SmolStr>,
+    /// Known interfaces, i.e. structs of function pointers
+    declared_interfaces: IndexMap<SmolStr, Interface>,
+    /// Prototypes kept as forward declarations, which become pointer types if they are used as
+    /// one. Only used with [TransformConfig::preserve_forward_declarations].
+    forward_declarations: IndexMap<SmolStr, Vec<FunctionPrototype>>,
+    /// Known GLSLT template functions, with all their overloads
+    declared_templates: IndexMap<SmolStr, Vec<Arc<TemplateDefinition>>>,
+    /// Explicit specializations of templates, by template name
+    declared_specializations: IndexMap<SmolStr, Vec<Arc<TemplateDefinition>>>,
+    /// Lambda aliases declared at the top level
+    declared_aliases: IndexMap<SmolStr, LambdaAlias>,
+    /// Dispatch tables declared at the top level
+    declared_dispatch_tables: IndexMap<SmolStr, DispatchTable>,
+    /// Locations of the functions and structs defined in the input, by name and signature (`None`
+    /// for structs)
+    defined_symbols: HashMap<(SmolStr, Option<SmolStr>), Option<NodeSpan>>,
+    /// Output names of the private functions, by source file and name
+    private_functions: HashMap<FileId, HashMap<SmolStr, SmolStr>>,
+    /// Output names of the functions, pointer types and structs declared in namespaces, by
+    /// namespace path and name
+    namespaces: HashMap<SmolStr, HashMap<SmolStr, SmolStr>>,
+    /// Paths of the namespaces enclosing the declarations being parsed, innermost last
+    namespace_stack: Vec<SmolStr>,
+    /// Global variables, including uniforms and fields of unnamed interface blocks
+    global_variables: HashMap<SmolStr, GlobalVariable>,
+    /// Known functions, with all their overloads
+    known_functions: IndexMap<SmolStr, Vec<FunctionPrototype>>,
     /// Identifiers of already instantiated templates
     instantiated_templates: HashSet<SmolStr>,
+    /// Instances which were identical to a previous instance, mapped to the name of that instance
+    instance_aliases: HashMap<SmolStr, SmolStr>,
+    /// Names of emitted instances, indexed by the identifier of their body
+    instance_bodies: HashMap<String, SmolStr>,
     /// Pending external declarations
     instanced_templates: Vec<FunctionDefinition>,
 }
@@ -52,78 +101,569 @@ impl GlobalScope {
     }
 
     fn parse_function_prototype(&mut self, prototype: FunctionPrototype) -> Result<()> {
-        if let Some(previous) = self.declared_pointer_types.get(&prototype.name.0) {
-            // Since function pointer types are raw identifiers, they have to be unique
-            return Err(Error::new_duplicate_pointer_definition(
-                &prototype.name,
-                previous,
-            ));
+        let overloads = self
+            .declared_pointer_types
+            .entry(prototype.name.0.clone())
+            .or_default();
+
+        if let Some(previous) = overloads
+            .iter()
+            .find(|previous| previous.same_parameter_types(&prototype))
+        {
+            if previous.ty.ty != prototype.ty.ty {
+                // Overloads can't only differ by their return type
+                return Err(Error::new_duplicate_pointer_definition(
+                    &prototype.name,
+                    previous,
+                ));
+            }
+
+            debug!("redeclared pointer: {}", prototype.name.0);
         } else {
             info!("declared pointer: {}", prototype.name.0);
 
-            self.declared_pointer_types
-                .insert(prototype.name.0.clone(), prototype);
+            overloads.push(prototype);
         }
 
         Ok(())
     }
 
     fn parse_declaration(&mut self, decl: Declaration) -> Result<ParsedDeclaration> {
+        if let DeclarationData::InitDeclaratorList(idl) = &decl.content {
+            // Variables can't have a function type, so this can only be a lambda alias
+            self.promote_forward_declarations(std::iter::once(&idl.head.ty.ty))?;
+
+            // Likewise, struct fields with a function type make the struct an interface
+            if let TypeSpecifierNonArrayData::Struct(st) = &*idl.head.ty.ty.ty {
+                self.promote_forward_declarations(st.fields.iter().map(|field| &field.ty))?;
+            }
+
+            // A pointer-typed variable initialized with a lambda is an alias for it
+            if let Some(aliases) = parse_lambda_aliases(idl, &self.declared_pointer_types) {
+                for (name, alias) in aliases {
+                    info!("declared lambda alias: {}", name);
+                    self.declared_aliases.insert(name, alias);
+                }
+
+                return Ok(ParsedDeclaration::ConsumedAsType);
+            }
+
+            // A struct of function pointers groups template parameters into an interface
+            if let Some((name, interface)) = parse_interface(idl, &self.declared_pointer_types) {
+                info!("declared interface: {}", name);
+                self.declared_interfaces.insert(name, interface);
+
+                return Ok(ParsedDeclaration::ConsumedAsType);
+            }
+
+            // A pointer-typed array initialized with a list of candidates is a dispatch table
+            if let Some(tables) = parse_dispatch_tables(idl, &self.declared_pointer_types) {
+                for (name, table) in tables {
+                    info!("declared dispatch table: {}", name);
+                    self.declared_dispatch_tables.insert(name, table);
+                }
+
+                return Ok(ParsedDeclaration::ConsumedAsType);
+            }
+        }
+
         match decl.content {
+            DeclarationData::FunctionPrototype(prototype) if prototype.is_type_parameter() => {
+                // layout(typename) void T(); declares a type templates can be generic over
+                info!("declared type parameter: {}", prototype.name.0);
+                self.declared_type_parameters
+                    .insert(prototype.name.0.clone());
+                Ok(ParsedDeclaration::ConsumedAsType)
+            }
+            DeclarationData::FunctionPrototype(prototype)
+                if self.config.preserve_forward_declarations
+                    && !prototype.is_pointer_type()
+                    && !self.declared_pointer_types.contains_key(&prototype.name.0) =>
+            {
+                // This may be a forward declaration, until it is used as a pointer type
+                debug!("declared prototype: {}", prototype.name.0);
+                self.register_function(&prototype, false);
+                self.forward_declarations
+                    .entry(prototype.name.0.clone())
+                    .or_default()
+                    .push(prototype.clone());
+
+                Ok(ParsedDeclaration::Unparsed(Arc::new(
+                    ExternalDeclaration::new(
+                        ExternalDeclarationData::Declaration(Declaration::new(
+                            DeclarationData::FunctionPrototype(prototype),
+                            decl.span,
+                        )),
+                        decl.span,
+                    ),
+                )))
+            }
             DeclarationData::FunctionPrototype(prototype) => {
                 // A function prototype is what we'll call a function pointer type
                 self.parse_function_prototype(prototype)?;
                 Ok(ParsedDeclaration::ConsumedAsType)
             }
-            other => Ok(ParsedDeclaration::Unparsed(Arc::new(
-                ExternalDeclaration::new(
-                    ExternalDeclarationData::Declaration(Declaration::new(other, decl.span)),
-                    decl.span,
-                ),
-            ))),
+            other => {
+                self.register_global_variables(&other);
+
+                Ok(ParsedDeclaration::Unparsed(Arc::new(
+                    ExternalDeclaration::new(
+                        ExternalDeclarationData::Declaration(Declaration::new(other, decl.span)),
+                        decl.span,
+                    ),
+                )))
+            }
+        }
+    }
+
+    /// Turn the forward declarations of the given types into pointer types
+    fn promote_forward_declarations<'t>(
+        &mut self,
+        types: impl Iterator<Item = &'t TypeSpecifier>,
+    ) -> Result<()> {
+        for ty in types {
+            if let TypeSpecifierNonArrayData::TypeName(tn) = &*ty.ty {
+                if let Some(prototypes) = self.forward_declarations.shift_remove(&tn.0) {
+                    info!("forward declaration used as pointer: {}", tn.0);
+                    self.known_functions.shift_remove(&tn.0);
+
+                    for prototype in prototypes {
+                        self.parse_function_prototype(prototype)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Return `true` if a prototype was kept as a forward declaration and later used as a pointer
+    /// type, so it must be removed from the output
+    pub fn is_promoted_prototype(&self, prototype: &FunctionPrototype) -> bool {
+        self.config.preserve_forward_declarations
+            && self.declared_pointer_types.contains_key(&prototype.name.0)
+    }
+
+    /// Record the definition of a function or struct
+    ///
+    /// Definitions with the same name and signature are reported as duplicates, whether they come
+    /// from the same file, from different files or from the same file included twice. Definitions
+    /// without a location can't be told apart, so the last one replaces the previous ones.
+    fn define_symbol(
+        &mut self,
+        name: &SmolStr,
+        signature: Option<SmolStr>,
+        span: Option<NodeSpan>,
+    ) -> Result<()> {
+        if let Some(previous_span) = self.defined_symbols.insert((name.clone(), signature), span) {
+            if previous_span.is_some() && span.is_some() {
+                return Err(Error::DuplicateDefinition {
+                    name: name.clone(),
+                    span,
+                    previous_span,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn register_global_variables(&mut self, decl: &DeclarationData) {
+        let (names, qualifier): (Vec<_>, _) = match decl {
+            DeclarationData::InitDeclaratorList(idl) => (
+                idl.head
+                    .name
+                    .iter()
+                    .chain(idl.tail.iter().map(|tail| &tail.ident.ident))
+                    .collect(),
+                idl.head.ty.qualifier.as_ref(),
+            ),
+            DeclarationData::Block(block) => (
+                if let Some(identifier) = &block.identifier {
+                    vec![&identifier.ident]
+                } else {
+                    // Fields of blocks without an instance name are global variables
+                    block
+                        .fields
+                        .iter()
+                        .flat_map(|field| field.identifiers.iter())
+                        .map(|ident| &ident.ident)
+                        .collect()
+                },
+                Some(&block.qualifier),
+            ),
+            _ => return,
+        };
+
+        let variable = GlobalVariable {
+            read_only: qualifier.is_some_and(|q| q.is_read_only()),
+        };
+
+        for name in names {
+            self.global_variables.insert(name.0.clone(), variable);
         }
     }
 
     fn parse_function_definition(&mut self, def: FunctionDefinition) -> Result<ParsedDeclaration> {
         let span = def.span;
 
-        // A function definition is a template if any of its arguments is a pointer
+        // Forward declarations used as parameter types are pointer types, while a definition
+        // with the same name confirms it was a genuine forward declaration
+        self.promote_forward_declarations(
+            def.prototype
+                .parameters
+                .iter()
+                .map(|parameter| parameter.parameter_type().0),
+        )?;
+        self.forward_declarations
+            .shift_remove(&def.prototype.name.0);
+
+        // A function definition is a template if any of its arguments is a pointer, or if its
+        // prototype uses a type template parameter
         let name = def.prototype.name.0.clone();
-        let template =
-            super::template::parse_definition_as_template(def, &self.declared_pointer_types)?;
+        let template = super::template::parse_definition_as_specialization(
+            def,
+            &self.declared_pointer_types,
+            &self.declared_type_parameters,
+            &self.declared_interfaces,
+            self.declared_templates
+                .get(&name)
+                .map(Vec::as_slice)
+                .unwrap_or_default(),
+            &self.known_functions,
+        )?;
 
         match template {
+            TryTemplate::Template(template) if template.is_specialization() => {
+                info!(
+                    "declared specialization: {}",
+                    template.ast().prototype.name.0
+                );
+
+                // A specialization with the same parameters replaces the previous definition
+                let template = Arc::new(*template);
+                let specializations = self.declared_specializations.entry(name).or_default();
+                if let Some(index) = specializations
+                    .iter()
+                    .position(|spec| spec.raw_prototype() == template.raw_prototype())
+                {
+                    specializations[index] = template.clone();
+                } else {
+                    specializations.push(template.clone());
+                }
+
+                Ok(ParsedDeclaration::ConsumedAsTemplate(template))
+            }
             TryTemplate::Template(template) => {
                 info!("declared template: {}", template.ast().prototype.name.0);
 
                 // We found a template parameter, so it's a template function
-                self.declared_templates
-                    .insert(name.clone(), Arc::new(*template));
+                let overloads = self.declared_templates.entry(name).or_default();
+
+                // A template with the same parameters replaces the previous definition
+                let template = if let Some(index) = overloads
+                    .iter()
+                    .position(|overload| overload.same_signature(&template))
+                {
+                    let template = Arc::new((*template).with_overload(index));
+                    overloads[index] = template.clone();
+                    template
+                } else {
+                    let template = Arc::new((*template).with_overload(overloads.len()));
+                    overloads.push(template.clone());
+                    template
+                };
+
+                Ok(ParsedDeclaration::ConsumedAsTemplate(template))
+            }
+            TryTemplate::Function(def) => {
+                self.define_symbol(&def.prototype.name.0, Some(def.prototype.signature()), span)?;
+
+                Ok(ParsedDeclaration::Unparsed(Arc::new(
+                    ExternalDeclaration::new(
+                        ExternalDeclarationData::FunctionDefinition(*def),
+                        span,
+                    ),
+                )))
+            }
+        }
+    }
 
-                let parsed = self.declared_templates.get(&name).unwrap();
-                Ok(ParsedDeclaration::ConsumedAsTemplate(parsed.clone()))
+    /// Register the pointer types, templates and functions of a set of declarations
+    ///
+    /// Declarations are only collected, nothing is instantiated or returned. Running this over all
+    /// the inputs before parsing them with
+    /// [parse_external_declaration](Self::parse_external_declaration) makes the transform
+    /// independent of the declaration order: templates can be used before the pointer types of
+    /// their parameters are declared, and functions can be used as template arguments before
+    /// their definition.
+    ///
+    /// # Parameters
+    ///
+    /// * `decls`: declarations to collect, in input order
+    pub fn collect_declarations<'a>(
+        &mut self,
+        decls: impl Iterator<Item = &'a ExternalDeclaration>,
+    ) -> Result<()> {
+        // Configuration directives apply to the whole input, and namespaced names are known
+        // before anything refers to them
+        let decls: Vec<_> = decls.collect();
+        let namespace_stack = self.namespace_stack.clone();
+        for decl in &decls {
+            if !self.parse_namespace_directive(decl)? {
+                self.source_config.parse_external_declaration(decl)?;
+                self.register_namespace_names(decl);
+            }
+        }
+
+        self.namespace_stack = namespace_stack.clone();
+        self.source_config.apply(&mut self.config);
+
+        // Private functions are renamed before anything refers to them
+        for decl in &decls {
+            self.register_private_function(decl);
+        }
+
+        let mut renamed = Vec::with_capacity(decls.len());
+        for decl in decls {
+            if !self.parse_namespace_directive(decl)? {
+                let mut decl = decl.clone();
+                self.rename_private_functions(&mut decl);
+                self.rename_namespaced_names(&mut decl)?;
+                renamed.push(decl);
             }
-            TryTemplate::Function(def) => Ok(ParsedDeclaration::Unparsed(Arc::new(
-                ExternalDeclaration::new(ExternalDeclarationData::FunctionDefinition(*def), span),
-            ))),
         }
+
+        self.namespace_stack = namespace_stack;
+        let decls = renamed;
+
+        let mut prototypes = Vec::new();
+        let mut variables = Vec::new();
+        let mut definitions = Vec::new();
+
+        for decl in &decls {
+            match &decl.content {
+                ExternalDeclarationData::Declaration(decl) => match &decl.content {
+                    DeclarationData::FunctionPrototype(prototype) => prototypes.push(prototype),
+                    DeclarationData::InitDeclaratorList(idl) => variables.push(idl),
+                    _ => {}
+                },
+                ExternalDeclarationData::FunctionDefinition(def) => definitions.push(def),
+                _ => {}
+            }
+        }
+
+        // Pointer types and type parameters, which decide which definitions are templates
+        for prototype in prototypes {
+            self.parse_declaration(Declaration::new(
+                DeclarationData::FunctionPrototype(prototype.clone()),
+                prototype.span,
+            ))?;
+        }
+
+        // Forward declarations used as pointer types anywhere in the input
+        for def in &definitions {
+            self.promote_forward_declarations(
+                def.prototype
+                    .parameters
+                    .iter()
+                    .map(|parameter| parameter.parameter_type().0),
+            )?;
+        }
+
+        for idl in variables {
+            self.parse_declaration(Declaration::new(
+                DeclarationData::InitDeclaratorList(idl.clone()),
+                idl.span,
+            ))?;
+        }
+
+        // Functions, which decide which template parameters are specialized
+        let mut templates = Vec::new();
+        for def in definitions {
+            match super::template::parse_definition_as_template(
+                def.clone(),
+                &self.declared_pointer_types,
+                &self.declared_type_parameters,
+                &self.declared_interfaces,
+            )? {
+                TryTemplate::Function(def) => {
+                    self.forward_declarations
+                        .shift_remove(&def.prototype.name.0);
+                    self.register_function(&def.prototype, true);
+                }
+                TryTemplate::Template(_) => templates.push(def),
+            }
+        }
+
+        // Generic templates first, so their specializations can be recognized
+        let mut specializations = Vec::new();
+        for def in templates {
+            if super::template::is_specialization_definition(
+                def,
+                &self.declared_pointer_types,
+                &self.declared_type_parameters,
+                &self.declared_interfaces,
+                &self.known_functions,
+            )? {
+                specializations.push(def);
+            } else {
+                self.parse_function_definition(def.clone())?;
+            }
+        }
+
+        for def in specializations {
+            self.parse_function_definition(def.clone())?;
+        }
+
+        Ok(())
     }
 
-    /// Get the list of defined function identifiers in this global scope
-    pub fn known_functions_mut(&mut self) -> &mut IndexMap<SmolStr, FunctionPrototype> {
-        &mut self.known_functions
+    /// Register a function overload, keeping the other overloads with the same name
+    ///
+    /// # Parameters
+    ///
+    /// * `prototype`: prototype of the function
+    /// * `replace`: `true` if this prototype should replace a previous declaration of the same
+    ///   overload
+    fn register_function(&mut self, prototype: &FunctionPrototype, replace: bool) {
+        let overloads = self
+            .known_functions
+            .entry(prototype.name.0.clone())
+            .or_default();
+
+        if let Some(previous) = overloads
+            .iter_mut()
+            .find(|previous| previous.same_parameter_types(prototype))
+        {
+            if replace {
+                *previous = prototype.clone();
+            }
+        } else {
+            overloads.push(prototype.clone());
+        }
+    }
+
+    /// Get the list of known functions in this global scope, with all their overloads
+    pub fn known_functions(&self) -> &IndexMap<SmolStr, Vec<FunctionPrototype>> {
+        &self.known_functions
+    }
+
+    fn register_private_function(&mut self, extdecl: &ExternalDeclaration) {
+        if let Some(name) = super::private::parse_private_function(extdecl) {
+            let file = super::private::declaration_file(extdecl.span);
+            let private_name = super::private::private_name(&self.config.prefix, file, name);
+
+            info!("declared private function: {} as {}", name, private_name);
+            self.private_functions
+                .entry(file)
+                .or_default()
+                .insert(name.clone(), private_name);
+        }
+    }
+
+    /// Rename the private functions defined and used by a declaration
+    ///
+    /// Functions marked with `layout(private)` are only visible from their own file, so they are
+    /// renamed with a prefix unique to that file.
+    ///
+    /// # Parameters
+    ///
+    /// * `extdecl`: declaration to rename private functions in
+    pub fn rename_private_functions(&self, extdecl: &mut ExternalDeclaration) {
+        let file = super::private::declaration_file(extdecl.span);
+        if let Some(names) = self.private_functions.get(&file) {
+            super::private::rename_private_functions(extdecl, names);
+        }
+    }
+
+    /// Enter or leave a namespace if the declaration is a namespace directive
+    ///
+    /// Returns `true` if the declaration was a namespace directive.
+    fn parse_namespace_directive(&mut self, extdecl: &ExternalDeclaration) -> Result<bool> {
+        match parse_namespace_directive(extdecl).transpose()? {
+            Some(NamespaceDirective::Begin(name)) => {
+                let path = super::namespace::namespace_path(self.namespace_stack.last(), &name);
+                self.namespaces.entry(path.clone()).or_default();
+                self.namespace_stack.push(path);
+            }
+            Some(NamespaceDirective::End) => {
+                if self.namespace_stack.pop().is_none() {
+                    return Err(Error::InvalidPragma {
+                        command: glslt_pragma(extdecl)
+                            .map(|pragma| pragma.command.clone())
+                            .unwrap_or_default(),
+                        span: extdecl.span,
+                    });
+                }
+            }
+            None => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    fn register_namespace_names(&mut self, extdecl: &ExternalDeclaration) {
+        if let Some(namespace) = self.namespace_stack.last() {
+            let names = self.namespaces.entry(namespace.clone()).or_default();
+            for name in super::namespace::declared_names(extdecl) {
+                let output_name = super::namespace::namespaced_name(namespace, name);
+                info!(
+                    "declared {} in namespace {} as {}",
+                    name, namespace, output_name
+                );
+                names.insert(name.clone(), output_name);
+            }
+        }
+    }
+
+    /// Rename the namespaced names defined and used by a declaration
+    ///
+    /// Names declared in a `#pragma glslt namespace` block are prefixed with the path of their
+    /// namespace, and qualified names written `namespace.name` are replaced with the prefixed
+    /// names.
+    ///
+    /// # Parameters
+    ///
+    /// * `extdecl`: declaration to rename namespaced names in
+    ///
+    /// # Errors
+    ///
+    /// Return [Error::UnknownQualifiedName] if a qualified name isn't declared in its namespace.
+    pub fn rename_namespaced_names(&self, extdecl: &mut ExternalDeclaration) -> Result<()> {
+        super::namespace::rename_namespaced_names(
+            extdecl,
+            self.namespace_stack.last(),
+            &self.namespaces,
+            &|name| self.global_variables.contains_key(name),
+        )
+    }
+
+    /// Get the configuration read from the `#pragma glslt` directives of the input
+    pub fn source_config(&self) -> &SourceConfig {
+        &self.source_config
     }
 
     /// Get the list of defined templates in this global scope
-    pub fn declared_templates(&self) -> &IndexMap<SmolStr, Arc<TemplateDefinition>> {
+    pub fn declared_templates(&self) -> &IndexMap<SmolStr, Vec<Arc<TemplateDefinition>>> {
         &self.declared_templates
     }
 
+    /// Get the list of explicit template specializations in this global scope
+    pub fn declared_specializations(&self) -> &IndexMap<SmolStr, Vec<Arc<TemplateDefinition>>> {
+        &self.declared_specializations
+    }
+
     /// Get the list of defined pointer types in this global scope
-    pub fn declared_pointer_types(&self) -> &IndexMap<SmolStr, FunctionPrototype> {
+    pub fn declared_pointer_types(&self) -> &IndexMap<SmolStr, Vec<FunctionPrototype>> {
         &self.declared_pointer_types
     }
 
+    /// Get the list of declared type template parameters in this global scope
+    pub fn declared_type_parameters(&self) -> &IndexSet<SmolStr> {
+        &self.declared_type_parameters
+    }
+
     /// Parse a top-level declaration from a GLSLT shader.
     ///
     /// If the declaration is a GLSLT definition, it will not be returned and stored as part of the
@@ -139,12 +679,38 @@ impl GlobalScope {
     /// if this declaration is not a template or needs to be instantiated in a global scope
     pub fn parse_external_declaration(
         &mut self,
-        extdecl: ExternalDeclaration,
+        mut extdecl: ExternalDeclaration,
     ) -> Result<ParsedDeclaration> {
         let span = extdecl.span;
 
+        if self.parse_namespace_directive(&extdecl)? {
+            return Ok(ParsedDeclaration::ConsumedAsPragma);
+        }
+
+        // Directives already read by collect_declarations are applied again, which is harmless,
+        // but this also makes them effective when the collection pass was skipped
+        if self.source_config.parse_external_declaration(&extdecl)? {
+            self.source_config.apply(&mut self.config);
+            return Ok(ParsedDeclaration::ConsumedAsPragma);
+        }
+
+        self.register_private_function(&extdecl);
+        self.register_namespace_names(&extdecl);
+        self.rename_private_functions(&mut extdecl);
+        self.rename_namespaced_names(&mut extdecl)?;
+
         match extdecl.content {
-            ExternalDeclarationData::Declaration(decl) => self.parse_declaration(decl),
+            ExternalDeclarationData::Declaration(decl) => {
+                if let DeclarationData::InitDeclaratorList(idl) = &decl.content {
+                    if let TypeSpecifierNonArrayData::Struct(ss) = &*idl.head.ty.ty.ty {
+                        if let Some(name) = &ss.name {
+                            self.define_symbol(&name.0, None, ss.span)?;
+                        }
+                    }
+                }
+
+                self.parse_declaration(decl)
+            }
             ExternalDeclarationData::FunctionDefinition(def) => {
                 Ok(self.parse_function_definition(def)?)
             }
@@ -164,9 +730,8 @@ impl GlobalScope {
     ///
     /// * `def`: function definition to register
     pub fn push_function_declaration(&mut self, def: &FunctionDefinition) {
-        // We discovered a new function
-        self.known_functions
-            .insert(def.prototype.name.0.clone(), def.prototype.clone());
+        // We discovered a new function, or a new overload of a known function
+        self.register_function(&def.prototype, true);
     }
 }
 
@@ -179,19 +744,67 @@ impl Scope for GlobalScope {
         None
     }
 
-    fn declared_pointer_types(&self) -> &IndexMap<SmolStr, FunctionPrototype> {
+    fn current_instance(&self) -> Option<(&str, &str)> {
+        None
+    }
+
+    fn declared_pointer_types(&self) -> &IndexMap<SmolStr, Vec<FunctionPrototype>> {
         &self.declared_pointer_types
     }
 
-    fn get_template(&self, template_name: &str) -> Option<Arc<TemplateDefinition>> {
-        self.declared_templates.get(template_name).cloned()
+    fn get_templates(&self, template_name: &str) -> &[Arc<TemplateDefinition>] {
+        self.declared_templates
+            .get(template_name)
+            .map(|overloads| &overloads[..])
+            .unwrap_or(&[])
     }
 
-    fn template_instance_declared(&self, template_name: &str) -> bool {
-        self.instantiated_templates.contains(template_name)
+    fn get_template_specializations(&self, template_name: &str) -> &[Arc<TemplateDefinition>] {
+        self.declared_specializations
+            .get(template_name)
+            .map(|specs| &specs[..])
+            .unwrap_or(&[])
     }
 
-    fn register_template_instance(&mut self, definitions: Vec<FunctionDefinition>) {
+    fn template_instance_name(&self, template_name: &str) -> Option<SmolStr> {
+        if let Some(canonical) = self.instance_aliases.get(template_name) {
+            Some(canonical.clone())
+        } else if self.instantiated_templates.contains(template_name) {
+            Some(template_name.into())
+        } else {
+            None
+        }
+    }
+
+    fn register_template_instance(
+        &mut self,
+        mut definitions: Vec<FunctionDefinition>,
+        body_id: String,
+    ) -> SmolStr {
+        let instance = definitions
+            .pop()
+            .expect("template instantiation should generate at least one function");
+
+        self.register_template_dependencies(definitions);
+
+        let instance_name = instance.prototype.name.0.clone();
+        if let Some(canonical) = self.instance_bodies.get(&body_id) {
+            // An identical instance already exists, call sites should use it instead
+            debug!("deduplicated {} as {}", instance_name, canonical);
+
+            self.instance_aliases
+                .insert(instance_name, canonical.clone());
+            return canonical.clone();
+        }
+
+        self.instance_bodies.insert(body_id, instance_name.clone());
+        self.instantiated_templates.insert(instance_name.clone());
+        self.instanced_templates.push(instance);
+
+        instance_name
+    }
+
+    fn register_template_dependencies(&mut self, definitions: Vec<FunctionDefinition>) {
         for template in definitions {
             let template_name = template.prototype.name.0.as_str();
 
@@ -210,12 +823,77 @@ impl Scope for GlobalScope {
     fn resolve_function_name(&self, name: &str) -> Option<ResolvedArgument> {
         self.known_functions
             .get(name)
+            .and_then(|overloads| overloads.first())
+            .or_else(|| {
+                // Instances which haven't been emitted yet are also known
+                self.instanced_templates
+                    .iter()
+                    .map(|def| &def.prototype)
+                    .find(|proto| proto.name.0 == name)
+            })
             .map(|proto| ResolvedArgument {
                 body: ResolvedArgumentExpr::FunctionName(proto.name.0.clone()),
                 pointer_type: proto,
             })
     }
 
+    fn get_function_overloads(&self, name: &str) -> &[FunctionPrototype] {
+        self.known_functions
+            .get(name)
+            .map(|overloads| &overloads[..])
+            .unwrap_or(&[])
+    }
+
+    fn get_lambda_alias(&self, name: &str) -> Option<&LambdaAlias> {
+        self.declared_aliases.get(name)
+    }
+
+    fn get_dispatch_table(&self, name: &str) -> Option<&DispatchTable> {
+        self.declared_dispatch_tables.get(name)
+    }
+
+    fn declare_dispatcher(
+        &mut self,
+        pointer_type: &FunctionPrototype,
+        count: usize,
+    ) -> Result<SmolStr> {
+        let name = dispatcher_name(&self.config.prefix, pointer_type.name.as_str(), count);
+        let definition =
+            dispatcher_definition(&self.config.prefix, name.clone(), pointer_type, count);
+
+        // Dispatchers for the overloads of a pointer type are overloads of the same template
+        let overloads = self.declared_templates.entry(name.clone()).or_default();
+        if overloads
+            .iter()
+            .any(|overload| overload.raw_prototype() == &definition.prototype)
+        {
+            return Ok(name);
+        }
+
+        match super::template::parse_definition_as_template(
+            definition,
+            &self.declared_pointer_types,
+            &self.declared_type_parameters,
+            &self.declared_interfaces,
+        )? {
+            TryTemplate::Template(template) => {
+                info!("declared dispatcher: {}", name);
+
+                let overloads = self.declared_templates.entry(name.clone()).or_default();
+                overloads.push(Arc::new(
+                    (*template).with_overload(overloads.len()).generated(),
+                ));
+            }
+            TryTemplate::Function(_) => unreachable!("dispatchers have template parameters"),
+        }
+
+        Ok(name)
+    }
+
+    fn get_global_variable(&self, name: &str) -> Option<&GlobalVariable> {
+        self.global_variables.get(name)
+    }
+
     fn transform_arg_call(
         &mut self,
         _expr: &mut Expr,
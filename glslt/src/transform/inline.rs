@@ -0,0 +1,230 @@
+//! Inlining of small template instances at their call sites
+
+use glsl_lang::{
+    ast::*,
+    visitor::{Host, HostMut, Visit, Visitor, VisitorMut},
+};
+
+use indexmap::IndexMap;
+
+use super::{instantiate::DeclaredSymbol, overload::infer_type, Scope};
+
+use crate::glsl_ext::FunctionParameterDeclarationExt;
+
+/// Return `true` if evaluating `expr` can't have any side effect
+fn is_side_effect_free(expr: &Expr) -> bool {
+    struct V {
+        pure: bool,
+    }
+
+    impl Visitor for V {
+        fn visit_expr(&mut self, e: &Expr) -> Visit {
+            match &**e {
+                ExprData::Assignment(_, _, _) | ExprData::PostInc(_) | ExprData::PostDec(_) => {
+                    self.pure = false;
+                }
+                ExprData::Unary(op, _) if matches!(&**op, UnaryOpData::Inc | UnaryOpData::Dec) => {
+                    self.pure = false;
+                }
+                // Only constructors are known not to have side effects
+                ExprData::FunCall(fun, _)
+                    if !matches!(&**fun, FunIdentifierData::TypeSpecifier(_)) =>
+                {
+                    self.pure = false;
+                }
+                _ => {}
+            }
+
+            if self.pure {
+                Visit::Children
+            } else {
+                Visit::Parent
+            }
+        }
+    }
+
+    let mut v = V { pure: true };
+    expr.visit(&mut v);
+    v.pure
+}
+
+/// Return `true` if `expr` assigns to, increments or decrements one of the `variables`
+fn writes_variable(expr: &Expr, variables: &IndexMap<SmolStr, &Expr>) -> bool {
+    struct V<'v> {
+        variables: &'v IndexMap<SmolStr, &'v Expr>,
+        writes: bool,
+    }
+
+    impl V<'_> {
+        fn is_variable(&self, mut target: &Expr) -> bool {
+            // Writing to a component or an element writes to the whole variable
+            loop {
+                match &**target {
+                    ExprData::Dot(e, _) | ExprData::Bracket(e, _) => target = e,
+                    ExprData::Variable(ident) => return self.variables.contains_key(&ident.0),
+                    _ => return false,
+                }
+            }
+        }
+    }
+
+    impl Visitor for V<'_> {
+        fn visit_expr(&mut self, e: &Expr) -> Visit {
+            match &**e {
+                ExprData::Assignment(target, _, _)
+                | ExprData::PostInc(target)
+                | ExprData::PostDec(target) => {
+                    self.writes |= self.is_variable(target);
+                }
+                ExprData::Unary(op, target)
+                    if matches!(&**op, UnaryOpData::Inc | UnaryOpData::Dec) =>
+                {
+                    self.writes |= self.is_variable(target);
+                }
+                _ => {}
+            }
+
+            if self.writes {
+                Visit::Parent
+            } else {
+                Visit::Children
+            }
+        }
+    }
+
+    let mut v = V {
+        variables,
+        writes: false,
+    };
+    expr.visit(&mut v);
+    v.writes
+}
+
+/// Return the number of expression nodes in `expr`
+fn expr_size(expr: &Expr) -> usize {
+    struct V {
+        size: usize,
+    }
+
+    impl Visitor for V {
+        fn visit_expr(&mut self, _: &Expr) -> Visit {
+            self.size += 1;
+            Visit::Children
+        }
+    }
+
+    let mut v = V { size: 0 };
+    expr.visit(&mut v);
+    v.size
+}
+
+/// Try to inline a template instance at its call site
+///
+/// The instance is inlined if its body is a single `return` statement smaller than `threshold`,
+/// if all the call arguments are free of side effects, if the returned expression doesn't write
+/// to its parameters and if its free variables are not shadowed by symbols of the calling
+/// function. The arguments and the returned expression must also be known to have the exact
+/// types of the parameters and of the return value, since implicit conversions would be lost.
+///
+/// # Parameters
+///
+/// * `def`: instantiated function definition
+/// * `args`: arguments of the call, including captured parameters
+/// * `symbol_table`: symbols declared by the calling function
+/// * `scope`: scope of the calling function
+/// * `threshold`: maximum number of expression nodes in the inlined expression
+///
+/// # Returns
+///
+/// The expression which should replace the call, or `None` if the instance can't be inlined.
+pub(crate) fn inline_instance(
+    def: &FunctionDefinition,
+    args: &[Expr],
+    symbol_table: &IndexMap<SmolStr, DeclaredSymbol>,
+    scope: &dyn Scope,
+    threshold: usize,
+) -> Option<Expr> {
+    // The body must be a single return statement
+    let expr = match def.statement.statement_list.as_slice() {
+        [stmt] => match &**stmt {
+            StatementData::Jump(jump) => match &**jump {
+                JumpStatementData::Return(Some(expr)) => expr,
+                _ => return None,
+            },
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    if expr_size(expr) > threshold
+        || args.len() != def.prototype.parameters.len()
+        || def.prototype.parameters.iter().any(|p| p.is_output())
+        || !args.iter().all(is_side_effect_free)
+    {
+        return None;
+    }
+
+    // Map parameter names to argument values
+    let mut subs = IndexMap::new();
+    for (parameter, arg) in def.prototype.parameters.iter().zip(args) {
+        let (ty, array) = parameter.parameter_type();
+        if array.is_some()
+            || ty.array_specifier.is_some()
+            || infer_type(arg, symbol_table, scope).as_ref() != Some(&*ty.ty)
+        {
+            return None;
+        }
+
+        if let FunctionParameterDeclarationData::Named(_, p) = &**parameter {
+            subs.insert(p.ident.ident.0.clone(), arg);
+        }
+    }
+
+    // Parameters are copies, so writing to them would write to the caller's values instead
+    if writes_variable(expr, &subs) {
+        return None;
+    }
+
+    struct V<'s> {
+        subs: IndexMap<SmolStr, &'s Expr>,
+        symbol_table: &'s IndexMap<SmolStr, DeclaredSymbol>,
+        shadowed: bool,
+    }
+
+    impl VisitorMut for V<'_> {
+        fn visit_expr(&mut self, e: &mut Expr) -> Visit {
+            if let ExprData::Variable(ident) = &**e {
+                if let Some(repl) = self.subs.get(ident.0.as_str()) {
+                    *e = (*repl).clone();
+
+                    // Arguments are already in the caller's scope
+                    return Visit::Parent;
+                } else if self.symbol_table.contains_key(ident.0.as_str()) {
+                    // This would refer to a caller local instead of a global
+                    self.shadowed = true;
+                }
+            }
+
+            Visit::Children
+        }
+    }
+
+    let mut v = V {
+        subs,
+        symbol_table,
+        shadowed: false,
+    };
+
+    let mut expr = (**expr).clone();
+    expr.visit_mut(&mut v);
+
+    let return_type = &def.prototype.ty.ty;
+    if v.shadowed
+        || return_type.array_specifier.is_some()
+        || infer_type(&expr, symbol_table, scope).as_ref() != Some(&*return_type.ty)
+    {
+        None
+    } else {
+        Some(expr)
+    }
+}
@@ -1,10 +1,20 @@
+use std::sync::Arc;
+
 use glsl_lang::{ast::*, visitor::*};
 
 use indexmap::IndexMap;
 
-use crate::{glsl_ext::FunIdentifierExt, Error, Result};
+use crate::{
+    glsl_ext::{FunIdentifierExt, TypeQualifierExt},
+    Error, InstantiationNote, Result,
+};
 
-use super::{template::TemplateDefinition, Scope};
+use super::{
+    alias::{parse_lambda_aliases, LambdaAlias},
+    dispatch::{forwarded_parameter_count, parse_dispatch_tables, DispatchTable},
+    template::{BoundArgument, TemplateDefinition},
+    Scope,
+};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct CapturedParameter {
@@ -13,6 +23,7 @@ pub struct CapturedParameter {
     pub gen_id: SmolStr,
     pub decl_type: TypeSpecifier,
     pub array: Option<ArraySpecifier>,
+    pub written: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -21,12 +32,22 @@ pub struct DeclaredSymbol {
     pub gen_id: Identifier,
     pub decl_type: TypeSpecifier,
     pub array: Option<ArraySpecifier>,
+    pub read_only: bool,
+    /// `true` if this symbol hides a global variable or a symbol from an enclosing block
+    pub shadowing: bool,
 }
 
 #[derive(Debug)]
 pub struct InstantiateTemplate {
     error: Option<Error>,
+    /// Symbols visible from the current block
     symbol_table: IndexMap<SmolStr, DeclaredSymbol>,
+    /// Symbols declared by each enclosing block, along with the symbols they hide
+    block_scopes: Vec<Vec<(SmolStr, Option<DeclaredSymbol>)>>,
+    /// Number of symbols declared in the current function
+    symbol_count: usize,
+    lambda_aliases: IndexMap<SmolStr, LambdaAlias>,
+    dispatch_tables: IndexMap<SmolStr, DispatchTable>,
     current_id: usize,
 }
 
@@ -35,6 +56,10 @@ impl InstantiateTemplate {
         Self {
             error: None,
             symbol_table: Default::default(),
+            block_scopes: vec![Vec::new()],
+            symbol_count: 0,
+            lambda_aliases: Default::default(),
+            dispatch_tables: Default::default(),
             current_id: outer.map(|o| o.current_id).unwrap_or(0),
         }
     }
@@ -52,6 +77,16 @@ impl InstantiateTemplate {
 
         def.visit_mut(&mut tgt);
 
+        // Lambda aliases and dispatch tables have been expanded at their use sites, remove their
+        // declarations
+        if !tgt.instantiator.lambda_aliases.is_empty()
+            || !tgt.instantiator.dispatch_tables.is_empty()
+        {
+            def.visit_mut(&mut RemoveLambdaAliases {
+                pointer_types: tgt.scope.declared_pointer_types(),
+            });
+        }
+
         // Push new function declarations
         let mut res = tgt.scope.take_instanced_templates();
         res.push(def);
@@ -67,6 +102,11 @@ impl InstantiateTemplate {
         self.symbol_table.get(name)
     }
 
+    /// Get the symbols visible from the current block
+    pub fn symbol_table(&self) -> &IndexMap<SmolStr, DeclaredSymbol> {
+        &self.symbol_table
+    }
+
     fn new_gen_id(&mut self, scope: &dyn Scope) -> Identifier {
         IdentifierData(SmolStr::from(format!("{}lp{}", scope.config().prefix, {
             let id = self.current_id;
@@ -76,9 +116,181 @@ impl InstantiateTemplate {
         .into()
     }
 
+    /// Find the lambda alias an identifier refers to
+    ///
+    /// Local aliases shadow the ones from the enclosing scopes, and local variables shadow both.
+    fn get_lambda_alias(&self, name: &str, scope: &dyn Scope) -> Option<LambdaAlias> {
+        if self.symbol_table.contains_key(name) {
+            return None;
+        }
+
+        self.lambda_aliases
+            .get(name)
+            .or_else(|| scope.get_lambda_alias(name))
+            .cloned()
+    }
+
+    /// Find the dispatch table an identifier refers to
+    ///
+    /// Local tables shadow the ones from the enclosing scopes, and local variables shadow both.
+    fn get_dispatch_table(&self, name: &str, scope: &dyn Scope) -> Option<DispatchTable> {
+        if self.symbol_table.contains_key(name) {
+            return None;
+        }
+
+        self.dispatch_tables
+            .get(name)
+            .or_else(|| scope.get_dispatch_table(name))
+            .cloned()
+    }
+
+    /// Expand `table[selector]` into a call to the dispatcher of the table
+    ///
+    /// Returns `None` if the expression doesn't index a dispatch table.
+    ///
+    /// # Parameters
+    ///
+    /// * `expr`: indexing expression
+    /// * `args`: arguments of the candidate call, or `None` to forward the arguments of a lambda
+    ///   expression using placeholders
+    /// * `scope`: current scope
+    fn expand_dispatch(
+        &self,
+        expr: &Expr,
+        args: Option<Vec<Expr>>,
+        scope: &mut dyn Scope,
+    ) -> Result<Option<Expr>> {
+        let (table, selector) = match &**expr {
+            ExprData::Bracket(table, selector) => match &***table {
+                ExprData::Variable(ident) => match self.get_dispatch_table(ident.0.as_str(), scope)
+                {
+                    Some(table) => (table, (**selector).clone()),
+                    None => return Ok(None),
+                },
+                _ => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+
+        debug!("expanding dispatch over {:?}", expr);
+
+        let (prototype, args) = match args {
+            Some(args) => (
+                super::overload::get_pointer_type_for_call(
+                    scope,
+                    table.pointer_type.as_str(),
+                    &args,
+                    &self.symbol_table,
+                ),
+                args,
+            ),
+            None => {
+                // Lambda expression which forwards its arguments
+                let prototype = scope.get_pointer_type(table.pointer_type.as_str(), 0);
+                let args = prototype
+                    .map(|prototype| {
+                        (1..=forwarded_parameter_count(prototype))
+                            .map(|i| {
+                                ExprData::Variable(IdentifierData(format!("_{}", i).into()).into())
+                                    .into_node()
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
+                (prototype, args)
+            }
+        };
+
+        let prototype = prototype
+            .ok_or_else(|| Error::UndeclaredPointerType(table.pointer_type.clone()))?
+            .clone();
+
+        let dispatcher = scope.declare_dispatcher(&prototype, table.candidates.len())?;
+
+        Ok(Some(table.dispatch_call(&dispatcher, selector, args)))
+    }
+
+    /// Expand a call to a lambda alias into its lambda expression
+    fn expand_alias_call(&self, expr: &mut Expr, scope: &dyn Scope) -> Result<bool> {
+        if let ExprData::FunCall(fun, args) = &**expr {
+            if let Some(alias) = fun
+                .as_ident_or_type_name()
+                .and_then(|ident| self.get_lambda_alias(ident.as_str(), scope))
+            {
+                let prototype = super::overload::get_pointer_type_for_call(
+                    scope,
+                    alias.pointer_type.as_str(),
+                    args,
+                    &self.symbol_table,
+                )
+                .ok_or_else(|| Error::UndeclaredPointerType(alias.pointer_type.clone()))?;
+
+                let mut lambda = alias.expr;
+                super::local_scope::lambda_instantiate(&mut lambda, args, prototype);
+
+                *expr = lambda;
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
     pub(in crate::transform) fn visit_fun_call(&mut self, expr: &mut Expr, scope: &mut dyn Scope) {
+        // Calls through dispatch tables are replaced by calls to their dispatcher
+        if let ExprData::FunCall(fun, args) = &**expr {
+            if let FunIdentifierData::Expr(fun) = &**fun {
+                match self.expand_dispatch(fun, Some(args.clone()), scope) {
+                    Ok(Some(dispatch)) => *expr = dispatch,
+                    Ok(None) => {}
+                    Err(error) => {
+                        self.error = Some(error);
+                        return;
+                    }
+                }
+            }
+        }
+
+        // Calls to lambda aliases are replaced by the aliased expression, which is then processed
+        // like the rest of the function
+        match self.expand_alias_call(expr, scope) {
+            Ok(true) => {
+                expr.visit_mut(&mut InstantiateTemplateUnit {
+                    instantiator: self,
+                    scope,
+                });
+                return;
+            }
+            Ok(false) => {}
+            Err(error) => {
+                self.error = Some(error);
+                return;
+            }
+        }
+
         match &mut **expr {
             ExprData::FunCall(fun, args) => {
+                // Lambda aliases passed as arguments are replaced by their expression, and
+                // dispatch tables by a lambda expression which calls their dispatcher
+                for arg in args.iter_mut() {
+                    if let ExprData::Variable(ident) = &**arg {
+                        if let Some(alias) = self.get_lambda_alias(ident.0.as_str(), scope) {
+                            debug!("expanding lambda alias {}", ident.0);
+                            *arg = alias.expr;
+                        }
+                    } else {
+                        match self.expand_dispatch(arg, None, scope) {
+                            Ok(Some(dispatch)) => *arg = dispatch,
+                            Ok(None) => {}
+                            Err(error) => {
+                                self.error = Some(error);
+                                return;
+                            }
+                        }
+                    }
+                }
+
                 // First visit the arguments to transform inner lambdas first
                 for arg in args.iter_mut() {
                     arg.visit_mut(&mut InstantiateTemplateUnit {
@@ -94,21 +306,24 @@ impl InstantiateTemplate {
                         match scope.transform_arg_call(expr, self) {
                             Ok(()) => {}
                             Err(Error::TransformAsTemplate) => {
+                                let mut inlined = None;
+                                let span = expr.span;
+
                                 if let ExprData::FunCall(ident, args) = &mut **expr {
                                     if let Some(ident) = ident.as_ident_or_type_name_mut() {
-                                        if let Some(template) = scope.get_template(ident) {
-                                            if let Err(error) =
-                                                self.transform_call(&template, ident, args, scope)
-                                            {
-                                                self.error = Some(error);
-                                            }
-                                        } else {
-                                            debug!("no template for function call: {}", ident);
+                                        match self.transform_template_call(ident, args, span, scope)
+                                        {
+                                            Ok(result) => inlined = result,
+                                            Err(error) => self.error = Some(error),
                                         }
                                     } else {
                                         debug!("invalid function identifier: {:?}", ident);
                                     }
                                 }
+
+                                if let Some(inlined) = inlined {
+                                    *expr = inlined;
+                                }
                             }
                             Err(error) => {
                                 self.error = Some(error);
@@ -117,45 +332,185 @@ impl InstantiateTemplate {
                     }
                 }
             }
-            other => panic!(
-                "expected ExprData::FunCall in InstantiateTemplate::visit_fun_call, got {:?}",
-                other
-            ),
+            _ => {
+                self.error = Some(Error::ExpectedFunctionCall { span: expr.span });
+            }
         }
     }
 
+    /// Transform a call to a function which may be a template
+    ///
+    /// Returns the expression which should replace the call if the instance was inlined.
+    fn transform_template_call(
+        &mut self,
+        fun: &mut SmolStr,
+        args: &mut Vec<Expr>,
+        span: Option<NodeSpan>,
+        scope: &mut dyn Scope,
+    ) -> Result<Option<Expr>> {
+        // Variadic templates are expanded for the number of arguments of the call. Overloads
+        // which can't be expanded are not viable for this call.
+        let mut expansion_error = None;
+        let templates: Vec<_> = scope
+            .get_templates(fun)
+            .iter()
+            .filter_map(|template| {
+                if template.is_variadic() {
+                    template
+                        .expand_pack(args.len(), scope)
+                        .map_err(|error| {
+                            expansion_error.get_or_insert(error);
+                        })
+                        .ok()
+                        .map(Arc::new)
+                } else {
+                    Some(template.clone())
+                }
+            })
+            .collect();
+
+        let template =
+            match super::overload::select_overload(&templates, args, &self.symbol_table, scope)? {
+                Some(template) => template,
+                None => {
+                    // The expansion error is only reported if no regular function matches either
+                    if let Some(error) = expansion_error {
+                        if !scope
+                            .get_function_overloads(fun)
+                            .iter()
+                            .any(|overload| overload.parameters.len() == args.len())
+                        {
+                            return Err(error);
+                        }
+                    }
+
+                    debug!("no template for function call: {}", fun);
+                    return Ok(None);
+                }
+            };
+
+        // Put the arguments in parameter order, with the default values of omitted template
+        // arguments
+        if let Some(bound) = template.bind_arguments(args, &self.symbol_table, scope)? {
+            *args = bound
+                .into_iter()
+                .map(|arg| match arg {
+                    BoundArgument::Given(expr) => expr,
+                    BoundArgument::Default(mut expr) => {
+                        // Call arguments have already been visited, but not default values
+                        expr.visit_mut(&mut InstantiateTemplateUnit {
+                            instantiator: self,
+                            scope,
+                        });
+                        expr
+                    }
+                })
+                .collect();
+        }
+
+        let template = Self::select_specialization(template, args, scope);
+        self.transform_call(&template, fun, args, span, scope)
+    }
+
+    /// Find the most specialized definition of a template for the given call arguments
+    ///
+    /// When several specializations bind the same number of parameters, the first declared one
+    /// is used.
+    fn select_specialization(
+        template: Arc<TemplateDefinition>,
+        args: &[Expr],
+        scope: &dyn Scope,
+    ) -> Arc<TemplateDefinition> {
+        scope
+            .get_template_specializations(template.ast().prototype.name.as_str())
+            .iter()
+            .rev()
+            .filter(|spec| {
+                spec.same_signature(&template) && spec.matches_specialization(args, scope)
+            })
+            .max_by_key(|spec| spec.specialized_count())
+            .cloned()
+            .unwrap_or(template)
+    }
+
+    /// Transform a call to a template into a call to its instance
+    ///
+    /// Returns the expression which should replace the call if the instance was inlined. Errors
+    /// raised while instantiating the template body are annotated with this call.
     fn transform_call(
         &mut self,
         template: &TemplateDefinition,
         fun: &mut SmolStr,
         args: &mut Vec<Expr>,
+        span: Option<NodeSpan>,
         scope: &mut dyn Scope,
-    ) -> Result<()> {
+    ) -> Result<Option<Expr>> {
         debug!("found template function call: {}({:?})", fun, args);
 
+        // Arguments as written at the call site, for error backtraces
+        let call_args = args.clone();
+
         // We found a template whose name matches the identifier
         // Thus, transform the function call
 
+        // Type template parameters are inferred from the arguments before they are rewritten
+        let type_arguments = template.infer_type_arguments(args, &self.symbol_table, scope)?;
+
         // Create the local scope
-        let mut local_scope = super::LocalScope::new(template, args, &self.symbol_table, scope)?;
+        let mut local_scope =
+            super::LocalScope::new(template, args, type_arguments, &self.symbol_table, scope)?;
         trace!("symbol table: {:?}", self.symbol_table);
 
-        // Instantiate the template if needed
-        if !local_scope.template_instance_declared(local_scope.name()) {
-            let template = template.instantiate(&mut local_scope, self)?;
-            local_scope.register_template_instance(template);
-        }
-
-        // The identifier should be replaced by the mangled name
-        *fun = local_scope.name().into();
-
         // Add the captured parameters to the end of the call
         for ep in local_scope.captured_parameters().iter() {
             // TODO: Preserve span information
             args.push(ExprData::Variable(IdentifierData(ep.ident.clone()).into()).into_node());
         }
 
-        Ok(())
+        // Instantiate the template if needed
+        let instance_name = match local_scope.template_instance_name(local_scope.name()) {
+            Some(name) => name,
+            None => {
+                let mut template =
+                    template
+                        .instantiate(&mut local_scope, self)
+                        .map_err(|error| {
+                            error.instantiated_from(InstantiationNote::new(
+                                fun.clone(),
+                                &call_args,
+                                span,
+                            ))
+                        })?;
+
+                if let Some(threshold) = local_scope.config().inline_threshold {
+                    if let Some(inlined) = super::inline::inline_instance(
+                        template.last().unwrap(),
+                        args,
+                        &self.symbol_table,
+                        &local_scope,
+                        threshold,
+                    ) {
+                        debug!("inlined {}", local_scope.name());
+
+                        template.pop();
+                        local_scope.register_template_dependencies(template);
+                        return Ok(Some(inlined));
+                    }
+                }
+
+                let body_id = super::template::instance_body_id(
+                    template.last().unwrap(),
+                    local_scope.captured_parameters(),
+                );
+
+                local_scope.register_template_instance(template, body_id)
+            }
+        };
+
+        // The identifier should be replaced by the mangled name
+        *fun = instance_name;
+
+        Ok(None)
     }
 
     fn add_declared_symbol(
@@ -164,6 +519,7 @@ impl InstantiateTemplate {
         name: SmolStr,
         decl_type: TypeSpecifier,
         array: Option<ArraySpecifier>,
+        read_only: bool,
     ) {
         if let TypeSpecifierNonArrayData::TypeName(tn) = &*decl_type.ty {
             if scope.declared_pointer_types().contains_key(tn.0.as_str()) {
@@ -173,15 +529,46 @@ impl InstantiateTemplate {
         }
 
         let gen_id = self.new_gen_id(scope);
+        let symbol_id = self.symbol_count;
+        self.symbol_count += 1;
+
+        let hidden = self.symbol_table.get(name.as_str()).cloned();
+        let shadowing = hidden.is_some() || scope.get_global_variable(name.as_str()).is_some();
+
         self.symbol_table.insert(
-            name,
+            name.clone(),
             DeclaredSymbol {
-                symbol_id: self.symbol_table.len(),
+                symbol_id,
                 gen_id,
                 decl_type,
                 array,
+                read_only,
+                shadowing,
             },
         );
+
+        self.block_scopes
+            .last_mut()
+            .expect("no block scope")
+            .push((name, hidden));
+    }
+
+    /// Enter a new block scope
+    fn push_block_scope(&mut self) {
+        self.block_scopes.push(Vec::new());
+    }
+
+    /// Leave the current block scope, making the symbols it hid visible again
+    fn pop_block_scope(&mut self) {
+        let declared = self.block_scopes.pop().expect("no block scope");
+
+        for (name, hidden) in declared.into_iter().rev() {
+            if let Some(hidden) = hidden {
+                self.symbol_table.insert(name, hidden);
+            } else {
+                self.symbol_table.shift_remove(&name);
+            }
+        }
     }
 }
 
@@ -201,19 +588,51 @@ impl VisitorMut for InstantiateTemplateUnit<'_> {
             p.ident.ident.0.clone(),
             p.ty.clone(),
             p.ident.array_spec.clone(),
+            false,
         );
 
         Visit::Children
     }
 
     fn visit_init_declarator_list(&mut self, idl: &mut InitDeclaratorList) -> Visit {
-        // Register all declared variables
-        self.instantiator.add_declared_symbol(
-            self.scope,
-            idl.head.name.as_ref().unwrap().0.clone(),
-            idl.head.ty.ty.clone(),
-            idl.head.array_specifier.clone(),
-        );
+        if let Some(aliases) = parse_lambda_aliases(idl, self.scope.declared_pointer_types()) {
+            // Lambda aliases are expanded where they are used, with the symbols visible there
+            for (name, alias) in aliases {
+                debug!("declared local lambda alias: {}", name);
+                self.instantiator.lambda_aliases.insert(name, alias);
+            }
+
+            return Visit::Parent;
+        }
+
+        if let Some(tables) = parse_dispatch_tables(idl, self.scope.declared_pointer_types()) {
+            // Dispatch tables are also expanded where they are used
+            for (name, table) in tables {
+                debug!("declared local dispatch table: {}", name);
+                self.instantiator.dispatch_tables.insert(name, table);
+            }
+
+            return Visit::Parent;
+        }
+
+        let read_only = idl
+            .head
+            .ty
+            .qualifier
+            .as_ref()
+            .is_some_and(|q| q.is_read_only());
+
+        // Register all declared variables. The head has no name for struct declarations such as
+        // `struct S { float x; };`
+        if let Some(name) = &idl.head.name {
+            self.instantiator.add_declared_symbol(
+                self.scope,
+                name.0.clone(),
+                idl.head.ty.ty.clone(),
+                idl.head.array_specifier.clone(),
+                read_only,
+            );
+        }
 
         // Add tail
         for t in &idl.tail {
@@ -222,6 +641,90 @@ impl VisitorMut for InstantiateTemplateUnit<'_> {
                 t.ident.ident.0.clone(),
                 idl.head.ty.ty.clone(),
                 idl.head.array_specifier.clone(),
+                read_only,
+            );
+        }
+
+        Visit::Children
+    }
+
+    fn visit_compound_statement(&mut self, cs: &mut CompoundStatement) -> Visit {
+        self.instantiator.push_block_scope();
+
+        for stmt in cs.statement_list.iter_mut() {
+            stmt.visit_mut(self);
+        }
+
+        self.instantiator.pop_block_scope();
+        Visit::Parent
+    }
+
+    fn visit_iteration_statement(&mut self, stmt: &mut IterationStatement) -> Visit {
+        // Variables declared in the loop header are only visible in the loop
+        match &mut **stmt {
+            IterationStatementData::For(init, rest, body) => {
+                self.instantiator.push_block_scope();
+
+                init.visit_mut(self);
+                rest.visit_mut(self);
+                body.visit_mut(self);
+
+                self.instantiator.pop_block_scope();
+                Visit::Parent
+            }
+            IterationStatementData::While(cond, body) => {
+                self.instantiator.push_block_scope();
+
+                cond.visit_mut(self);
+                body.visit_mut(self);
+
+                self.instantiator.pop_block_scope();
+                Visit::Parent
+            }
+            IterationStatementData::DoWhile(_, _) => Visit::Children,
+        }
+    }
+
+    fn visit_selection_statement(&mut self, stmt: &mut SelectionStatement) -> Visit {
+        stmt.cond.visit_mut(self);
+
+        // Each branch is a scope of its own, even without braces
+        let branches = match &mut *stmt.rest {
+            SelectionRestStatementData::Statement(then) => vec![then],
+            SelectionRestStatementData::Else(then, otherwise) => vec![then, otherwise],
+        };
+
+        for branch in branches {
+            self.instantiator.push_block_scope();
+            branch.visit_mut(self);
+            self.instantiator.pop_block_scope();
+        }
+
+        Visit::Parent
+    }
+
+    fn visit_switch_statement(&mut self, stmt: &mut SwitchStatement) -> Visit {
+        stmt.head.visit_mut(self);
+
+        // The body of a switch is a block, even though it's not a compound statement
+        self.instantiator.push_block_scope();
+
+        for body in stmt.body.iter_mut() {
+            body.visit_mut(self);
+        }
+
+        self.instantiator.pop_block_scope();
+        Visit::Parent
+    }
+
+    fn visit_condition(&mut self, cond: &mut Condition) -> Visit {
+        if let ConditionData::Assignment(ty, ident, _) = &**cond {
+            self.instantiator.add_declared_symbol(
+                self.scope,
+                ident.0.clone(),
+                ty.ty.clone(),
+                None,
+                ty.qualifier.as_ref().is_some_and(|q| q.is_read_only()),
             );
         }
 
@@ -239,3 +742,25 @@ impl VisitorMut for InstantiateTemplateUnit<'_> {
         Visit::Children
     }
 }
+
+/// Remove the declarations of lambda aliases and dispatch tables from a function body
+struct RemoveLambdaAliases<'s> {
+    pointer_types: &'s IndexMap<SmolStr, Vec<FunctionPrototype>>,
+}
+
+impl VisitorMut for RemoveLambdaAliases<'_> {
+    fn visit_compound_statement(&mut self, cs: &mut CompoundStatement) -> Visit {
+        cs.statement_list.retain(|stmt| match &**stmt {
+            StatementData::Declaration(decl) => match &**decl {
+                DeclarationData::InitDeclaratorList(idl) => {
+                    parse_lambda_aliases(idl, self.pointer_types).is_none()
+                        && parse_dispatch_tables(idl, self.pointer_types).is_none()
+                }
+                _ => true,
+            },
+            _ => true,
+        });
+
+        Visit::Children
+    }
+}
@@ -0,0 +1,196 @@
+//! Interfaces, i.e. structs of function pointers
+
+use glsl_lang::{ast::*, visitor::Visit};
+
+use indexmap::IndexMap;
+
+use super::shadow::{visit_scoped, BlockScopes, ScopedVisitorMut};
+use crate::glsl_ext::FunIdentifierExt;
+
+/// A group of pointer types passed to templates as a single value, declared as a struct whose
+/// fields all have pointer types
+///
+/// ```glsl
+/// struct Material { shadeFn shade; normalFn normal; };
+/// ```
+///
+/// A template parameter of an interface type stands for one template parameter per field, and is
+/// bound to a constructor expression such as `Material(phong, sdNormal)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interface {
+    /// Names of the fields and of their pointer types, in declaration order
+    pub fields: Vec<(SmolStr, SmolStr)>,
+}
+
+/// Extract the interface declared by a declarator list
+///
+/// # Parameters
+///
+/// * `idl`: declarator list to inspect
+/// * `pointer_types`: known pointer types
+///
+/// # Returns
+///
+/// The name of the interface and its fields, or `None` if this declarator list is not a named
+/// struct declaration whose fields all have non-array pointer types.
+pub fn parse_interface(
+    idl: &InitDeclaratorList,
+    pointer_types: &IndexMap<SmolStr, Vec<FunctionPrototype>>,
+) -> Option<(SmolStr, Interface)> {
+    if idl.head.name.is_some() || !idl.tail.is_empty() {
+        return None;
+    }
+
+    let st = match &*idl.head.ty.ty.ty {
+        TypeSpecifierNonArrayData::Struct(st) => st,
+        _ => return None,
+    };
+
+    let mut fields = Vec::with_capacity(st.fields.len());
+    for field in &st.fields {
+        let pointer_type = match &*field.ty.ty {
+            TypeSpecifierNonArrayData::TypeName(tn)
+                if field.ty.array_specifier.is_none()
+                    && pointer_types.contains_key(tn.0.as_str()) =>
+            {
+                tn.0.clone()
+            }
+            _ => return None,
+        };
+
+        for ident in &field.identifiers {
+            if ident.array_spec.is_some() {
+                return None;
+            }
+
+            fields.push((ident.ident.0.clone(), pointer_type.clone()));
+        }
+    }
+
+    if fields.is_empty() {
+        None
+    } else {
+        Some((st.name.as_ref()?.0.clone(), Interface { fields }))
+    }
+}
+
+/// Return the name of the template parameter which stands for a field of an interface parameter
+///
+/// The name is not a valid GLSL identifier, so it can't conflict with the template body.
+pub fn field_symbol(parameter: &str, field: &str) -> SmolStr {
+    format!("{}.{}", parameter, field).into()
+}
+
+/// Split an interface argument into the values of its fields
+///
+/// # Parameters
+///
+/// * `arg`: template argument
+/// * `name`: name of the interface of the template parameter
+/// * `count`: number of fields of the interface
+///
+/// # Returns
+///
+/// The field values, or `None` if `arg` is not a constructor expression for this interface.
+pub fn interface_components(arg: Expr, name: &str, count: usize) -> Option<Vec<Expr>> {
+    match arg.content {
+        ExprData::FunCall(fun, args)
+            if fun.as_ident_or_type_name().map(|ident| ident.as_str()) == Some(name)
+                && args.len() == count =>
+        {
+            Some(args)
+        }
+        _ => None,
+    }
+}
+
+/// Rewrite the uses of interface parameters in a template body
+///
+/// `m.shade` becomes the template parameter for the `shade` field of `m`, and `m` on its own
+/// becomes a constructor expression of its fields, so it can be forwarded to other templates.
+/// Local declarations with the same name as a parameter are left alone.
+struct ExpandInterfaceParameters<'i> {
+    /// Interface parameters of the template, with the name of their interface
+    parameters: IndexMap<SmolStr, (SmolStr, &'i Interface)>,
+}
+
+impl ExpandInterfaceParameters<'_> {
+    fn get(&self, name: &str, scopes: &BlockScopes) -> Option<&(SmolStr, &Interface)> {
+        if scopes.is_shadowed(name) {
+            None
+        } else {
+            self.parameters.get(name)
+        }
+    }
+}
+
+impl ScopedVisitorMut for ExpandInterfaceParameters<'_> {
+    fn visit_expr(&mut self, expr: &mut Expr, scopes: &BlockScopes) -> Visit {
+        match &**expr {
+            ExprData::Dot(e, field) => {
+                if let ExprData::Variable(ident) = &***e {
+                    if let Some((_, interface)) = self.get(ident.0.as_str(), scopes) {
+                        if interface.fields.iter().any(|(name, _)| *name == field.0) {
+                            expr.content = ExprData::Variable(
+                                IdentifierData(field_symbol(ident.0.as_str(), field.0.as_str()))
+                                    .into_node(),
+                            );
+
+                            return Visit::Parent;
+                        }
+                    }
+                }
+            }
+            ExprData::Variable(ident) => {
+                if let Some((name, interface)) = self.get(ident.0.as_str(), scopes) {
+                    let fields = interface
+                        .fields
+                        .iter()
+                        .map(|(field, _)| {
+                            ExprData::Variable(
+                                IdentifierData(field_symbol(ident.0.as_str(), field.as_str()))
+                                    .into_node(),
+                            )
+                            .into_node()
+                        })
+                        .collect();
+
+                    expr.content = ExprData::FunCall(
+                        FunIdentifierData::TypeSpecifier(Box::new(
+                            TypeSpecifierData {
+                                ty: TypeSpecifierNonArrayData::TypeName(
+                                    TypeNameData(name.clone()).into_node(),
+                                )
+                                .into_node(),
+                                array_specifier: None,
+                            }
+                            .into_node(),
+                        ))
+                        .into_node(),
+                        fields,
+                    );
+
+                    return Visit::Parent;
+                }
+            }
+            _ => {}
+        }
+
+        Visit::Children
+    }
+}
+
+/// Rewrite the uses of interface parameters in a template body
+///
+/// # Parameters
+///
+/// * `body`: body of the template
+/// * `parameters`: interface parameters of the template, with the name of their interface
+pub(crate) fn expand_interface_parameters(
+    body: &mut CompoundStatement,
+    parameters: IndexMap<SmolStr, (SmolStr, &Interface)>,
+) {
+    if !parameters.is_empty() {
+        visit_scoped(body, ExpandInterfaceParameters { parameters });
+    }
+}
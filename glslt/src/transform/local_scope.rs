@@ -4,15 +4,18 @@ use std::sync::Arc;
 
 use glsl_lang::{
     ast::*,
-    visitor::{HostMut, Visit, VisitorMut},
+    visitor::{Host, HostMut, Visit, Visitor, VisitorMut},
 };
 
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
 
-use crate::glsl_ext::FunIdentifierExt;
+use crate::glsl_ext::{FunIdentifierExt, FunctionParameterDeclarationExt};
 
-use super::template::TemplateDefinition;
+use super::template::{ConstantArgument, TemplateDefinition, TemplateParameterKind, TypeArgument};
 use super::{
+    alias::LambdaAlias,
+    dispatch::DispatchTable,
+    global_scope::GlobalVariable,
     instantiate::{CapturedParameter, DeclaredSymbol, InstantiateTemplate},
     ResolvedArgument, ResolvedArgumentExpr, Scope,
 };
@@ -25,12 +28,18 @@ pub struct LocalScope<'p, 'q> {
     parent: &'p mut dyn Scope,
     /// Name of the current template scope
     name: SmolStr,
+    /// Name of the template instantiated in this scope
+    template_name: SmolStr,
     /// List of ordered template parameters
     template_parameters: Vec<(Expr, &'q str)>,
     /// Lookup table for template parameters by name
     template_parameters_by_name: IndexMap<SmolStr, usize>,
     /// List of parameter names captured by entering the current scope
     captured_parameters: Vec<CapturedParameter>,
+    /// Concrete types of the type template parameters
+    type_arguments: Vec<TypeArgument>,
+    /// Values of the constant template parameters
+    constant_arguments: Vec<ConstantArgument>,
 }
 
 impl<'p, 'q> LocalScope<'p, 'q> {
@@ -40,27 +49,31 @@ impl<'p, 'q> LocalScope<'p, 'q> {
     ///
     /// * `template`: template being instantiated as part of a function call
     /// * `args`: list of expressions which are arguments to the template call
+    /// * `type_arguments`: concrete types of the type template parameters
     /// * `symbol_table`: locally-declared symbols from the outer function
     pub fn new(
         template: &'q TemplateDefinition,
         args: &mut Vec<Expr>,
+        type_arguments: Vec<TypeArgument>,
         symbol_table: &IndexMap<SmolStr, DeclaredSymbol>,
         parent: &'p mut dyn Scope,
     ) -> crate::Result<Self> {
         // Extract template parameters for this scope
         let mut template_parameters = template.extract_template_parameters(args)?;
 
-        // Build lookup table
+        // Build lookup table. Constant parameters are substituted in the body before it is
+        // instantiated, so they are never resolved as functions.
         let mut template_parameters_by_name = IndexMap::with_capacity(template_parameters.len());
         for (index, parameter) in template.parameters().iter().enumerate() {
+            if parameter.kind != TemplateParameterKind::Function {
+                continue;
+            }
+
             if let Some(name) = parameter.symbol.as_ref() {
                 template_parameters_by_name.insert(name.to_owned(), index);
             }
         }
 
-        // Generate scope name
-        let name = template.generate_id(&template_parameters[..], parent);
-
         // Extract the set of captured variables
         struct Capturer<'ds> {
             st: &'ds IndexMap<SmolStr, DeclaredSymbol>,
@@ -74,6 +87,13 @@ impl<'p, 'q> LocalScope<'p, 'q> {
                     // captured and renamed
 
                     if let Some(sb) = self.st.get(ident.0.as_str()) {
+                        if sb.shadowing && !self.captured.contains_key(ident.0.as_str()) {
+                            warn!(
+                                "captured variable {} hides another declaration with the same name",
+                                ident.0
+                            );
+                        }
+
                         // Add it to the list of captured variables
                         self.captured.insert(ident.0.clone(), sb);
                         // Rename the reference
@@ -90,6 +110,19 @@ impl<'p, 'q> LocalScope<'p, 'q> {
             }
         }
 
+        // Find the variables written to by the lambda expressions
+        let mut writes = WriteCollector {
+            scope: &*parent,
+            symbol_table,
+            written: IndexSet::new(),
+        };
+
+        for tp in &template_parameters {
+            tp.0.visit(&mut writes);
+        }
+
+        let written = writes.written;
+
         // Merge in the symbol table the outer captured parameters
         let mut symbol_table = symbol_table.clone();
         for captured_parameter in parent.captured_parameters() {
@@ -100,10 +133,48 @@ impl<'p, 'q> LocalScope<'p, 'q> {
                     gen_id: IdentifierData(captured_parameter.gen_id.clone()).into(),
                     decl_type: captured_parameter.decl_type.clone(),
                     array: captured_parameter.array.clone(),
+                    read_only: false,
+                    shadowing: false,
                 },
             );
         }
 
+        // Writing to read-only variables would only fail when compiling the generated code
+        for name in &written {
+            let read_only = match symbol_table.get(name) {
+                Some(sb) => sb.read_only,
+                None => parent
+                    .get_global_variable(name)
+                    .is_some_and(|global| global.read_only),
+            };
+
+            if read_only {
+                return Err(crate::Error::ReadOnlyCapture(name.clone()));
+            }
+        }
+
+        // Constant arguments are copied into the instance, so they can't refer to local variables
+        let mut constant_arguments = Vec::new();
+        for (parameter, arg) in template.parameters().iter().zip(&template_parameters) {
+            if parameter.kind != TemplateParameterKind::Constant {
+                continue;
+            }
+
+            if !is_constant_expression(&arg.0, &symbol_table) {
+                return Err(crate::Error::NonConstantTemplateArgument {
+                    name: template.ast().prototype.name.0.clone(),
+                    parameter: parameter.symbol.clone().unwrap_or_default(),
+                });
+            }
+
+            if let Some(name) = parameter.symbol.as_ref() {
+                constant_arguments.push((name.clone(), arg.0.clone()));
+            }
+        }
+
+        // Instance names are generated from the arguments as written by the caller
+        let caller_parameters = template_parameters.clone();
+
         // Visit the input expressions
         let mut capturer = Capturer {
             st: &symbol_table,
@@ -118,12 +189,12 @@ impl<'p, 'q> LocalScope<'p, 'q> {
         let mut captured_parameters: IndexMap<_, _> = capturer
             .captured
             .into_iter()
-            .map(|(key, _)| {
-                // TODO: This should be a proper semantic error
-                let declared = symbol_table.get(&key).expect("unknown symbol").clone();
+            .map(|(key, declared)| {
+                let declared = declared.clone();
                 (
                     key.clone(),
                     CapturedParameter {
+                        written: written.contains(&key),
                         ident: key,
                         gen_id: declared.gen_id.0.clone(),
                         symbol_id: declared.symbol_id,
@@ -149,12 +220,27 @@ impl<'p, 'q> LocalScope<'p, 'q> {
             captured_parameters.into_iter().map(|(_, v)| v).collect();
         captured_parameters.sort_by_key(|item| item.symbol_id);
 
+        // Generate scope name. Captured parameters are part of the signature of the instance,
+        // so the same lambda capturing variables of different types needs another instance.
+        let name = template.generate_id(
+            &caller_parameters[..],
+            &type_arguments,
+            &captured_parameters,
+            parent,
+        );
+
+        let template_name = template.ast().prototype.name.0.clone();
+        check_instantiation_stack(&template_name, &name, parent)?;
+
         Ok(Self {
             parent,
             name,
+            template_name,
             template_parameters,
             template_parameters_by_name,
             captured_parameters,
+            type_arguments,
+            constant_arguments,
         })
     }
 
@@ -163,16 +249,27 @@ impl<'p, 'q> LocalScope<'p, 'q> {
         self.name.as_str()
     }
 
+    /// Get the concrete types of the type template parameters
+    pub fn type_arguments(&self) -> &[TypeArgument] {
+        &self.type_arguments[..]
+    }
+
+    /// Get the values of the constant template parameters
+    pub fn constant_arguments(&self) -> &[ConstantArgument] {
+        &self.constant_arguments[..]
+    }
+
     /// Transform the target function call expression into a GLSL function call
     ///
-    /// This takes an exclusive reference to the expression to modify it, but assumes it's a
-    /// function call and will panic if it isn't the case.
+    /// This takes an exclusive reference to the expression to modify it, and returns
+    /// [Error::ExpectedFunctionCall](crate::Error::ExpectedFunctionCall) if it isn't a function
+    /// call.
     pub fn transform_fn_call(
         &mut self,
         e: &mut Expr,
         instantiator: &mut InstantiateTemplate,
         template: &TemplateDefinition,
-    ) {
+    ) -> crate::Result<()> {
         self.transform_arg_call_typed(e, instantiator, &template.ast().prototype)
     }
 
@@ -181,7 +278,9 @@ impl<'p, 'q> LocalScope<'p, 'q> {
         e: &mut Expr,
         instantiator: &mut InstantiateTemplate,
         prototype: &FunctionPrototype,
-    ) {
+    ) -> crate::Result<()> {
+        let span = e.span;
+
         match &mut **e {
             ExprData::FunCall(fun, src_args) => {
                 // Only consider raw identifiers for function names
@@ -258,10 +357,12 @@ impl<'p, 'q> LocalScope<'p, 'q> {
                     );
                 }
             }
-            other => {
-                panic!("LocalScope::transform_fn_call can only process function call expressions, got {:?}", other);
+            _ => {
+                return Err(crate::Error::ExpectedFunctionCall { span });
             }
         }
+
+        Ok(())
     }
 }
 
@@ -274,20 +375,36 @@ impl Scope for LocalScope<'_, '_> {
         Some(self.parent)
     }
 
-    fn declared_pointer_types(&self) -> &IndexMap<SmolStr, FunctionPrototype> {
+    fn current_instance(&self) -> Option<(&str, &str)> {
+        Some((self.template_name.as_str(), self.name.as_str()))
+    }
+
+    fn declared_pointer_types(&self) -> &IndexMap<SmolStr, Vec<FunctionPrototype>> {
         self.parent.declared_pointer_types()
     }
 
-    fn get_template(&self, template_name: &str) -> Option<Arc<TemplateDefinition>> {
-        self.parent.get_template(template_name)
+    fn get_templates(&self, template_name: &str) -> &[Arc<TemplateDefinition>] {
+        self.parent.get_templates(template_name)
+    }
+
+    fn get_template_specializations(&self, template_name: &str) -> &[Arc<TemplateDefinition>] {
+        self.parent.get_template_specializations(template_name)
+    }
+
+    fn template_instance_name(&self, template_name: &str) -> Option<SmolStr> {
+        self.parent.template_instance_name(template_name)
     }
 
-    fn template_instance_declared(&self, template_name: &str) -> bool {
-        self.parent.template_instance_declared(template_name)
+    fn register_template_instance(
+        &mut self,
+        definitions: Vec<FunctionDefinition>,
+        body_id: String,
+    ) -> SmolStr {
+        self.parent.register_template_instance(definitions, body_id)
     }
 
-    fn register_template_instance(&mut self, definitions: Vec<FunctionDefinition>) {
-        self.parent.register_template_instance(definitions)
+    fn register_template_dependencies(&mut self, definitions: Vec<FunctionDefinition>) {
+        self.parent.register_template_dependencies(definitions)
     }
 
     fn take_instanced_templates(&mut self) -> Vec<FunctionDefinition> {
@@ -332,6 +449,7 @@ impl Scope for LocalScope<'_, '_> {
                             .or_else(|| {
                                 self.declared_pointer_types()
                                     .get(arg.1)
+                                    .and_then(|overloads| overloads.first())
                                     .map(|pointer_type| ResolvedArgument {
                                         body: ResolvedArgumentExpr::Lambda(arg.0.clone()),
                                         pointer_type,
@@ -344,6 +462,7 @@ impl Scope for LocalScope<'_, '_> {
                 _ => self
                     .declared_pointer_types()
                     .get(arg.1)
+                    .and_then(|overloads| overloads.first())
                     .map(|pointer_type| ResolvedArgument {
                         body: ResolvedArgumentExpr::Lambda(arg.0.clone()),
                         pointer_type,
@@ -355,13 +474,47 @@ impl Scope for LocalScope<'_, '_> {
         }
     }
 
+    fn get_function_overloads(&self, name: &str) -> &[FunctionPrototype] {
+        self.parent.get_function_overloads(name)
+    }
+
+    fn get_lambda_alias(&self, name: &str) -> Option<&LambdaAlias> {
+        // Template parameters shadow aliases from the outer scopes
+        if self.template_parameters_by_name.contains_key(name) {
+            None
+        } else {
+            self.parent.get_lambda_alias(name)
+        }
+    }
+
+    fn get_dispatch_table(&self, name: &str) -> Option<&DispatchTable> {
+        // Template parameters shadow dispatch tables from the outer scopes
+        if self.template_parameters_by_name.contains_key(name) {
+            None
+        } else {
+            self.parent.get_dispatch_table(name)
+        }
+    }
+
+    fn declare_dispatcher(
+        &mut self,
+        pointer_type: &FunctionPrototype,
+        count: usize,
+    ) -> crate::Result<SmolStr> {
+        self.parent.declare_dispatcher(pointer_type, count)
+    }
+
+    fn get_global_variable(&self, name: &str) -> Option<&GlobalVariable> {
+        self.parent.get_global_variable(name)
+    }
+
     fn transform_arg_call(
         &mut self,
         expr: &mut Expr,
         instantiator: &mut InstantiateTemplate,
     ) -> crate::Result<()> {
         match &mut **expr {
-            ExprData::FunCall(ident, _) => {
+            ExprData::FunCall(ident, args) => {
                 if let Some(ident) = ident.as_ident_or_type_name() {
                     if let Some(tplarg) = self
                         .template_parameters_by_name
@@ -369,21 +522,23 @@ impl Scope for LocalScope<'_, '_> {
                         .and_then(|id| self.template_parameters.get(*id))
                     {
                         // TODO: Remove this clone, with an Rc?
-                        let c = self
-                            .declared_pointer_types()
-                            .get(tplarg.1)
-                            .ok_or_else(|| crate::Error::UndeclaredPointerType(tplarg.1.into()))?
-                            .clone();
+                        let c = super::overload::get_pointer_type_for_call(
+                            self,
+                            tplarg.1,
+                            args,
+                            instantiator.symbol_table(),
+                        )
+                        .ok_or_else(|| crate::Error::UndeclaredPointerType(tplarg.1.into()))?
+                        .clone();
 
                         debug!("transforming call to {:?} using prototype {:?}", expr, c);
-                        self.transform_arg_call_typed(expr, instantiator, &c);
-                        return Ok(());
+                        return self.transform_arg_call_typed(expr, instantiator, &c);
                     }
                 } else {
                     debug!("invalid function identifier: {:?}", ident);
                 }
             }
-            _ => panic!("unsupported expression for LocalScope::transform_arg_call"),
+            _ => return Err(crate::Error::ExpectedFunctionCall { span: expr.span }),
         }
 
         Err(crate::Error::TransformAsTemplate)
@@ -394,7 +549,170 @@ impl Scope for LocalScope<'_, '_> {
     }
 }
 
-fn lambda_instantiate(tgt: &mut Expr, source_parameters: &[Expr], prototype: &FunctionPrototype) {
+/// Collects the variables written to by an expression
+struct WriteCollector<'s> {
+    scope: &'s dyn Scope,
+    symbol_table: &'s IndexMap<SmolStr, DeclaredSymbol>,
+    written: IndexSet<SmolStr>,
+}
+
+impl WriteCollector<'_> {
+    fn write(&mut self, lvalue: &Expr) {
+        match &**lvalue {
+            ExprData::Variable(ident) => {
+                self.written.insert(ident.0.clone());
+            }
+            ExprData::Dot(e, _) | ExprData::Bracket(e, _) => self.write(e),
+            _ => {}
+        }
+    }
+}
+
+impl Visitor for WriteCollector<'_> {
+    fn visit_expr(&mut self, e: &Expr) -> Visit {
+        match &**e {
+            ExprData::Assignment(lhs, _, _) => self.write(lhs),
+            ExprData::Unary(op, e) if matches!(&**op, UnaryOpData::Inc | UnaryOpData::Dec) => {
+                self.write(e)
+            }
+            ExprData::PostInc(e) | ExprData::PostDec(e) => self.write(e),
+            ExprData::FunCall(fun, args) => {
+                if let Some(name) = fun.as_ident_or_type_name() {
+                    // Arguments passed to out parameters are written to by the call
+                    let outputs: Vec<usize> = if crate::util::is_builtin_glsl_function(name) {
+                        crate::util::builtin_glsl_output_parameters(name).to_vec()
+                    } else {
+                        self.scope
+                            .resolve_function_name(name)
+                            .map(|resolved| {
+                                // The pointer type of a lambda is only one of its overloads
+                                let prototype = match resolved.body {
+                                    ResolvedArgumentExpr::Lambda(_) => {
+                                        super::overload::get_pointer_type_for_call(
+                                            self.scope,
+                                            resolved.pointer_type.name.as_str(),
+                                            args,
+                                            self.symbol_table,
+                                        )
+                                        .unwrap_or(resolved.pointer_type)
+                                    }
+                                    _ => resolved.pointer_type,
+                                };
+
+                                prototype
+                                    .parameters
+                                    .iter()
+                                    .enumerate()
+                                    .filter(|(_, p)| p.is_output())
+                                    .map(|(index, _)| index)
+                                    .collect()
+                            })
+                            .unwrap_or_default()
+                    };
+
+                    for index in outputs {
+                        if let Some(arg) = args.get(index) {
+                            self.write(arg);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Visit::Children
+    }
+}
+
+/// Return `true` if an expression can be copied into a template instance as a constant
+///
+/// Constant expressions are built from literals, operators, constructors and built-in function
+/// calls. They may refer to global constants, but not to variables of the calling function.
+fn is_constant_expression(expr: &Expr, symbol_table: &IndexMap<SmolStr, DeclaredSymbol>) -> bool {
+    match &**expr {
+        ExprData::Variable(ident) => !symbol_table.contains_key(ident.0.as_str()),
+        ExprData::IntConst(_)
+        | ExprData::UIntConst(_)
+        | ExprData::BoolConst(_)
+        | ExprData::FloatConst(_)
+        | ExprData::DoubleConst(_) => true,
+        ExprData::Unary(op, e) => {
+            !matches!(&**op, UnaryOpData::Inc | UnaryOpData::Dec)
+                && is_constant_expression(e, symbol_table)
+        }
+        ExprData::Binary(_, a, b) => {
+            is_constant_expression(a, symbol_table) && is_constant_expression(b, symbol_table)
+        }
+        ExprData::Ternary(c, a, b) => {
+            is_constant_expression(c, symbol_table)
+                && is_constant_expression(a, symbol_table)
+                && is_constant_expression(b, symbol_table)
+        }
+        ExprData::FunCall(fun, args) => {
+            let callable = match &**fun {
+                FunIdentifierData::TypeSpecifier(_) => true,
+                FunIdentifierData::Expr(_) => fun
+                    .as_ident_or_type_name()
+                    .is_some_and(|name| crate::util::is_builtin_glsl_function(name)),
+            };
+
+            callable
+                && args
+                    .iter()
+                    .all(|arg| is_constant_expression(arg, symbol_table))
+        }
+        ExprData::Dot(e, _) => is_constant_expression(e, symbol_table),
+        ExprData::Bracket(e, index) => {
+            is_constant_expression(e, symbol_table) && is_constant_expression(index, symbol_table)
+        }
+        _ => false,
+    }
+}
+
+/// Check that a new template instance doesn't recurse through the instantiations in progress
+///
+/// # Parameters
+///
+/// * `template_name`: name of the instantiated template
+/// * `instance`: name of the new template instance
+/// * `parent`: scope the template is instantiated from
+fn check_instantiation_stack(
+    template_name: &SmolStr,
+    instance: &str,
+    parent: &dyn Scope,
+) -> crate::Result<()> {
+    let mut chain = vec![template_name.clone()];
+    let mut recursive = false;
+
+    let mut current = Some(parent);
+    while let Some(scope) = current {
+        if let Some((template, name)) = scope.current_instance() {
+            chain.push(template.into());
+            recursive |= name == instance;
+        }
+
+        current = scope.parent_scope();
+    }
+
+    chain.reverse();
+
+    if recursive {
+        return Err(crate::Error::RecursiveTemplate { chain });
+    }
+
+    let limit = parent.config().max_instantiation_depth;
+    if chain.len() > limit {
+        return Err(crate::Error::InstantiationDepthExceeded { limit, chain });
+    }
+
+    Ok(())
+}
+
+pub(super) fn lambda_instantiate(
+    tgt: &mut Expr,
+    source_parameters: &[Expr],
+    prototype: &FunctionPrototype,
+) {
     // Declare the visitor for the substitution
     struct V<'s> {
         subs: IndexMap<String, &'s Expr>,
@@ -420,7 +738,9 @@ fn lambda_instantiate(tgt: &mut Expr, source_parameters: &[Expr], prototype: &Fu
     for (id, value) in source_parameters.iter().enumerate() {
         subs.insert(format!("_{}", id + 1), value);
 
-        if let FunctionParameterDeclarationData::Named(_, p) = &*prototype.parameters[id] {
+        if let Some(FunctionParameterDeclarationData::Named(_, p)) =
+            prototype.parameters.get(id).map(|p| &**p)
+        {
             subs.insert(format!("_{}", p.ident.ident.0), value);
         }
     }
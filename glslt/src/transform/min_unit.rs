@@ -7,7 +7,10 @@ use indexmap::IndexMap;
 use super::instantiate::InstantiateTemplate;
 use super::{FnHandle, FnRef, GlobalScope, ParsedDeclaration, TransformUnit};
 
-use crate::{glsl_ext::FunIdentifierExt, Error, Result, TransformConfig};
+use crate::{
+    glsl_ext::{FunIdentifierExt, FunctionPrototypeExt},
+    Error, Result, TransformConfig,
+};
 
 mod dependency_dag;
 use dependency_dag::*;
@@ -74,6 +77,13 @@ impl MinUnit {
                 self.global_scope
                     .declared_templates()
                     .values()
+                    .flatten()
+                    .chain(
+                        self.global_scope
+                            .declared_specializations()
+                            .values()
+                            .flatten(),
+                    )
                     .map(|dt| dt.fn_ref()),
             )
     }
@@ -90,7 +100,8 @@ impl MinUnit {
     ///
     /// # Parameters
     ///
-    /// * `wanted`: list of function names to include in the result
+    /// * `wanted`: list of function names to include in the result, in addition to the ones listed
+    ///   by `#pragma glslt keep` directives
     pub fn into_translation_unit<'a>(
         self,
         wanted: impl std::iter::Iterator<Item = &'a str>,
@@ -99,10 +110,22 @@ impl MinUnit {
         let mut external_declarations = self.static_declarations;
 
         // TODO: Maybe wanted can include other declarations than functions?
-        let wanted: Vec<_> = wanted
-            .map(|id| ExternalIdentifier::FunctionDefinition(id.into()))
+        let source_config = self.global_scope.source_config();
+        let mut wanted: Vec<_> = wanted
+            .map(|id| ExternalIdentifier::Function(id.into()))
+            .chain(
+                source_config
+                    .keep_fns
+                    .iter()
+                    .map(|id| ExternalIdentifier::Function(id.into())),
+            )
             .collect();
 
+        // `#pragma glslt minify` keeps main by default
+        if wanted.is_empty() && source_config.minify {
+            wanted.push(ExternalIdentifier::Function("main".into()));
+        }
+
         // Extract declarations
         let mut stored_decls = self.external_declarations;
 
@@ -110,7 +133,7 @@ impl MinUnit {
         // TODO: filter_map just ignores missing dependencies, we should at least warn the user
         external_declarations.extend(
             self.dag
-                .into_dependencies(&wanted)
+                .into_dependencies(&wanted)?
                 .into_iter()
                 .filter_map(|id| stored_decls.shift_remove(&id)),
         );
@@ -123,15 +146,26 @@ impl MinUnit {
         ))
     }
 
-    fn extend_dag(&mut self, tu: &impl Host) {
+    fn extend_dag(&mut self, tu: &impl Host) -> Result<()> {
         struct VisitorData<'e> {
             current_scope_name: Option<usize>,
+            current_function: Option<SmolStr>,
             this: &'e mut MinUnit,
+            error: Option<Error>,
+        }
+
+        impl VisitorData<'_> {
+            fn add_dep(&mut self, scope: usize, dependency: usize, span: Option<NodeSpan>) {
+                if let Err(error) = self.this.dag.add_dep(scope, dependency, span) {
+                    self.error.get_or_insert(error);
+                }
+            }
         }
 
         impl Visitor for VisitorData<'_> {
             fn visit_external_declaration(&mut self, _node: &ExternalDeclaration) -> Visit {
                 self.current_scope_name = None;
+                self.current_function = None;
 
                 Visit::Children
             }
@@ -151,9 +185,19 @@ impl MinUnit {
             }
 
             fn visit_function_definition(&mut self, node: &FunctionDefinition) -> Visit {
-                self.current_scope_name = Some(self.this.dag.declare_symbol(
-                    ExternalId::FunctionDefinition(node.prototype.name.0.as_str()),
-                ));
+                let name = node.prototype.name.0.as_str();
+                let signature = node.prototype.signature();
+                let this = self
+                    .this
+                    .dag
+                    .declare_symbol(ExternalId::FunctionDefinition(name, signature.as_str()));
+
+                // Using a function by name keeps all its overloads
+                let function = self.this.dag.declare_symbol(ExternalId::Function(name));
+                self.add_dep(function, this, node.span);
+
+                self.current_scope_name = Some(this);
+                self.current_function = Some(node.prototype.name.0.clone());
 
                 Visit::Children
             }
@@ -166,7 +210,7 @@ impl MinUnit {
                         .declare_symbol(ExternalId::Declaration(node.0.as_str()));
 
                     if this != csn {
-                        self.this.dag.add_dep(csn, this);
+                        self.add_dep(csn, this, node.span);
                     }
                 }
 
@@ -176,11 +220,15 @@ impl MinUnit {
             fn visit_fun_identifier(&mut self, node: &FunIdentifier) -> Visit {
                 if let Some(ident) = node.as_ident_or_type_name() {
                     if let Some(csn) = self.current_scope_name {
-                        let this = self
-                            .this
-                            .dag
-                            .declare_symbol(ExternalId::FunctionDefinition(ident.as_str()));
-                        self.this.dag.add_dep(csn, this);
+                        if self.current_function.as_ref() == Some(ident) {
+                            self.this.dag.add_overload_call(csn, node.span);
+                        } else {
+                            let this = self
+                                .this
+                                .dag
+                                .declare_symbol(ExternalId::Function(ident.as_str()));
+                            self.add_dep(csn, this, node.span);
+                        }
                     }
                 }
 
@@ -199,7 +247,7 @@ impl MinUnit {
                             .this
                             .dag
                             .declare_symbol(ExternalId::Declaration(node.0.as_str()));
-                        self.this.dag.add_dep(csn, this);
+                        self.add_dep(csn, this, node.span);
                     }
                 }
 
@@ -209,10 +257,17 @@ impl MinUnit {
 
         let mut visitor = VisitorData {
             current_scope_name: None,
+            current_function: None,
             this: self,
+            error: None,
         };
 
         tu.visit(&mut visitor);
+
+        match visitor.error {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
     }
 }
 
@@ -225,19 +280,20 @@ impl TransformUnit for MinUnit {
         &mut self.global_scope
     }
 
-    fn push_function_declaration(&mut self, def: FunctionDefinition) {
+    fn push_function_declaration(&mut self, def: FunctionDefinition) -> Result<()> {
         // Register the function as a known function
-        self.global_scope
-            .known_functions_mut()
-            .insert(def.prototype.name.0.clone(), def.prototype.clone());
+        self.global_scope.push_function_declaration(&def);
 
         // Register it in the dependency graph
-        self.extend_dag(&def);
+        self.extend_dag(&def)?;
 
         // Add the definition to the declarations
         // TODO: Don't clone def.span?
         let span = def.span;
-        let id = ExternalIdentifier::FunctionDefinition(def.prototype.name.0.clone());
+        let id = ExternalIdentifier::FunctionDefinition(
+            def.prototype.name.0.clone(),
+            def.prototype.signature(),
+        );
         self.external_declarations.insert(
             id,
             Arc::new(ExternalDeclaration::new(
@@ -245,6 +301,8 @@ impl TransformUnit for MinUnit {
                 span,
             )),
         );
+
+        Ok(())
     }
 
     fn parse_external_declaration(
@@ -252,7 +310,7 @@ impl TransformUnit for MinUnit {
         extdecl: ExternalDeclaration,
     ) -> Result<Option<FnHandle>> {
         let unparsed = match self.global_scope.parse_external_declaration(extdecl)? {
-            ParsedDeclaration::ConsumedAsType => {
+            ParsedDeclaration::ConsumedAsType | ParsedDeclaration::ConsumedAsPragma => {
                 return Ok(None);
             }
             ParsedDeclaration::ConsumedAsTemplate(r) => {
@@ -266,28 +324,31 @@ impl TransformUnit for MinUnit {
             ExternalDeclarationData::FunctionDefinition(def) => {
                 // No template parameter, it's a "regular" function so it has to be
                 // processed to instantiate parameters
+                let id = ExternalIdentifier::FunctionDefinition(
+                    def.prototype.name.0.clone(),
+                    def.prototype.signature(),
+                );
                 let decls =
                     InstantiateTemplate::new(None).instantiate(&mut self.global_scope, def)?;
 
                 for d in decls {
-                    self.push_function_declaration(d);
+                    self.push_function_declaration(d)?;
                 }
 
-                let f = self.external_declarations.last().unwrap();
-                match &f.1.content {
-                    ExternalDeclarationData::FunctionDefinition(def) => {
-                        return Ok(Some(
-                            Node::new(
-                                FnRef {
-                                    prototype: &def.prototype,
-                                    statement: &def.statement,
-                                },
-                                extdecl.span,
-                            )
-                            .into(),
-                        ));
-                    }
-                    _ => unreachable!(),
+                // Redefinitions keep their position in the map, so look the function up by signature
+                if let Some(ExternalDeclarationData::FunctionDefinition(def)) =
+                    self.external_declarations.get(&id).map(|f| &f.content)
+                {
+                    return Ok(Some(
+                        Node::new(
+                            FnRef {
+                                prototype: &def.prototype,
+                                statement: &def.statement,
+                            },
+                            extdecl.span,
+                        )
+                        .into(),
+                    ));
                 }
             }
             other => match other {
@@ -305,7 +366,8 @@ impl TransformUnit for MinUnit {
                 },
                 ExternalDeclarationData::Declaration(ref decl) => match &decl.content {
                     DeclarationData::FunctionPrototype(_) => {
-                        unreachable!("prototype already consumed by template engine")
+                        // Forward declarations aren't needed since functions are emitted in
+                        // dependency order
                     }
                     DeclarationData::InitDeclaratorList(idl) => {
                         // TODO: Handle variable declarations at top-level using
@@ -319,7 +381,7 @@ impl TransformUnit for MinUnit {
                                 let node = Node::new(other, extdecl.span);
 
                                 // Parse type name dependencies in the struct specification
-                                self.extend_dag(&node);
+                                self.extend_dag(&node)?;
 
                                 self.external_declarations.insert(key, Arc::new(node));
                             } else {
@@ -1,18 +1,34 @@
-use glsl_lang::ast::SmolStr;
+use glsl_lang::ast::{NodeSpan, SmolStr};
 use petgraph::graph::NodeIndex;
 
+use crate::{Error, Result};
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum ExternalIdentifier {
-    /// Function definition
-    FunctionDefinition(SmolStr),
+    /// Function definition, identified by its name and parameter signature
+    FunctionDefinition(SmolStr, SmolStr),
+    /// All the overloads of a function
+    Function(SmolStr),
     /// Standalone declaration
     Declaration(SmolStr),
 }
 
+impl ExternalIdentifier {
+    fn name(&self) -> &SmolStr {
+        match self {
+            Self::FunctionDefinition(name, _) | Self::Function(name) | Self::Declaration(name) => {
+                name
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum ExternalId<'a> {
-    /// Function definition
-    FunctionDefinition(&'a str),
+    /// Function definition, identified by its name and parameter signature
+    FunctionDefinition(&'a str, &'a str),
+    /// All the overloads of a function
+    Function(&'a str),
     /// Standalone declaration
     Declaration(&'a str),
 }
@@ -20,7 +36,10 @@ pub enum ExternalId<'a> {
 impl<'a> ExternalId<'a> {
     pub fn to_owned(&self) -> ExternalIdentifier {
         match self {
-            Self::FunctionDefinition(sym) => ExternalIdentifier::FunctionDefinition((*sym).into()),
+            Self::FunctionDefinition(sym, signature) => {
+                ExternalIdentifier::FunctionDefinition((*sym).into(), (*signature).into())
+            }
+            Self::Function(sym) => ExternalIdentifier::Function((*sym).into()),
             Self::Declaration(sym) => ExternalIdentifier::Declaration((*sym).into()),
         }
     }
@@ -30,6 +49,9 @@ impl<'a> ExternalId<'a> {
 pub struct DependencyDag {
     symbol_map: bimap::BiMap<ExternalIdentifier, usize>,
     graph: petgraph::Graph<(), (), petgraph::Directed>,
+    /// Calls from a function to another overload with the same name, resolved once all the
+    /// overloads are known
+    overload_calls: Vec<(usize, Option<NodeSpan>)>,
 }
 
 impl DependencyDag {
@@ -49,21 +71,91 @@ impl DependencyDag {
         }
     }
 
-    pub fn add_dep(&mut self, scope: usize, dependency: usize) {
-        // Self-reference makes no sense here
-        assert!(scope != dependency);
+    pub fn add_dep(
+        &mut self,
+        scope: usize,
+        dependency: usize,
+        span: Option<NodeSpan>,
+    ) -> Result<()> {
+        // Self-reference makes no sense here, GLSL functions can't be recursive
+        if scope == dependency {
+            let name = self
+                .symbol_map
+                .get_by_right(&scope)
+                .map(|symbol| symbol.name().clone())
+                .unwrap_or_default();
+
+            return Err(Error::RecursiveFunction { name, span });
+        }
 
         trace!(
             "DependencyDag: add_dep {:?} -> {:?}",
-            self.symbol_map.get_by_right(&scope).unwrap(),
-            self.symbol_map.get_by_right(&dependency).unwrap()
+            self.symbol_map.get_by_right(&scope),
+            self.symbol_map.get_by_right(&dependency)
         );
 
         self.graph
             .add_edge(NodeIndex::new(scope), NodeIndex::new(dependency), ());
+
+        Ok(())
     }
 
-    pub fn into_dependencies(mut self, wanted: &[ExternalIdentifier]) -> Vec<ExternalIdentifier> {
+    /// Record a call from the function `scope` to a function with the same name
+    ///
+    /// Since GLSL functions can't be recursive, this is a call to another overload.
+    pub fn add_overload_call(&mut self, scope: usize, span: Option<NodeSpan>) {
+        self.overload_calls.push((scope, span));
+    }
+
+    pub fn into_dependencies(
+        mut self,
+        wanted: &[ExternalIdentifier],
+    ) -> Result<Vec<ExternalIdentifier>> {
+        // The called overload isn't resolved, so a call to another overload depends on all of
+        // them. Overloads declared before the caller come first, as GLSL requires. Overloads
+        // declared after it are only added when they don't depend on the caller, so the output
+        // order stays valid.
+        let mut later_overloads = Vec::new();
+        for (scope, span) in std::mem::take(&mut self.overload_calls) {
+            let name = match self.symbol_map.get_by_right(&scope) {
+                Some(ExternalIdentifier::FunctionDefinition(name, _)) => name.clone(),
+                _ => continue,
+            };
+
+            let overloads: Vec<_> = self
+                .symbol_map
+                .iter()
+                .filter(|(symbol, id)| {
+                    **id != scope
+                        && matches!(symbol, ExternalIdentifier::FunctionDefinition(n, _) if *n == name)
+                })
+                .map(|(_, id)| *id)
+                .collect();
+
+            if overloads.is_empty() {
+                return Err(Error::RecursiveFunction { name, span });
+            }
+
+            for dependency in overloads {
+                if dependency < scope {
+                    self.add_dep(scope, dependency, span)?;
+                } else {
+                    later_overloads.push((scope, dependency, span));
+                }
+            }
+        }
+
+        for (scope, dependency, span) in later_overloads {
+            if !petgraph::algo::has_path_connecting(
+                &self.graph,
+                NodeIndex::new(dependency),
+                NodeIndex::new(scope),
+                None,
+            ) {
+                self.add_dep(scope, dependency, span)?;
+            }
+        }
+
         // Create a wanted node
         let wanted_id = self.graph.add_node(());
 
@@ -86,6 +178,6 @@ impl DependencyDag {
             }
         }
 
-        res
+        Ok(res)
     }
 }
@@ -0,0 +1,213 @@
+//! Namespaced declarations
+
+use std::collections::{HashMap, HashSet};
+
+use glsl_lang::{ast::*, visitor::*};
+
+use super::shadow::{visit_scoped, BlockScopes, ScopedVisitorMut};
+
+use crate::{glsl_ext::FunctionPrototypeExt, Error, Result};
+
+/// Return the output name of a name declared in a namespace
+///
+/// # Parameters
+///
+/// * `namespace`: path of the namespace, as returned by [namespace_path]
+/// * `name`: name of the declaration in its namespace
+pub fn namespaced_name(namespace: &str, name: &str) -> SmolStr {
+    format!("{}_{}", namespace, name).into()
+}
+
+/// Return the path of a namespace nested in another one
+///
+/// # Parameters
+///
+/// * `parent`: path of the enclosing namespace, if any
+/// * `name`: name of the nested namespace
+pub fn namespace_path(parent: Option<&SmolStr>, name: &str) -> SmolStr {
+    match parent {
+        Some(parent) => namespaced_name(parent, name),
+        None => name.into(),
+    }
+}
+
+/// Return the names of the functions, pointer types and structs declared by a top-level
+/// declaration
+///
+/// Private functions are already renamed per file, and global variables are not namespaced.
+pub fn declared_names(extdecl: &ExternalDeclaration) -> Vec<&SmolStr> {
+    match &extdecl.content {
+        ExternalDeclarationData::FunctionDefinition(def) if !def.prototype.is_private() => {
+            vec![&def.prototype.name.0]
+        }
+        ExternalDeclarationData::Declaration(decl) => match &decl.content {
+            DeclarationData::FunctionPrototype(prototype) => vec![&prototype.name.0],
+            DeclarationData::InitDeclaratorList(idl) => match &*idl.head.ty.ty.ty {
+                TypeSpecifierNonArrayData::Struct(ss) => {
+                    ss.name.iter().map(|name| &name.0).collect()
+                }
+                _ => Vec::new(),
+            },
+            _ => Vec::new(),
+        },
+        _ => Vec::new(),
+    }
+}
+
+/// Rename the namespaced names used by a declaration
+///
+/// Names declared in the current namespace are replaced with their output names, and so are
+/// qualified names written `namespace.name`. A qualified name is looked up in the namespaces
+/// nested in the current one first. Identifiers shadowed by parameters, local variables or
+/// global variables are left untouched, so field selections on variables still work.
+///
+/// # Parameters
+///
+/// * `extdecl`: declaration to transform
+/// * `current`: path of the namespace of the declaration, if any
+/// * `namespaces`: output names of the declarations of each namespace, by namespace path
+/// * `is_variable`: returns `true` if the given name is a global variable
+///
+/// # Errors
+///
+/// Return [Error::UnknownQualifiedName] if a qualified name isn't declared in its namespace.
+pub fn rename_namespaced_names(
+    extdecl: &mut ExternalDeclaration,
+    current: Option<&SmolStr>,
+    namespaces: &HashMap<SmolStr, HashMap<SmolStr, SmolStr>>,
+    is_variable: &dyn Fn(&str) -> bool,
+) -> Result<()> {
+    struct RenameDeclarations<'n> {
+        names: &'n HashMap<SmolStr, SmolStr>,
+    }
+
+    impl VisitorMut for RenameDeclarations<'_> {
+        fn visit_function_prototype(&mut self, node: &mut FunctionPrototype) -> Visit {
+            if let Some(name) = self.names.get(&node.name.0) {
+                node.name.0 = name.clone();
+            }
+
+            Visit::Children
+        }
+
+        fn visit_type_name(&mut self, node: &mut TypeName) -> Visit {
+            if let Some(name) = self.names.get(&node.0) {
+                node.0 = name.clone();
+            }
+
+            Visit::Parent
+        }
+    }
+
+    struct RenameReferences<'n> {
+        current: Option<&'n SmolStr>,
+        namespaces: &'n HashMap<SmolStr, HashMap<SmolStr, SmolStr>>,
+        is_variable: &'n dyn Fn(&str) -> bool,
+        parameters: HashSet<SmolStr>,
+        error: Option<Error>,
+    }
+
+    impl RenameReferences<'_> {
+        fn is_shadowed(&self, name: &str, scopes: &BlockScopes) -> bool {
+            self.parameters.contains(name) || scopes.is_shadowed(name)
+        }
+
+        fn resolve_namespace(&self, name: &str) -> Option<(&SmolStr, &HashMap<SmolStr, SmolStr>)> {
+            self.current
+                .map(|current| namespace_path(Some(current), name))
+                .into_iter()
+                .chain(std::iter::once(SmolStr::from(name)))
+                .find_map(|path| self.namespaces.get_key_value(&path))
+        }
+    }
+
+    impl ScopedVisitorMut for RenameReferences<'_> {
+        fn visit_expr(&mut self, node: &mut Expr, scopes: &BlockScopes) -> Visit {
+            let span = node.span;
+
+            match &mut **node {
+                // Qualified names parse as field selections on the namespace
+                ExprData::Dot(target, field) => {
+                    let ExprData::Variable(namespace) = &***target else {
+                        return Visit::Children;
+                    };
+
+                    if self.is_shadowed(&namespace.0, scopes) || (self.is_variable)(&namespace.0) {
+                        return Visit::Children;
+                    }
+
+                    if let Some((path, names)) = self.resolve_namespace(&namespace.0) {
+                        match names.get(&field.0) {
+                            Some(name) => {
+                                let mut ident = IdentifierData(name.clone()).into_node();
+                                ident.span = span;
+                                **node = ExprData::Variable(ident);
+                            }
+                            None => {
+                                self.error.get_or_insert(Error::UnknownQualifiedName {
+                                    namespace: path.clone(),
+                                    name: field.0.clone(),
+                                    span,
+                                });
+                            }
+                        }
+
+                        return Visit::Parent;
+                    }
+                }
+                ExprData::Variable(ident) => {
+                    if let Some(names) = self
+                        .current
+                        .and_then(|current| self.namespaces.get(current))
+                    {
+                        if !self.is_shadowed(&ident.0, scopes) {
+                            if let Some(name) = names.get(&ident.0) {
+                                ident.0 = name.clone();
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+
+            Visit::Children
+        }
+    }
+
+    if namespaces.is_empty() {
+        return Ok(());
+    }
+
+    // Parameters shadow namespaced names in the whole body
+    let parameters = match &**extdecl {
+        ExternalDeclarationData::FunctionDefinition(def) => def
+            .prototype
+            .parameters
+            .iter()
+            .filter_map(|parameter| match &**parameter {
+                FunctionParameterDeclarationData::Named(_, p) => Some(p.ident.ident.0.clone()),
+                FunctionParameterDeclarationData::Unnamed(_, _) => None,
+            })
+            .collect(),
+        _ => HashSet::new(),
+    };
+
+    let mut references = RenameReferences {
+        current,
+        namespaces,
+        is_variable,
+        parameters,
+        error: None,
+    };
+
+    visit_scoped(extdecl, &mut references);
+    if let Some(error) = references.error {
+        return Err(error);
+    }
+
+    if let Some(names) = current.and_then(|current| namespaces.get(current)) {
+        extdecl.visit_mut(&mut RenameDeclarations { names });
+    }
+
+    Ok(())
+}
@@ -0,0 +1,347 @@
+//! Overload resolution for template calls
+
+use std::sync::Arc;
+
+use glsl_lang::ast::*;
+
+use indexmap::IndexMap;
+
+use super::{
+    instantiate::DeclaredSymbol,
+    template::{BoundArgument, TemplateDefinition, TemplateParameterKind},
+    ResolvedArgumentExpr, Scope,
+};
+
+use crate::{
+    glsl_ext::{FunIdentifierExt, FunctionParameterDeclarationExt, FunctionPrototypeExt},
+    Error, Result,
+};
+
+/// Infer the type of an expression, if it can be done without a full type checker
+///
+/// # Parameters
+///
+/// * `expr`: expression to infer the type of
+/// * `symbol_table`: symbols declared in the current function
+/// * `scope`: scope the functions called by the expression are resolved in
+pub(crate) fn infer_type(
+    expr: &Expr,
+    symbol_table: &IndexMap<SmolStr, DeclaredSymbol>,
+    scope: &dyn Scope,
+) -> Option<TypeSpecifierNonArrayData> {
+    match &**expr {
+        ExprData::Variable(ident) => symbol_table
+            .get(ident.0.as_str())
+            .filter(|sb| sb.array.is_none() && sb.decl_type.array_specifier.is_none())
+            .map(|sb| (*sb.decl_type.ty).clone()),
+        ExprData::IntConst(_) => Some(TypeSpecifierNonArrayData::Int),
+        ExprData::UIntConst(_) => Some(TypeSpecifierNonArrayData::UInt),
+        ExprData::BoolConst(_) => Some(TypeSpecifierNonArrayData::Bool),
+        ExprData::FloatConst(_) => Some(TypeSpecifierNonArrayData::Float),
+        ExprData::DoubleConst(_) => Some(TypeSpecifierNonArrayData::Double),
+        ExprData::Unary(op, e) => match &**op {
+            UnaryOpData::Not => Some(TypeSpecifierNonArrayData::Bool),
+            _ => infer_type(e, symbol_table, scope),
+        },
+        ExprData::Binary(op, a, b) => match &**op {
+            BinaryOpData::Or
+            | BinaryOpData::Xor
+            | BinaryOpData::And
+            | BinaryOpData::Equal
+            | BinaryOpData::NonEqual
+            | BinaryOpData::Lt
+            | BinaryOpData::Gt
+            | BinaryOpData::Lte
+            | BinaryOpData::Gte => Some(TypeSpecifierNonArrayData::Bool),
+            _ => {
+                let a = infer_type(a, symbol_table, scope)?;
+                let b = infer_type(b, symbol_table, scope)?;
+                if a == b {
+                    Some(a)
+                } else {
+                    None
+                }
+            }
+        },
+        ExprData::Ternary(_, a, b) => {
+            let a = infer_type(a, symbol_table, scope)?;
+            let b = infer_type(b, symbol_table, scope)?;
+            if a == b {
+                Some(a)
+            } else {
+                None
+            }
+        }
+        ExprData::FunCall(fun, _) => match &**fun {
+            // Constructors return the constructed type
+            FunIdentifierData::TypeSpecifier(ts) if ts.array_specifier.is_none() => {
+                match &*ts.ty {
+                    // Prototype names are type names too, but struct constructors aren't
+                    // known functions
+                    TypeSpecifierNonArrayData::TypeName(tn) => {
+                        function_return_type(tn.0.as_str(), scope)
+                            .or_else(|| Some(ts.ty.content.clone()))
+                    }
+                    other => Some(other.clone()),
+                }
+            }
+            _ => fun
+                .as_ident_or_type_name()
+                .and_then(|name| function_return_type(name.as_str(), scope)),
+        },
+        _ => None,
+    }
+}
+
+/// Return `Some(true)` if the argument is the name of a function which has an overload with the
+/// same parameters as the pointer type, `Some(false)` if it has none, and `None` if it isn't the
+/// name of a known function
+fn function_matches_pointer_type(
+    arg: &Expr,
+    pointer_type: &str,
+    symbol_table: &IndexMap<SmolStr, DeclaredSymbol>,
+    scope: &dyn Scope,
+) -> Option<bool> {
+    let ident = match &**arg {
+        ExprData::Variable(ident) if !symbol_table.contains_key(&ident.0) => ident,
+        _ => return None,
+    };
+
+    let name = match scope.resolve_function_name(ident.0.as_str())?.body {
+        ResolvedArgumentExpr::FunctionName(name) => name,
+        ResolvedArgumentExpr::Lambda(_) => return None,
+    };
+
+    let overloads = scope.get_function_overloads(name.as_str());
+    if overloads.is_empty() {
+        return None;
+    }
+
+    let pointer_types = scope.declared_pointer_types().get(pointer_type)?;
+    Some(overloads.iter().any(|overload| {
+        pointer_types
+            .iter()
+            .any(|pointer_type| overload.same_parameter_types(pointer_type))
+    }))
+}
+
+/// Return the type returned by a known function, if it isn't an array
+///
+/// Overloads of a function may return different types, in which case `None` is returned.
+fn function_return_type(name: &str, scope: &dyn Scope) -> Option<TypeSpecifierNonArrayData> {
+    if let [first, rest @ ..] = scope.get_function_overloads(name) {
+        if rest.iter().any(|overload| overload.ty.ty != first.ty.ty) {
+            return None;
+        }
+    }
+
+    let resolved = scope.resolve_function_name(name)?;
+    let ty = &resolved.pointer_type.ty.ty;
+
+    if ty.array_specifier.is_none() {
+        Some((*ty.ty).clone())
+    } else {
+        None
+    }
+}
+
+/// Return `true` if a value of type `from` is implicitly converted to `to` in function calls
+fn is_implicitly_convertible(
+    from: &TypeSpecifierNonArrayData,
+    to: &TypeSpecifierNonArrayData,
+) -> bool {
+    use TypeSpecifierNonArrayData::*;
+
+    matches!(
+        (from, to),
+        (Int | UInt, Float | Double)
+            | (Float, Double)
+            | (IVec2 | UVec2, Vec2 | DVec2)
+            | (IVec3 | UVec3, Vec3 | DVec3)
+            | (IVec4 | UVec4, Vec4 | DVec4)
+            | (Vec2, DVec2)
+            | (Vec3, DVec3)
+            | (Vec4, DVec4)
+    )
+}
+
+/// Select the overload of a pointer type called with the given arguments
+///
+/// Overloads with the same number of parameters as there are arguments are scored like template
+/// overloads, by the number of arguments whose type exactly matches the parameter type. If no
+/// overload accepts the arguments, the first one is returned.
+///
+/// # Parameters
+///
+/// * `scope`: scope the call arguments are resolved in
+/// * `pointer_type`: name of the pointer type
+/// * `args`: arguments of the call
+/// * `symbol_table`: symbols declared in the calling function
+pub(crate) fn get_pointer_type_for_call<'s>(
+    scope: &'s dyn Scope,
+    pointer_type: &str,
+    args: &[Expr],
+    symbol_table: &IndexMap<SmolStr, DeclaredSymbol>,
+) -> Option<&'s FunctionPrototype> {
+    let overloads = scope.declared_pointer_types().get(pointer_type)?;
+
+    let score = |prototype: &FunctionPrototype| -> Option<usize> {
+        if prototype.parameters.len() != args.len() {
+            return None;
+        }
+
+        let mut score = 0;
+        for (parameter, arg) in prototype.parameters.iter().zip(args) {
+            let (ty, array) = parameter.parameter_type();
+            if array.is_some() || ty.array_specifier.is_some() {
+                continue;
+            }
+
+            if let Some(arg_ty) = infer_type(arg, symbol_table, scope) {
+                if arg_ty == *ty.ty {
+                    score += 1;
+                } else if !is_implicitly_convertible(&arg_ty, &ty.ty) {
+                    return None;
+                }
+            }
+        }
+
+        Some(score)
+    };
+
+    // The first overload wins ties
+    overloads
+        .iter()
+        .filter_map(|prototype| score(prototype).map(|score| (score, prototype)))
+        .rev()
+        .max_by_key(|(score, _)| *score)
+        .map(|(_, prototype)| prototype)
+        .or_else(|| overloads.first())
+}
+
+/// Score a template overload for the given call arguments
+///
+/// Returns `None` if the overload can't be called with these arguments, or the number of
+/// arguments whose type exactly matches the parameter type.
+fn score_overload(
+    template: &TemplateDefinition,
+    args: &[Expr],
+    symbol_table: &IndexMap<SmolStr, DeclaredSymbol>,
+    scope: &dyn Scope,
+) -> Option<usize> {
+    let parameters = &template.raw_prototype().parameters;
+
+    // Named and omitted template arguments can't be told apart otherwise
+    let args: Vec<_> = template
+        .bind_arguments(args, symbol_table, scope)
+        .ok()??
+        .into_iter()
+        .map(BoundArgument::into_expr)
+        .collect();
+
+    let mut score = 0;
+    for (index, (parameter, arg)) in parameters.iter().zip(&args).enumerate() {
+        if let Some(p) = template
+            .parameters()
+            .iter()
+            .find(|p| p.index == index && p.kind == TemplateParameterKind::Function)
+        {
+            if let Some(interface) = &p.interface {
+                // Interface parameters only accept constructor expressions of their interface
+                match &**arg {
+                    ExprData::FunCall(fun, _)
+                        if fun.as_ident_or_type_name().map(|ident| ident.as_str())
+                            == Some(interface.as_str()) =>
+                    {
+                        score += 1
+                    }
+                    _ => return None,
+                }
+
+                continue;
+            }
+
+            // Function template parameters accept any lambda expression, but functions passed by
+            // name need an overload matching the pointer type
+            match function_matches_pointer_type(arg, &p.typename, symbol_table, scope) {
+                Some(true) => score += 1,
+                Some(false) => return None,
+                None => {}
+            }
+
+            continue;
+        }
+
+        if template.is_type_parameter_typed(index) {
+            // Type template parameters accept any type
+            continue;
+        }
+
+        let (ty, array) = parameter.parameter_type();
+        if array.is_some() || ty.array_specifier.is_some() {
+            continue;
+        }
+
+        if let Some(arg_ty) = infer_type(arg, symbol_table, scope) {
+            if arg_ty == *ty.ty {
+                score += 1;
+            } else if !is_implicitly_convertible(&arg_ty, &ty.ty) {
+                return None;
+            }
+        }
+    }
+
+    Some(score)
+}
+
+/// Select the template overload to use for a call
+///
+/// # Parameters
+///
+/// * `templates`: overloads of the called template
+/// * `args`: list of all function call arguments
+/// * `symbol_table`: symbols declared in the calling function
+/// * `scope`: scope the call arguments are resolved in
+///
+/// # Returns
+///
+/// The selected overload, or `None` if no overload accepts these arguments, in which case the
+/// call is not a template call. If the template is not overloaded, it is always selected.
+///
+/// # Errors
+///
+/// Returns [Error::AmbiguousTemplateCall] if several overloads match equally well.
+pub(crate) fn select_overload(
+    templates: &[Arc<TemplateDefinition>],
+    args: &[Expr],
+    symbol_table: &IndexMap<SmolStr, DeclaredSymbol>,
+    scope: &dyn Scope,
+) -> Result<Option<Arc<TemplateDefinition>>> {
+    if let [template] = templates {
+        // Templates which aren't overloaded are always selected
+        return Ok(Some(template.clone()));
+    }
+
+    let candidates: Vec<_> = templates
+        .iter()
+        .filter_map(|template| {
+            score_overload(template, args, symbol_table, scope).map(|score| (score, template))
+        })
+        .collect();
+
+    let best = match candidates.iter().map(|(score, _)| *score).max() {
+        Some(best) => best,
+        None => return Ok(None),
+    };
+
+    let mut best_candidates = candidates.into_iter().filter(|(score, _)| *score == best);
+    let (_, selected) = best_candidates.next().unwrap();
+
+    if best_candidates.next().is_some() {
+        return Err(Error::AmbiguousTemplateCall(
+            selected.ast().prototype.name.0.clone(),
+        ));
+    }
+
+    Ok(Some(selected.clone()))
+}
@@ -0,0 +1,361 @@
+//! Variadic template parameters
+
+use glsl_lang::{
+    ast::*,
+    visitor::{HostMut, Visit, VisitorMut},
+};
+
+use super::shadow::{visit_scoped, BlockScopes, ScopedVisitorMut};
+
+/// Return the name of the template parameter which stands for an element of a parameter pack
+///
+/// The name is not a valid GLSL identifier, so it can't conflict with the template body.
+pub fn element_symbol(pack: &str, index: usize) -> SmolStr {
+    format!("{}[{}]", pack, index).into()
+}
+
+/// Expand the uses of a parameter pack in a template body
+///
+/// * `pack.length()` becomes the number of elements in the pack
+/// * `pack[i]` becomes the template parameter for the `i`-th element, `i` being an integer
+///   constant
+/// * `pack` used as a call argument becomes the list of all the elements
+/// * `for (int i = start; i < pack.length(); ++i)` loops are unrolled, replacing `i` with its
+///   value in each copy of the loop body. `start` may not be negative, and the body may not
+///   `break` or `continue` the unrolled loop, nor write to `i`.
+///
+/// # Parameters
+///
+/// * `body`: body of the template
+/// * `pack`: name of the parameter pack
+/// * `count`: number of elements in the pack
+/// * `is_output`: returns whether the parameter at the given index of the named function is an
+///   `out` or `inout` parameter, or `None` if the function is unknown
+///
+/// # Returns
+///
+/// `false` if the pack is used in any other way, in which case it can't be expanded.
+pub(crate) fn expand_pack(
+    body: &mut CompoundStatement,
+    pack: &str,
+    count: usize,
+    is_output: &dyn Fn(&str, usize) -> Option<bool>,
+) -> bool {
+    let mut expand = ExpandPack {
+        pack,
+        count,
+        is_output,
+        valid: true,
+    };
+
+    body.visit_mut(&mut expand);
+    expand.valid
+}
+
+struct ExpandPack<'p> {
+    pack: &'p str,
+    count: usize,
+    is_output: &'p dyn Fn(&str, usize) -> Option<bool>,
+    valid: bool,
+}
+
+impl ExpandPack<'_> {
+    fn is_pack(&self, expr: &Expr) -> bool {
+        matches!(&**expr, ExprData::Variable(ident) if ident.0 == self.pack)
+    }
+
+    /// Return `true` if the expression is `pack.length()`
+    fn is_length(&self, expr: &Expr) -> bool {
+        match &**expr {
+            ExprData::FunCall(fun, args) if args.is_empty() => match &**fun {
+                FunIdentifierData::Expr(fun) => match &***fun {
+                    ExprData::Dot(e, method) => method.0 == "length" && self.is_pack(e),
+                    _ => false,
+                },
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Return the loop variable and its start value if the loop iterates over the pack
+    fn unrolled_loop(
+        &self,
+        init: &ForInitStatement,
+        rest: &ForRestStatement,
+    ) -> Option<(SmolStr, i32)> {
+        let (var, start) = match &**init {
+            ForInitStatementData::Declaration(decl) => match &***decl {
+                DeclarationData::InitDeclaratorList(idl)
+                    if idl.tail.is_empty()
+                        && matches!(*idl.head.ty.ty.ty, TypeSpecifierNonArrayData::Int) =>
+                {
+                    match (&idl.head.name, idl.head.initializer.as_deref()) {
+                        (Some(name), Some(InitializerData::Simple(start))) => match &***start {
+                            ExprData::IntConst(start) => (name.0.clone(), *start),
+                            ExprData::Unary(op, start) if matches!(**op, UnaryOpData::Minus) => {
+                                match &***start {
+                                    ExprData::IntConst(start) => (name.0.clone(), -*start),
+                                    _ => return None,
+                                }
+                            }
+                            _ => return None,
+                        },
+                        _ => return None,
+                    }
+                }
+                _ => return None,
+            },
+            ForInitStatementData::Expression(_) => return None,
+        };
+
+        let is_var = |expr: &Expr| matches!(&**expr, ExprData::Variable(ident) if ident.0 == var);
+
+        // i < pack.length()
+        match rest.condition.as_deref() {
+            Some(ConditionData::Expr(cond)) => match &**cond {
+                ExprData::Binary(op, lhs, rhs)
+                    if matches!(**op, BinaryOpData::Lt) && is_var(lhs) && self.is_length(rhs) => {}
+                _ => return None,
+            },
+            _ => return None,
+        }
+
+        // ++i, i++ or i += 1
+        match rest.post_expr.as_deref().map(|expr| &**expr) {
+            Some(ExprData::Unary(op, e)) if matches!(**op, UnaryOpData::Inc) && is_var(e) => {}
+            Some(ExprData::PostInc(e)) if is_var(e) => {}
+            Some(ExprData::Assignment(lhs, op, rhs))
+                if matches!(**op, AssignmentOpData::Add)
+                    && is_var(lhs)
+                    && matches!(***rhs, ExprData::IntConst(1)) => {}
+            _ => return None,
+        }
+
+        Some((var, start))
+    }
+
+    /// Return `true` if the statement may write to the variable `var`, except where it is
+    /// shadowed
+    fn writes_variable(&self, stmt: &Statement, var: &str) -> bool {
+        let mut writes = WritesVariable {
+            var,
+            pack: self.pack,
+            is_output: self.is_output,
+            writes: false,
+        };
+
+        visit_scoped(&mut stmt.clone(), &mut writes);
+        writes.writes
+    }
+}
+
+impl VisitorMut for ExpandPack<'_> {
+    fn visit_statement(&mut self, stmt: &mut Statement) -> Visit {
+        if let StatementData::Iteration(iteration) = &**stmt {
+            if let IterationStatementData::For(init, rest, body) = &**iteration {
+                if let Some((var, start)) = self.unrolled_loop(init, rest) {
+                    // The copies of the body are not in a loop anymore, and the loop variable
+                    // is replaced by a constant
+                    if start < 0 || escapes_loop(body, false) || self.writes_variable(body, &var) {
+                        self.valid = false;
+                        return Visit::Parent;
+                    }
+
+                    let statement_list = (start as usize..self.count)
+                        .map(|i| {
+                            let mut body = (**body).clone();
+                            visit_scoped(
+                                &mut body,
+                                SubstituteLoopVariable {
+                                    var: &var,
+                                    value: i as _,
+                                },
+                            );
+                            body
+                        })
+                        .collect();
+
+                    // The unrolled body is then visited for uses of the pack
+                    stmt.content =
+                        StatementData::Compound(CompoundStatementData { statement_list }.into());
+                }
+            }
+        }
+
+        Visit::Children
+    }
+
+    fn visit_expr(&mut self, expr: &mut Expr) -> Visit {
+        if self.is_length(expr) {
+            expr.content = ExprData::IntConst(self.count as _);
+            return Visit::Parent;
+        }
+
+        if let ExprData::Bracket(e, index) = &**expr {
+            if self.is_pack(e) {
+                match &***index {
+                    ExprData::IntConst(index) if (0..self.count as i32).contains(index) => {
+                        let symbol = element_symbol(self.pack, *index as _);
+                        expr.content = ExprData::Variable(IdentifierData(symbol).into());
+                    }
+                    _ => self.valid = false,
+                }
+
+                return Visit::Parent;
+            }
+        }
+
+        match &mut **expr {
+            ExprData::FunCall(_, args) if args.iter().any(|arg| self.is_pack(arg)) => {
+                // The pack is forwarded as a list of arguments
+                let pack = self.pack;
+                let count = self.count;
+
+                *args = std::mem::take(args)
+                    .into_iter()
+                    .flat_map(|arg| {
+                        if matches!(&*arg, ExprData::Variable(ident) if ident.0 == pack) {
+                            (0..count)
+                                .map(|i| {
+                                    Node::new(
+                                        ExprData::Variable(
+                                            IdentifierData(element_symbol(pack, i)).into(),
+                                        ),
+                                        arg.span,
+                                    )
+                                })
+                                .collect()
+                        } else {
+                            vec![arg]
+                        }
+                    })
+                    .collect();
+            }
+            ExprData::Variable(ident) if ident.0 == self.pack => {
+                self.valid = false;
+            }
+            _ => {}
+        }
+
+        Visit::Children
+    }
+}
+
+/// Return `true` if the statement contains a `break` or `continue` which would leave the
+/// enclosing loop
+///
+/// # Parameters
+///
+/// * `stmt`: statement to inspect
+/// * `in_switch`: `true` if `stmt` is in a switch of its own, so `break` leaves the switch
+fn escapes_loop(stmt: &Statement, in_switch: bool) -> bool {
+    match &**stmt {
+        StatementData::Jump(jump) => match &**jump {
+            JumpStatementData::Break => !in_switch,
+            JumpStatementData::Continue => true,
+            _ => false,
+        },
+        StatementData::Compound(cs) => cs
+            .statement_list
+            .iter()
+            .any(|stmt| escapes_loop(stmt, in_switch)),
+        StatementData::Selection(selection) => match &*selection.rest {
+            SelectionRestStatementData::Statement(then) => escapes_loop(then, in_switch),
+            SelectionRestStatementData::Else(then, otherwise) => {
+                escapes_loop(then, in_switch) || escapes_loop(otherwise, in_switch)
+            }
+        },
+        StatementData::Switch(switch) => switch.body.iter().any(|stmt| escapes_loop(stmt, true)),
+        // Jumps in nested loops stay in these loops
+        _ => false,
+    }
+}
+
+/// Finds writes to the variable of an unrolled loop
+struct WritesVariable<'v> {
+    var: &'v str,
+    pack: &'v str,
+    is_output: &'v dyn Fn(&str, usize) -> Option<bool>,
+    writes: bool,
+}
+
+impl WritesVariable<'_> {
+    fn is_var(&self, mut target: &Expr, scopes: &BlockScopes) -> bool {
+        // Writing to a component writes to the whole variable
+        loop {
+            match &**target {
+                ExprData::Dot(e, _) | ExprData::Bracket(e, _) => target = e,
+                ExprData::Variable(ident) => {
+                    return ident.0 == self.var && !scopes.is_shadowed(self.var)
+                }
+                _ => return false,
+            }
+        }
+    }
+
+    /// Return `true` if the argument at `index` of a call to `fun` may be written to
+    fn is_output(&self, fun: &FunIdentifier, index: usize) -> bool {
+        match &**fun {
+            FunIdentifierData::Expr(expr) => match &***expr {
+                ExprData::Variable(ident) => (self.is_output)(&ident.0, index).unwrap_or(true),
+                ExprData::Bracket(e, _) => match &***e {
+                    ExprData::Variable(ident) if ident.0 == self.pack => {
+                        (self.is_output)(self.pack, index).unwrap_or(true)
+                    }
+                    _ => true,
+                },
+                _ => true,
+            },
+            // Constructors don't write to their arguments
+            FunIdentifierData::TypeSpecifier(ts) => match &*ts.ty {
+                TypeSpecifierNonArrayData::TypeName(tn) => {
+                    (self.is_output)(&tn.0, index).unwrap_or(false)
+                }
+                _ => false,
+            },
+        }
+    }
+}
+
+impl ScopedVisitorMut for WritesVariable<'_> {
+    fn visit_expr(&mut self, expr: &mut Expr, scopes: &BlockScopes) -> Visit {
+        match &**expr {
+            ExprData::Assignment(target, _, _)
+            | ExprData::PostInc(target)
+            | ExprData::PostDec(target) => {
+                self.writes |= self.is_var(target, scopes);
+            }
+            ExprData::Unary(op, target) if matches!(&**op, UnaryOpData::Inc | UnaryOpData::Dec) => {
+                self.writes |= self.is_var(target, scopes);
+            }
+            ExprData::FunCall(fun, args) => {
+                self.writes |= args
+                    .iter()
+                    .enumerate()
+                    .any(|(index, arg)| self.is_var(arg, scopes) && self.is_output(fun, index));
+            }
+            _ => {}
+        }
+
+        Visit::Children
+    }
+}
+
+/// Replaces the variable of an unrolled loop with its value, except where it is shadowed
+struct SubstituteLoopVariable<'v> {
+    var: &'v str,
+    value: i32,
+}
+
+impl ScopedVisitorMut for SubstituteLoopVariable<'_> {
+    fn visit_expr(&mut self, expr: &mut Expr, scopes: &BlockScopes) -> Visit {
+        if let ExprData::Variable(ident) = &**expr {
+            if ident.0 == self.var && !scopes.is_shadowed(self.var) {
+                expr.content = ExprData::IntConst(self.value);
+            }
+        }
+
+        Visit::Children
+    }
+}
@@ -0,0 +1,127 @@
+//! File-private functions
+
+use std::collections::{HashMap, HashSet};
+
+use glsl_lang::{ast::*, visitor::*};
+
+use super::shadow::{visit_scoped, BlockScopes, ScopedVisitorMut};
+
+use crate::glsl_ext::FunctionPrototypeExt;
+
+/// Return the output name of a private function
+///
+/// # Parameters
+///
+/// * `prefix`: identifier prefix for generated code
+/// * `file`: source file the function is defined in
+/// * `name`: name of the function in its source file
+pub fn private_name(prefix: &str, file: FileId, name: &str) -> SmolStr {
+    format!("{}f{}_{}", prefix, file.number(), name).into()
+}
+
+/// Return the source file of a declaration, used to scope private functions
+pub fn declaration_file(span: Option<NodeSpan>) -> FileId {
+    span.map(|span| span.source_id()).unwrap_or(FileId::new(0))
+}
+
+/// Return the name of a function definition if it is marked as private, i.e. its prototype is
+/// written `layout(private) float f(...)`
+pub fn parse_private_function(extdecl: &ExternalDeclaration) -> Option<&SmolStr> {
+    match &extdecl.content {
+        ExternalDeclarationData::FunctionDefinition(def) if def.prototype.is_private() => {
+            Some(&def.prototype.name.0)
+        }
+        _ => None,
+    }
+}
+
+/// Rename the private functions declared and referenced by a declaration
+///
+/// The `layout(private)` qualifier is removed from the renamed prototypes, and identifiers
+/// shadowed by parameters or by local variables visible at that point are left untouched.
+///
+/// # Parameters
+///
+/// * `extdecl`: declaration to transform
+/// * `names`: output names of the private functions of the declaration's file
+pub fn rename_private_functions(
+    extdecl: &mut ExternalDeclaration,
+    names: &HashMap<SmolStr, SmolStr>,
+) {
+    struct RenamePrototypes<'n> {
+        names: &'n HashMap<SmolStr, SmolStr>,
+    }
+
+    impl VisitorMut for RenamePrototypes<'_> {
+        fn visit_function_prototype(&mut self, node: &mut FunctionPrototype) -> Visit {
+            if let Some(name) = self.names.get(&node.name.0) {
+                node.name.0 = name.clone();
+                remove_private_qualifier(node);
+            }
+
+            Visit::Parent
+        }
+    }
+
+    struct RenameReferences<'n> {
+        names: &'n HashMap<SmolStr, SmolStr>,
+        parameters: HashSet<SmolStr>,
+    }
+
+    impl ScopedVisitorMut for RenameReferences<'_> {
+        fn visit_expr(&mut self, node: &mut Expr, scopes: &BlockScopes) -> Visit {
+            // Function calls and functions passed as template arguments
+            if let ExprData::Variable(ident) = &mut **node {
+                if !self.parameters.contains(&ident.0) && !scopes.is_shadowed(&ident.0) {
+                    if let Some(name) = self.names.get(&ident.0) {
+                        ident.0 = name.clone();
+                    }
+                }
+            }
+
+            Visit::Children
+        }
+    }
+
+    if names.is_empty() {
+        return;
+    }
+
+    // Parameters shadow functions in the whole body
+    let parameters = match &**extdecl {
+        ExternalDeclarationData::FunctionDefinition(def) => def
+            .prototype
+            .parameters
+            .iter()
+            .filter_map(|parameter| match &**parameter {
+                FunctionParameterDeclarationData::Named(_, p) => Some(p.ident.ident.0.clone()),
+                FunctionParameterDeclarationData::Unnamed(_, _) => None,
+            })
+            .collect(),
+        _ => HashSet::new(),
+    };
+
+    visit_scoped(extdecl, RenameReferences { names, parameters });
+    extdecl.visit_mut(&mut RenamePrototypes { names });
+}
+
+/// Remove the `layout(private)` qualifier of a prototype
+fn remove_private_qualifier(prototype: &mut FunctionPrototype) {
+    if let Some(qualifier) = &mut prototype.ty.qualifier {
+        for spec in qualifier.qualifiers.iter_mut() {
+            if let TypeQualifierSpecData::Layout(layout) = &mut **spec {
+                layout.ids.retain(|id| {
+                    !matches!(&**id, LayoutQualifierSpecData::Identifier(ident, None) if ident.0 == "private")
+                });
+            }
+        }
+
+        qualifier.qualifiers.retain(|spec| {
+            !matches!(&**spec, TypeQualifierSpecData::Layout(layout) if layout.ids.is_empty())
+        });
+
+        if qualifier.qualifiers.is_empty() {
+            prototype.ty.qualifier = None;
+        }
+    }
+}
@@ -4,6 +4,9 @@ use glsl_lang::ast::*;
 
 use indexmap::IndexMap;
 
+use super::alias::LambdaAlias;
+use super::dispatch::DispatchTable;
+use super::global_scope::GlobalVariable;
 use super::instantiate::{CapturedParameter, InstantiateTemplate};
 use super::template::TemplateDefinition;
 use crate::TransformConfig;
@@ -16,25 +19,68 @@ pub trait Scope: std::fmt::Debug {
     /// Return the parent scope, unless this is the global scope
     fn parent_scope(&self) -> Option<&dyn Scope>;
 
+    /// Return the name of the template instantiated in this scope and the name of its instance,
+    /// unless this is the global scope
+    fn current_instance(&self) -> Option<(&str, &str)>;
+
     /// Get the list of defined pointer types in this global scope
-    fn declared_pointer_types(&self) -> &IndexMap<SmolStr, FunctionPrototype>;
+    fn declared_pointer_types(&self) -> &IndexMap<SmolStr, Vec<FunctionPrototype>>;
+
+    /// Get the prototype of a pointer type for a call with the given number of arguments
+    ///
+    /// If no overload of the pointer type has this number of parameters, the first one is
+    /// returned.
+    fn get_pointer_type(&self, name: &str, arity: usize) -> Option<&FunctionPrototype> {
+        self.declared_pointer_types()
+            .get(name)
+            .and_then(|overloads| {
+                overloads
+                    .iter()
+                    .find(|p| p.parameters.len() == arity)
+                    .or_else(|| overloads.first())
+            })
+    }
 
-    /// Get the template corresponding to the given name
-    fn get_template(&self, template_name: &str) -> Option<Arc<TemplateDefinition>>;
+    /// Get the overloads of the template corresponding to the given name
+    fn get_templates(&self, template_name: &str) -> &[Arc<TemplateDefinition>];
 
-    /// Determine if a template has been instantiated with the given name
+    /// Get the explicit specializations of the template corresponding to the given name
+    fn get_template_specializations(&self, template_name: &str) -> &[Arc<TemplateDefinition>];
+
+    /// Get the name of the function generated for a template instantiation
+    ///
+    /// Returns `None` if no template has been instantiated with the given name. If the
+    /// instantiation was found to be identical to a previous one, the name of the previous
+    /// instance is returned instead.
     ///
     /// # Parameters
     ///
     /// * `template_name`: name of the template instantiation
-    fn template_instance_declared(&self, template_name: &str) -> bool;
+    fn template_instance_name(&self, template_name: &str) -> Option<SmolStr>;
 
     /// Register a template instantiation in the current transform unit
     ///
+    /// Returns the name call sites should use for this instance, which is the name of a
+    /// previously registered instance if its body is identical.
+    ///
+    /// # Parameters
+    ///
+    /// * `definitions`: function definitions introduced by the template instantiation. The last
+    ///   one is the instance itself, the ones before are its dependencies.
+    /// * `body_id`: identifier of the instance body, see
+    ///   [instance_body_id](super::template::instance_body_id)
+    fn register_template_instance(
+        &mut self,
+        definitions: Vec<FunctionDefinition>,
+        body_id: String,
+    ) -> SmolStr;
+
+    /// Register the dependencies of a template instantiation which was inlined at its call site
+    ///
     /// # Parameters
     ///
     /// * `definitions`: function definitions introduced by the template instantiation
-    fn register_template_instance(&mut self, definitions: Vec<FunctionDefinition>);
+    fn register_template_dependencies(&mut self, definitions: Vec<FunctionDefinition>);
 
     /// Each template instantiation round will generate functions declarations
     /// This allows pulling them in from the parent transformation unit and should be invoked after
@@ -44,6 +90,33 @@ pub trait Scope: std::fmt::Debug {
     /// Resolve an identifier as a function name. Returns None if this is not possible.
     fn resolve_function_name(&self, name: &str) -> Option<ResolvedArgument>;
 
+    /// Get the overloads of the known function with the given name
+    fn get_function_overloads(&self, name: &str) -> &[FunctionPrototype];
+
+    /// Get the lambda alias declared with the given name, if it is visible from this scope
+    fn get_lambda_alias(&self, name: &str) -> Option<&LambdaAlias>;
+
+    /// Get the dispatch table declared with the given name, if it is visible from this scope
+    fn get_dispatch_table(&self, name: &str) -> Option<&DispatchTable>;
+
+    /// Declare the dispatcher template for a table of candidates of a pointer type
+    ///
+    /// Returns the name of the dispatcher template. See
+    /// [dispatcher_definition](super::dispatch::dispatcher_definition).
+    ///
+    /// # Parameters
+    ///
+    /// * `pointer_type`: overload of the pointer type of the candidates
+    /// * `count`: number of candidates
+    fn declare_dispatcher(
+        &mut self,
+        pointer_type: &FunctionPrototype,
+        count: usize,
+    ) -> crate::Result<SmolStr>;
+
+    /// Get the global variable declared with the given name
+    fn get_global_variable(&self, name: &str) -> Option<&GlobalVariable>;
+
     /// Transform a function call to a parameter into an expression
     ///
     /// Returns Err(Error::TransformAsTemplate) if the call can't be transformed by the current
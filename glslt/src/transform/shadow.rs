@@ -0,0 +1,181 @@
+//! Block scoping for template body rewrites
+
+use glsl_lang::{
+    ast::*,
+    visitor::{HostMut, Visit, VisitorMut},
+};
+
+/// An expression rewrite which must not apply to names shadowed by local declarations
+pub(crate) trait ScopedVisitorMut {
+    /// Visit an expression
+    ///
+    /// # Parameters
+    ///
+    /// * `expr`: expression to visit
+    /// * `scopes`: local declarations visible at this point
+    fn visit_expr(&mut self, expr: &mut Expr, scopes: &BlockScopes) -> Visit;
+}
+
+impl<V: ScopedVisitorMut> ScopedVisitorMut for &mut V {
+    fn visit_expr(&mut self, expr: &mut Expr, scopes: &BlockScopes) -> Visit {
+        (**self).visit_expr(expr, scopes)
+    }
+}
+
+/// Names declared in the enclosing blocks of a statement
+pub(crate) struct BlockScopes {
+    scopes: Vec<Vec<SmolStr>>,
+}
+
+impl BlockScopes {
+    /// Return `true` if `name` is declared in an enclosing block
+    pub fn is_shadowed(&self, name: &str) -> bool {
+        self.scopes
+            .iter()
+            .flatten()
+            .any(|declared| declared == name)
+    }
+
+    fn declare(&mut self, name: &SmolStr) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(name.clone());
+        }
+    }
+}
+
+/// Run a [ScopedVisitorMut] over a template body, or a part of it
+pub(crate) fn visit_scoped(host: &mut impl HostMut, visitor: impl ScopedVisitorMut) {
+    host.visit_mut(&mut Scoped {
+        scopes: BlockScopes {
+            scopes: vec![Vec::new()],
+        },
+        visitor,
+    });
+}
+
+struct Scoped<V> {
+    scopes: BlockScopes,
+    visitor: V,
+}
+
+impl<V: ScopedVisitorMut> Scoped<V> {
+    fn in_block(&mut self, f: impl FnOnce(&mut Self)) {
+        self.scopes.scopes.push(Vec::new());
+        f(self);
+        self.scopes.scopes.pop();
+    }
+}
+
+impl<V: ScopedVisitorMut> VisitorMut for Scoped<V> {
+    fn visit_compound_statement(&mut self, cs: &mut CompoundStatement) -> Visit {
+        self.in_block(|this| {
+            for stmt in cs.statement_list.iter_mut() {
+                stmt.visit_mut(this);
+            }
+        });
+
+        Visit::Parent
+    }
+
+    fn visit_iteration_statement(&mut self, stmt: &mut IterationStatement) -> Visit {
+        // Variables declared in the loop header are only visible in the loop
+        self.in_block(|this| match &mut **stmt {
+            IterationStatementData::For(init, rest, body) => {
+                init.visit_mut(this);
+                rest.visit_mut(this);
+                body.visit_mut(this);
+            }
+            IterationStatementData::While(cond, body) => {
+                cond.visit_mut(this);
+                body.visit_mut(this);
+            }
+            IterationStatementData::DoWhile(body, cond) => {
+                body.visit_mut(this);
+                cond.visit_mut(this);
+            }
+        });
+
+        Visit::Parent
+    }
+
+    fn visit_selection_statement(&mut self, stmt: &mut SelectionStatement) -> Visit {
+        stmt.cond.visit_mut(self);
+
+        // Each branch is a scope of its own, even without braces
+        match &mut *stmt.rest {
+            SelectionRestStatementData::Statement(then) => {
+                self.in_block(|this| {
+                    then.visit_mut(this);
+                });
+            }
+            SelectionRestStatementData::Else(then, otherwise) => {
+                self.in_block(|this| {
+                    then.visit_mut(this);
+                });
+                self.in_block(|this| {
+                    otherwise.visit_mut(this);
+                });
+            }
+        }
+
+        Visit::Parent
+    }
+
+    fn visit_switch_statement(&mut self, stmt: &mut SwitchStatement) -> Visit {
+        stmt.head.visit_mut(self);
+
+        // The body of a switch is a block, even though it's not a compound statement
+        self.in_block(|this| {
+            for body in stmt.body.iter_mut() {
+                body.visit_mut(this);
+            }
+        });
+
+        Visit::Parent
+    }
+
+    fn visit_init_declarator_list(&mut self, idl: &mut InitDeclaratorList) -> Visit {
+        // A name is visible from the end of its initializer, so `int i = i;` still refers to the
+        // outer `i` in the initializer
+        idl.head.ty.visit_mut(self);
+        if let Some(array_specifier) = &mut idl.head.array_specifier {
+            array_specifier.visit_mut(self);
+        }
+
+        if let Some(initializer) = &mut idl.head.initializer {
+            initializer.visit_mut(self);
+        }
+
+        if let Some(name) = &idl.head.name {
+            self.scopes.declare(&name.0);
+        }
+
+        for t in idl.tail.iter_mut() {
+            if let Some(array_specifier) = &mut t.ident.array_spec {
+                array_specifier.visit_mut(self);
+            }
+
+            if let Some(initializer) = &mut t.initializer {
+                initializer.visit_mut(self);
+            }
+
+            self.scopes.declare(&t.ident.ident.0);
+        }
+
+        Visit::Parent
+    }
+
+    fn visit_condition(&mut self, cond: &mut Condition) -> Visit {
+        if let ConditionData::Assignment(_, ident, initializer) = &mut **cond {
+            initializer.visit_mut(self);
+            self.scopes.declare(&ident.0);
+            return Visit::Parent;
+        }
+
+        Visit::Children
+    }
+
+    fn visit_expr(&mut self, expr: &mut Expr) -> Visit {
+        self.visitor.visit_expr(expr, &self.scopes)
+    }
+}
@@ -1,22 +1,74 @@
 //! Definitions of template function abstractrepresentations
 
-use glsl_lang::ast::*;
+use glsl_lang::{
+    ast::*,
+    visitor::{HostMut, Visit, VisitorMut},
+};
 
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
 
-use super::{instantiate::InstantiateTemplate, FnRef, LocalScope, Scope};
+use super::{
+    instantiate::{CapturedParameter, DeclaredSymbol, InstantiateTemplate},
+    interface::{expand_interface_parameters, field_symbol, interface_components, Interface},
+    pack::{element_symbol, expand_pack},
+    shadow::{visit_scoped, BlockScopes, ScopedVisitorMut},
+    FnRef, LocalScope, ResolvedArgumentExpr, Scope,
+};
 
-use crate::{Error, Result};
+use crate::{
+    glsl_ext::{FunctionParameterDeclarationExt, TypeQualifierExt},
+    Error, Result,
+};
+
+/// Kind of a template parameter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateParameterKind {
+    /// Function pointer, bound to a function name or a lambda expression
+    Function,
+    /// Compile-time constant, declared with `layout(constant)` and substituted into the body
+    Constant,
+}
 
 /// Function parameter of a template
 #[derive(Debug, Clone)]
 pub struct TemplateParameter {
-    /// Name of the function pointer type
+    /// Kind of this parameter
+    pub kind: TemplateParameterKind,
+    /// Name of the function pointer type, or the GLSL type of a constant parameter
     pub typename: SmolStr,
     /// Template variable name
     pub symbol: Option<SmolStr>,
     /// Original parameter index
     pub index: usize,
+    /// Name of the function this parameter is bound to, if the template is an explicit
+    /// specialization
+    pub specialized: Option<SmolStr>,
+    /// Function or lambda expression used when the argument is omitted
+    pub default: Option<Expr>,
+    /// Name of the interface this parameter is a field of, if the original parameter has an
+    /// interface type. The fields of an interface parameter share the same index.
+    pub interface: Option<SmolStr>,
+    /// `true` if this parameter is a parameter pack, declared with `layout(variadic)`, which
+    /// binds any number of arguments
+    pub variadic: bool,
+}
+
+/// Argument of a template call, bound to a parameter of the template
+#[derive(Debug, Clone)]
+pub enum BoundArgument {
+    /// Argument given at the call site
+    Given(Expr),
+    /// Default value of an omitted template parameter
+    Default(Expr),
+}
+
+impl BoundArgument {
+    /// Get the expression of this argument
+    pub fn into_expr(self) -> Expr {
+        match self {
+            Self::Given(expr) | Self::Default(expr) => expr,
+        }
+    }
 }
 
 /// Definition of a template function
@@ -28,13 +80,72 @@ pub struct TemplateDefinition {
     ast: FunctionDefinition,
     /// List of template parameters
     parameters: Vec<TemplateParameter>,
+    /// Type template parameters used in the prototype, in order of first appearance
+    type_parameters: Vec<SmolStr>,
     /// Original prototype
     raw_prototype: FunctionPrototype,
+    /// Position of this template in the list of overloads with the same name
+    overload: usize,
+    /// Number of arguments bound to the parameter pack, if this template was expanded from a
+    /// variadic template
+    pack_size: Option<usize>,
+    /// `true` if this template was generated by the transform, so its name is already prefixed
+    generated: bool,
 }
 
-fn expr_vec_to_id(exprs: &[(Expr, &str)]) -> String {
+/// Concrete type bound to a type template parameter
+pub type TypeArgument = (SmolStr, TypeSpecifierNonArrayData);
+
+/// Constant expression bound to a constant template parameter
+pub type ConstantArgument = (SmolStr, Expr);
+
+fn expr_vec_to_id(
+    exprs: &[(Expr, &str)],
+    parameters: &[TemplateParameter],
+    type_arguments: &[TypeArgument],
+    captured: &[CapturedParameter],
+    overload: usize,
+    pack_size: Option<usize>,
+    generated: bool,
+) -> String {
     let mut sbuf = String::new();
 
+    // Generated templates must not share instances with user templates named like them once
+    // prefixed
+    if generated {
+        sbuf.push('!');
+    }
+
+    // Overloads must not share instances with each other
+    if overload > 0 {
+        sbuf.push_str(&format!("#{}", overload));
+    }
+
+    // Each number of arguments bound to the parameter pack is a different instance
+    if let Some(pack_size) = pack_size {
+        sbuf.push_str(&format!("*{}", pack_size));
+    }
+
+    // Each set of type arguments is a different instance
+    for (name, ty) in type_arguments {
+        sbuf.push('<');
+        sbuf.push_str(name);
+        sbuf.push('=');
+
+        glsl_lang::transpiler::glsl::show_type_specifier_non_array(
+            &mut sbuf,
+            &ty.clone().into(),
+            &mut glsl_lang::transpiler::glsl::FormattingState::default(),
+        )
+        .unwrap();
+    }
+
+    // Specializations must not share instances with their generic template
+    for name in parameters.iter().filter_map(|p| p.specialized.as_ref()) {
+        sbuf.push('=');
+        sbuf.push_str(name);
+    }
+
     // Transpile all expressions into the string buffer
     for expr in exprs {
         sbuf.push_str(expr.1);
@@ -47,11 +158,79 @@ fn expr_vec_to_id(exprs: &[(Expr, &str)]) -> String {
         .unwrap();
     }
 
+    // Append the signature of captured parameters
+    for cp in captured {
+        sbuf.push(if cp.written { '&' } else { '@' });
+
+        glsl_lang::transpiler::glsl::show_type_specifier(
+            &mut sbuf,
+            &cp.decl_type,
+            &mut glsl_lang::transpiler::glsl::FormattingState::default(),
+        )
+        .unwrap();
+
+        if let Some(array) = &cp.array {
+            glsl_lang::transpiler::glsl::show_array_spec(
+                &mut sbuf,
+                array,
+                &mut glsl_lang::transpiler::glsl::FormattingState::default(),
+            )
+            .unwrap();
+        }
+    }
+
     // Compute it's SHA-1
     use sha1::{Digest, Sha1};
     format!("{:x}", Sha1::digest(sbuf.as_bytes()))[0..6].to_string()
 }
 
+/// Compute the identifier of the body of a template instance
+///
+/// Two instances with the same identifier only differ by their name and the names of their
+/// captured parameters, so call sites can use them interchangeably.
+///
+/// # Parameters
+///
+/// * `def`: instantiated function definition
+/// * `captured`: parameters captured by the instance, in call order
+pub(crate) fn instance_body_id(def: &FunctionDefinition, captured: &[CapturedParameter]) -> String {
+    struct V {
+        renames: IndexMap<SmolStr, SmolStr>,
+    }
+
+    impl VisitorMut for V {
+        fn visit_identifier(&mut self, ident: &mut Identifier) -> Visit {
+            if let Some(repl) = self.renames.get(&ident.0) {
+                ident.0 = repl.clone();
+            }
+
+            Visit::Children
+        }
+    }
+
+    // Captured parameter names depend on the call site, only their position matters
+    let mut def = def.clone();
+    def.prototype.name.0 = SmolStr::default();
+    def.visit_mut(&mut V {
+        renames: captured
+            .iter()
+            .enumerate()
+            .map(|(id, cp)| (cp.gen_id.clone(), SmolStr::from(format!("#{}", id))))
+            .collect(),
+    });
+
+    let mut sbuf = String::new();
+    glsl_lang::transpiler::glsl::show_function_definition(
+        &mut sbuf,
+        &def,
+        &mut glsl_lang::transpiler::glsl::FormattingState::default(),
+    )
+    .unwrap();
+
+    use sha1::{Digest, Sha1};
+    format!("{:x}", Sha1::digest(sbuf.as_bytes()))
+}
+
 impl TemplateDefinition {
     /// Get the AST of this template definition
     pub fn ast(&self) -> &FunctionDefinition {
@@ -63,6 +242,396 @@ impl TemplateDefinition {
         &self.parameters[..]
     }
 
+    /// Get the type template parameters used in the prototype of this template
+    pub fn type_parameters(&self) -> &[SmolStr] {
+        &self.type_parameters[..]
+    }
+
+    /// Get the original prototype of this template, including template parameters
+    pub fn raw_prototype(&self) -> &FunctionPrototype {
+        &self.raw_prototype
+    }
+
+    /// Return `true` if the parameter at the given index is declared with a type template
+    /// parameter as its type
+    pub fn is_type_parameter_typed(&self, index: usize) -> bool {
+        self.type_parameter_at(index).is_some()
+    }
+
+    fn type_parameter_at(&self, index: usize) -> Option<&SmolStr> {
+        let (ty, array) = self.raw_prototype.parameters.get(index)?.parameter_type();
+        if array.is_some() || ty.array_specifier.is_some() {
+            return None;
+        }
+
+        match &*ty.ty {
+            TypeSpecifierNonArrayData::TypeName(tn) => {
+                self.type_parameters.iter().find(|tp| **tp == tn.0)
+            }
+            _ => None,
+        }
+    }
+
+    /// Infer the concrete types of the type template parameters from the call arguments
+    ///
+    /// # Parameters
+    ///
+    /// * `args`: list of all function call arguments, in parameter order
+    /// * `symbol_table`: symbols declared in the calling function
+    /// * `scope`: scope the call arguments are resolved in
+    ///
+    /// # Errors
+    ///
+    /// Returns [Error::UnresolvedTypeParameter] if the type of a parameter can't be inferred from
+    /// the arguments, and [Error::ConflictingTypeParameter] if arguments imply different types
+    /// for the same parameter.
+    pub fn infer_type_arguments(
+        &self,
+        args: &[Expr],
+        symbol_table: &IndexMap<SmolStr, DeclaredSymbol>,
+        scope: &dyn Scope,
+    ) -> Result<Vec<TypeArgument>> {
+        let mut inferred: IndexMap<&SmolStr, TypeSpecifierNonArrayData> = IndexMap::new();
+
+        for (index, arg) in args.iter().enumerate() {
+            let name = match self.type_parameter_at(index) {
+                Some(name) => name,
+                None => continue,
+            };
+
+            if let Some(ty) = super::overload::infer_type(arg, symbol_table, scope) {
+                match inferred.get(name) {
+                    Some(previous) if *previous != ty => {
+                        return Err(Error::ConflictingTypeParameter {
+                            name: self.ast.prototype.name.0.clone(),
+                            parameter: name.clone(),
+                        });
+                    }
+                    Some(_) => {}
+                    None => {
+                        inferred.insert(name, ty);
+                    }
+                }
+            }
+        }
+
+        self.type_parameters
+            .iter()
+            .map(|name| {
+                inferred
+                    .get(name)
+                    .map(|ty| (name.clone(), ty.clone()))
+                    .ok_or_else(|| Error::UnresolvedTypeParameter {
+                        name: self.ast.prototype.name.0.clone(),
+                        parameter: name.clone(),
+                    })
+            })
+            .collect()
+    }
+
+    /// Set the position of this template in the list of overloads with the same name
+    pub(crate) fn with_overload(mut self, overload: usize) -> Self {
+        self.overload = overload;
+        self
+    }
+
+    /// Mark this template as generated by the transform, so its instances aren't prefixed again
+    pub(crate) fn generated(mut self) -> Self {
+        self.generated = true;
+        self
+    }
+
+    /// Return `true` if both templates have the same template and regular parameter types, i.e.
+    /// one can't be an overload of the other
+    pub fn same_signature(&self, other: &TemplateDefinition) -> bool {
+        let raw = &self.raw_prototype.parameters;
+        let other_raw = &other.raw_prototype.parameters;
+
+        raw.len() == other_raw.len()
+            && raw
+                .iter()
+                .zip(other_raw.iter())
+                .enumerate()
+                .all(|(index, (a, b))| {
+                    let a_template = self.parameters.iter().find(|p| p.index == index);
+                    let b_template = other.parameters.iter().find(|p| p.index == index);
+
+                    match (a_template, b_template) {
+                        (Some(a), Some(b)) => {
+                            a.kind == b.kind
+                                && a.typename == b.typename
+                                && a.interface == b.interface
+                                && a.variadic == b.variadic
+                        }
+                        (None, None) => a.parameter_type() == b.parameter_type(),
+                        _ => false,
+                    }
+                })
+    }
+
+    /// Return `true` if the last template parameter of this template is a parameter pack
+    pub fn is_variadic(&self) -> bool {
+        self.parameters.last().is_some_and(|p| p.variadic)
+    }
+
+    /// Expand the parameter pack of this variadic template for a call with `arg_count` arguments
+    ///
+    /// The parameter pack is replaced with one template parameter per argument it binds, and
+    /// its uses in the body are expanded accordingly.
+    ///
+    /// # Errors
+    ///
+    /// Returns [Error::InvalidPackExpansion] if the call leaves the parameter pack empty, or if
+    /// the body uses the parameter pack in a way which can't be expanded for this number of
+    /// arguments.
+    pub fn expand_pack(&self, arg_count: usize, scope: &dyn Scope) -> Result<TemplateDefinition> {
+        let pack = match self.parameters.last() {
+            Some(pack) if pack.variadic => pack,
+            _ => return Ok(self.clone()),
+        };
+
+        // The pack binds the arguments which are not bound to the other parameters
+        let count = (arg_count + 1).saturating_sub(self.raw_prototype.parameters.len());
+        let invalid = || Error::InvalidPackExpansion {
+            name: self.ast.prototype.name.0.clone(),
+            parameter: pack.symbol.clone().unwrap_or_default(),
+        };
+
+        if count == 0 {
+            return Err(invalid());
+        }
+
+        let mut raw_prototype = self.raw_prototype.clone();
+        let declaration = raw_prototype.parameters.remove(pack.index);
+        raw_prototype.parameters.splice(
+            pack.index..pack.index,
+            std::iter::repeat_n(declaration, count),
+        );
+
+        let mut parameters = self.parameters.clone();
+        parameters.pop();
+        parameters.extend((0..count).map(|i| TemplateParameter {
+            symbol: pack.symbol.as_ref().map(|symbol| element_symbol(symbol, i)),
+            index: pack.index + i,
+            variadic: false,
+            ..pack.clone()
+        }));
+
+        // Unrolled loops must not pass their variable to out parameters
+        let is_output = |fun: &str, index: usize| -> Option<bool> {
+            let has_output = |prototype: &FunctionPrototype| {
+                prototype
+                    .parameters
+                    .get(index)
+                    .is_some_and(|parameter| parameter.is_output())
+            };
+
+            if crate::util::is_builtin_glsl_function(fun) {
+                return Some(crate::util::builtin_glsl_output_parameters(fun).contains(&index));
+            }
+
+            if let Some(parameter) = self
+                .parameters
+                .iter()
+                .find(|p| p.symbol.as_deref() == Some(fun))
+            {
+                return scope
+                    .declared_pointer_types()
+                    .get(&parameter.typename)
+                    .map(|overloads| overloads.iter().any(has_output));
+            }
+
+            let functions = scope.get_function_overloads(fun);
+            let templates = scope.get_templates(fun);
+            if functions.is_empty() && templates.is_empty() {
+                return None;
+            }
+
+            Some(
+                functions.iter().any(has_output)
+                    || templates
+                        .iter()
+                        .any(|template| has_output(template.raw_prototype())),
+            )
+        };
+
+        let mut ast = self.ast.clone();
+        if let Some(symbol) = &pack.symbol {
+            if !expand_pack(&mut ast.statement, symbol, count, &is_output) {
+                return Err(invalid());
+            }
+        }
+
+        Ok(TemplateDefinition {
+            ast,
+            parameters,
+            type_parameters: self.type_parameters.clone(),
+            raw_prototype,
+            overload: self.overload,
+            pack_size: Some(count),
+            generated: self.generated,
+        })
+    }
+
+    /// Return `true` if this template is an explicit specialization of another template
+    pub fn is_specialization(&self) -> bool {
+        self.parameters.iter().any(|p| p.specialized.is_some())
+    }
+
+    /// Return the number of template parameters bound by this explicit specialization
+    pub fn specialized_count(&self) -> usize {
+        self.parameters
+            .iter()
+            .filter(|p| p.specialized.is_some())
+            .count()
+    }
+
+    /// Determine if this specialization applies to the given call arguments
+    ///
+    /// # Parameters
+    ///
+    /// * `args`: list of all function call arguments
+    /// * `scope`: scope the call arguments are resolved in
+    pub fn matches_specialization(&self, args: &[Expr], scope: &dyn Scope) -> bool {
+        self.parameters.iter().all(|p| {
+            let name = match &p.specialized {
+                Some(name) => name,
+                None => return true,
+            };
+
+            match args.get(p.index).map(|arg| &**arg) {
+                Some(ExprData::Variable(ident)) => matches!(
+                    scope.resolve_function_name(ident.0.as_str()).map(|r| r.body),
+                    Some(ResolvedArgumentExpr::FunctionName(target)) if target == *name
+                ),
+                _ => false,
+            }
+        })
+    }
+
+    /// Return the template parameter bound by a named argument, along with its value
+    ///
+    /// Named arguments are assignments to an identifier which is not a local variable, such as
+    /// `b = sdBox`. Assignments to global variables which aren't named like a template parameter
+    /// are regular arguments.
+    fn named_argument<'e>(
+        &self,
+        arg: &'e Expr,
+        symbol_table: &IndexMap<SmolStr, DeclaredSymbol>,
+        scope: &dyn Scope,
+    ) -> Result<Option<(&TemplateParameter, &'e Expr)>> {
+        let (name, value) = match &**arg {
+            ExprData::Assignment(lhs, op, value) if matches!(&**op, AssignmentOpData::Equal) => {
+                match &***lhs {
+                    ExprData::Variable(ident) if !symbol_table.contains_key(ident.0.as_str()) => {
+                        (&ident.0, &**value)
+                    }
+                    _ => return Ok(None),
+                }
+            }
+            _ => return Ok(None),
+        };
+
+        match self
+            .parameters
+            .iter()
+            .find(|p| p.symbol.as_ref() == Some(name))
+        {
+            Some(p) => Ok(Some((p, value))),
+            None if scope.get_global_variable(name).is_some() => Ok(None),
+            None => Err(Error::UnknownTemplateArgument {
+                name: self.ast.prototype.name.0.clone(),
+                argument: name.clone(),
+            }),
+        }
+    }
+
+    /// Bind the arguments of a call to the parameters of this template
+    ///
+    /// Named arguments bind the template parameter with the same name. The other arguments are
+    /// bound in order to the remaining parameters, and trailing template parameters may be
+    /// omitted if they have a default value.
+    ///
+    /// # Parameters
+    ///
+    /// * `args`: list of all function call arguments
+    /// * `symbol_table`: symbols declared in the calling function
+    /// * `scope`: scope of the calling function
+    ///
+    /// # Returns
+    ///
+    /// The arguments in parameter order, or `None` if the number of arguments doesn't match the
+    /// parameters of this template.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a named argument doesn't match any template parameter, or binds a
+    /// parameter which is already bound.
+    pub fn bind_arguments(
+        &self,
+        args: &[Expr],
+        symbol_table: &IndexMap<SmolStr, DeclaredSymbol>,
+        scope: &dyn Scope,
+    ) -> Result<Option<Vec<BoundArgument>>> {
+        let mut bound: Vec<Option<BoundArgument>> = (0..self.raw_prototype.parameters.len())
+            .map(|_| None)
+            .collect();
+        let mut positional = Vec::with_capacity(args.len());
+
+        for arg in args {
+            match self.named_argument(arg, symbol_table, scope)? {
+                Some((parameter, value)) => {
+                    if bound[parameter.index].is_some() {
+                        return Err(Error::DuplicateTemplateArgument {
+                            name: self.ast.prototype.name.0.clone(),
+                            argument: parameter.symbol.clone().unwrap_or_default(),
+                        });
+                    }
+
+                    bound[parameter.index] = Some(BoundArgument::Given(value.clone()));
+                }
+                None => positional.push(arg),
+            }
+        }
+
+        let unbound: Vec<_> = (0..bound.len()).filter(|i| bound[*i].is_none()).collect();
+        let missing = match unbound.len().checked_sub(positional.len()) {
+            Some(missing) => missing,
+            None => return Ok(None),
+        };
+
+        // Omitted arguments are the trailing unbound template parameters
+        let omitted: Vec<_> = unbound
+            .iter()
+            .rev()
+            .filter_map(|i| self.parameters.iter().find(|p| p.index == *i))
+            .take(missing)
+            .collect();
+
+        if omitted.len() < missing {
+            return Ok(None);
+        }
+
+        for parameter in omitted {
+            match &parameter.default {
+                Some(default) => {
+                    bound[parameter.index] = Some(BoundArgument::Default(default.clone()))
+                }
+                None => return Ok(None),
+            }
+        }
+
+        let mut positional = positional.into_iter();
+        Ok(Some(
+            bound
+                .into_iter()
+                .map(|arg| {
+                    arg.unwrap_or_else(|| BoundArgument::Given(positional.next().unwrap().clone()))
+                })
+                .collect(),
+        ))
+    }
+
     /// Get the FnRef definition of this template
     pub fn fn_ref(&self) -> Node<FnRef> {
         Node::new(
@@ -79,10 +648,32 @@ impl TemplateDefinition {
     /// # Parameters
     ///
     /// * `args`: list of template parameter values used in the invocation
+    /// * `type_arguments`: concrete types of the type template parameters
+    /// * `captured`: parameters captured by the invocation
     /// * `config`: scope in which this identifier should be generated
-    pub fn generate_id(&self, args: &[(Expr, &str)], scope: &dyn Scope) -> SmolStr {
-        let args_id = expr_vec_to_id(args);
-        let base = scope.config().prefix.clone() + self.ast.prototype.name.0.as_str();
+    pub fn generate_id(
+        &self,
+        args: &[(Expr, &str)],
+        type_arguments: &[TypeArgument],
+        captured: &[CapturedParameter],
+        scope: &dyn Scope,
+    ) -> SmolStr {
+        let args_id = expr_vec_to_id(
+            args,
+            &self.parameters,
+            type_arguments,
+            captured,
+            self.overload,
+            self.pack_size,
+            self.generated,
+        );
+        let name = self.ast.prototype.name.0.as_str();
+        let base = if self.generated {
+            name.to_owned()
+        } else {
+            scope.config().prefix.clone() + name
+        };
+
         SmolStr::from([base.as_str(), &args_id].join("_"))
     }
 
@@ -98,8 +689,23 @@ impl TemplateDefinition {
         scope: &mut LocalScope,
         outer_instantiator: &InstantiateTemplate,
     ) -> crate::Result<Vec<FunctionDefinition>> {
-        // Clone the AST
-        let ast = self.ast.clone();
+        // Clone the AST, replacing type template parameters with their concrete types and
+        // constant template parameters with their values
+        let mut ast = self.ast.clone();
+        if !scope.type_arguments().is_empty() {
+            ast.visit_mut(&mut SubstituteTypes {
+                type_arguments: scope.type_arguments(),
+            });
+        }
+
+        if !scope.constant_arguments().is_empty() {
+            visit_scoped(
+                &mut ast,
+                SubstituteConstants {
+                    constant_arguments: scope.constant_arguments(),
+                },
+            );
+        }
 
         // We're entering a new function, thus we need a new context
         let mut res = InstantiateTemplate::new(Some(outer_instantiator)).instantiate(scope, ast)?;
@@ -115,9 +721,24 @@ impl TemplateDefinition {
         // Add the captured parameters to the signature
         for ep in scope.captured_parameters() {
             // TODO: Span information?
+            // Captured variables written by the lambda are passed by reference
+            let qualifier = if ep.written {
+                Some(
+                    TypeQualifierData {
+                        qualifiers: vec![TypeQualifierSpecData::Storage(
+                            StorageQualifierData::InOut.into(),
+                        )
+                        .into()],
+                    }
+                    .into(),
+                )
+            } else {
+                None
+            };
+
             ast.prototype.parameters.push(
                 FunctionParameterDeclarationData::Named(
-                    None,
+                    qualifier,
                     FunctionParameterDeclaratorData {
                         ty: ep.decl_type.clone(),
                         ident: ArrayedIdentifierData {
@@ -143,38 +764,99 @@ impl TemplateDefinition {
     ///
     /// # Returns
     ///
-    /// List of expressions to be used in the template call. `args` will contain regular arguments
-    /// to the GLSL function (which do not require a template instantiation).
+    /// List of expressions to be used in the template call, one per template parameter. `args`
+    /// will contain regular arguments to the GLSL function (which do not require a template
+    /// instantiation).
+    ///
+    /// # Errors
+    ///
+    /// Returns [Error::InvalidInterfaceArgument] if the argument for an interface parameter is not
+    /// a constructor expression of the interface.
     pub fn extract_template_parameters(&self, args: &mut Vec<Expr>) -> Result<Vec<(Expr, &str)>> {
-        let mut idx = 0;
-        let mut it = self.parameters.iter();
-        let mut current = it.next();
-
-        let (res, other): (Vec<_>, Vec<_>) = args.drain(0..args.len()).partition(|_| {
-            let res = if let Some(c) = current {
-                if c.index <= idx {
-                    current = it.next();
-                    true
-                } else {
-                    false
+        let mut parameters = self.parameters.iter().peekable();
+        let mut res = Vec::with_capacity(self.parameters.len());
+        let mut other = Vec::with_capacity(args.len());
+
+        for (idx, arg) in args.drain(0..args.len()).enumerate() {
+            let current = match parameters.next_if(|p| p.index <= idx) {
+                Some(current) => current,
+                None => {
+                    other.push(arg);
+                    continue;
                 }
-            } else {
-                false
             };
 
-            idx += 1;
-            res
-        });
+            match &current.interface {
+                Some(interface) => {
+                    // Interface arguments are expanded into the values of their fields
+                    let mut fields = vec![current];
+                    while let Some(field) = parameters.next_if(|p| p.index == current.index) {
+                        fields.push(field);
+                    }
+
+                    let components = interface_components(arg, interface.as_str(), fields.len())
+                        .ok_or_else(|| Error::InvalidInterfaceArgument {
+                            name: self.ast.prototype.name.0.clone(),
+                            interface: interface.clone(),
+                            index: current.index,
+                        })?;
+
+                    res.extend(
+                        components
+                            .into_iter()
+                            .zip(fields)
+                            .map(|(arg, field)| (arg, field.typename.as_str())),
+                    );
+                }
+                None => res.push((arg, current.typename.as_str())),
+            }
+        }
 
         // Put regular args back into the function call
         args.extend(other);
 
         // Return template args with their values and type names
-        Ok(res
-            .into_iter()
-            .enumerate()
-            .map(|(id, arg)| (arg, self.parameters[id].typename.as_str()))
-            .collect())
+        Ok(res)
+    }
+}
+
+/// Replaces type template parameters with their concrete types
+struct SubstituteTypes<'a> {
+    type_arguments: &'a [TypeArgument],
+}
+
+impl VisitorMut for SubstituteTypes<'_> {
+    fn visit_type_specifier_non_array(&mut self, ty: &mut TypeSpecifierNonArray) -> Visit {
+        if let TypeSpecifierNonArrayData::TypeName(tn) = &**ty {
+            if let Some((_, concrete)) = self.type_arguments.iter().find(|(name, _)| *name == tn.0)
+            {
+                ty.content = concrete.clone();
+            }
+        }
+
+        Visit::Children
+    }
+}
+
+/// Replaces constant template parameters with their values, except where they are shadowed
+struct SubstituteConstants<'a> {
+    constant_arguments: &'a [ConstantArgument],
+}
+
+impl ScopedVisitorMut for SubstituteConstants<'_> {
+    fn visit_expr(&mut self, expr: &mut Expr, scopes: &BlockScopes) -> Visit {
+        if let ExprData::Variable(ident) = &**expr {
+            if let Some((_, value)) = self
+                .constant_arguments
+                .iter()
+                .find(|(name, _)| *name == ident.0 && !scopes.is_shadowed(name))
+            {
+                expr.content = value.content.clone();
+                return Visit::Parent;
+            }
+        }
+
+        Visit::Children
     }
 }
 
@@ -192,6 +874,8 @@ pub enum TryTemplate {
 ///
 /// * `def`: function definition to parse
 /// * `declared_pointer_types`: map of known function pointer types
+/// * `declared_type_parameters`: set of known type template parameters
+/// * `declared_interfaces`: map of known interfaces
 ///
 /// # Returns
 ///
@@ -204,24 +888,246 @@ pub enum TryTemplate {
 /// See [crate::Error] for potential template declaration errors.
 pub fn parse_definition_as_template(
     def: FunctionDefinition,
-    declared_pointer_types: &IndexMap<SmolStr, FunctionPrototype>,
+    declared_pointer_types: &IndexMap<SmolStr, Vec<FunctionPrototype>>,
+    declared_type_parameters: &IndexSet<SmolStr>,
+    declared_interfaces: &IndexMap<SmolStr, Interface>,
+) -> Result<TryTemplate> {
+    parse_definition(
+        def,
+        declared_pointer_types,
+        declared_type_parameters,
+        declared_interfaces,
+        None,
+    )
+}
+
+/// Try parsing a function definition as an explicit specialization of a template
+///
+/// Template parameters of the definition marked with `layout(specialize)` are bound to the
+/// known function they are named after. The definition must then have the same parameters as one
+/// of the overloads in `generic`.
+///
+/// # Parameters
+///
+/// * `def`: function definition to parse
+/// * `declared_pointer_types`: map of known function pointer types
+/// * `declared_type_parameters`: set of known type template parameters
+/// * `declared_interfaces`: map of known interfaces
+/// * `generic`: overloads of the template this definition may specialize
+/// * `known_functions`: map of known function names
+///
+/// # Returns
+///
+/// A [TryTemplate] structure which represents a specialization if at least one parameter is
+/// bound to a function, or the result of [parse_definition_as_template] otherwise.
+///
+/// # Errors
+///
+/// See [crate::Error] for potential template declaration errors.
+pub fn parse_definition_as_specialization(
+    def: FunctionDefinition,
+    declared_pointer_types: &IndexMap<SmolStr, Vec<FunctionPrototype>>,
+    declared_type_parameters: &IndexSet<SmolStr>,
+    declared_interfaces: &IndexMap<SmolStr, Interface>,
+    generic: &[std::sync::Arc<TemplateDefinition>],
+    known_functions: &IndexMap<SmolStr, Vec<FunctionPrototype>>,
 ) -> Result<TryTemplate> {
-    let mut parameters = Vec::new();
+    match parse_definition(
+        def,
+        declared_pointer_types,
+        declared_type_parameters,
+        declared_interfaces,
+        Some(known_functions),
+    )? {
+        TryTemplate::Template(template) if template.is_specialization() => {
+            if let Some(overload) = generic.iter().find(|g| g.same_signature(&template)) {
+                Ok(TryTemplate::Template(
+                    template.with_overload(overload.overload).into(),
+                ))
+            } else {
+                Err(Error::InvalidSpecialization {
+                    name: template.ast.prototype.name.0.clone(),
+                    index: template
+                        .parameters
+                        .iter()
+                        .find(|p| p.specialized.is_some())
+                        .unwrap()
+                        .index,
+                })
+            }
+        }
+        other => Ok(other),
+    }
+}
+
+/// Return `true` if a function definition is an explicit specialization, i.e. one of its template
+/// parameters is marked with `layout(specialize)`
+///
+/// # Parameters
+///
+/// * `def`: function definition to check
+/// * `declared_pointer_types`: map of known function pointer types
+/// * `declared_type_parameters`: set of known type template parameters
+/// * `declared_interfaces`: map of known interfaces
+/// * `known_functions`: map of known function names
+pub fn is_specialization_definition(
+    def: &FunctionDefinition,
+    declared_pointer_types: &IndexMap<SmolStr, Vec<FunctionPrototype>>,
+    declared_type_parameters: &IndexSet<SmolStr>,
+    declared_interfaces: &IndexMap<SmolStr, Interface>,
+    known_functions: &IndexMap<SmolStr, Vec<FunctionPrototype>>,
+) -> Result<bool> {
+    Ok(matches!(
+        parse_definition(
+            def.clone(),
+            declared_pointer_types,
+            declared_type_parameters,
+            declared_interfaces,
+            Some(known_functions),
+        )?,
+        TryTemplate::Template(template) if template.is_specialization()
+    ))
+}
+
+/// Extract the default value of a template parameter from its `layout(default_fn = ...)`
+/// qualifier
+fn parse_default_qualifier(qualifier: &TypeQualifier) -> Option<Expr> {
+    qualifier.qualifiers.iter().find_map(|spec| match &**spec {
+        TypeQualifierSpecData::Layout(layout) => layout.ids.iter().find_map(|id| match &**id {
+            LayoutQualifierSpecData::Identifier(ident, Some(expr)) if ident.0 == "default_fn" => {
+                Some((**expr).clone())
+            }
+            _ => None,
+        }),
+        _ => None,
+    })
+}
+
+/// Collect the type template parameters used in a function prototype, in order of first
+/// appearance
+fn collect_type_parameters(
+    prototype: &FunctionPrototype,
+    declared_type_parameters: &IndexSet<SmolStr>,
+) -> Vec<SmolStr> {
+    let mut res = IndexSet::new();
+
+    for ty in std::iter::once(&prototype.ty.ty).chain(
+        prototype
+            .parameters
+            .iter()
+            .map(|parameter| parameter.parameter_type().0),
+    ) {
+        if let TypeSpecifierNonArrayData::TypeName(tn) = &*ty.ty {
+            if declared_type_parameters.contains(&tn.0) {
+                res.insert(tn.0.clone());
+            }
+        }
+    }
+
+    res.into_iter().collect()
+}
+
+fn parse_definition(
+    def: FunctionDefinition,
+    declared_pointer_types: &IndexMap<SmolStr, Vec<FunctionPrototype>>,
+    declared_type_parameters: &IndexSet<SmolStr>,
+    declared_interfaces: &IndexMap<SmolStr, Interface>,
+    known_functions: Option<&IndexMap<SmolStr, Vec<FunctionPrototype>>>,
+) -> Result<TryTemplate> {
+    let mut parameters: Vec<TemplateParameter> = Vec::new();
+    let mut interface_parameters = IndexMap::new();
     let mut non_template_parameters = Vec::new();
     let span = def.span;
     let mut def = def.into_inner();
     let raw_prototype = def.prototype.clone();
+    let type_parameters = collect_type_parameters(&raw_prototype, declared_type_parameters);
 
     let len = def.prototype.parameters.len();
     let name: SmolStr = def.prototype.name.as_str().into();
 
     for (arg_id, parameter) in def.prototype.parameters.drain(0..len).enumerate() {
-        let (n, t) = match &*parameter {
-            FunctionParameterDeclarationData::Named(_, d) => (Some(d.ident.ident.0.clone()), &d.ty),
-            FunctionParameterDeclarationData::Unnamed(_, t) => (None, t),
+        let (q, n, t) = match &*parameter {
+            FunctionParameterDeclarationData::Named(q, d) => {
+                (q, Some(d.ident.ident.0.clone()), &d.ty)
+            }
+            FunctionParameterDeclarationData::Unnamed(q, t) => (q, None, t),
         };
 
+        if q.as_ref().is_some_and(|q| q.has_layout_flag("constant")) {
+            // Constant parameters are substituted in the body, they can't be arrays
+            if t.array_specifier.is_some() {
+                return Err(Error::ArrayedTemplateParameter {
+                    name,
+                    index: arg_id,
+                });
+            }
+
+            if parameters.iter().any(|p| p.default.is_some()) {
+                return Err(Error::MissingDefaultParameter {
+                    name,
+                    index: arg_id,
+                });
+            }
+
+            let mut typename = String::new();
+            glsl_lang::transpiler::glsl::show_type_specifier(
+                &mut typename,
+                t,
+                &mut glsl_lang::transpiler::glsl::FormattingState::default(),
+            )
+            .unwrap();
+
+            parameters.push(TemplateParameter {
+                kind: TemplateParameterKind::Constant,
+                typename: typename.into(),
+                symbol: n,
+                index: arg_id,
+                specialized: None,
+                default: None,
+                interface: None,
+                variadic: false,
+            });
+
+            continue;
+        }
+
         if let TypeSpecifierNonArrayData::TypeName(tn) = &*t.ty {
+            if let Some(interface) = declared_interfaces.get(&tn.0) {
+                if t.array_specifier.is_some() {
+                    return Err(Error::ArrayedTemplateParameter {
+                        name,
+                        index: arg_id,
+                    });
+                }
+
+                if parameters.iter().any(|p| p.default.is_some()) {
+                    return Err(Error::MissingDefaultParameter {
+                        name,
+                        index: arg_id,
+                    });
+                }
+
+                // An interface parameter stands for one template parameter per field
+                for (field, pointer_type) in &interface.fields {
+                    parameters.push(TemplateParameter {
+                        kind: TemplateParameterKind::Function,
+                        typename: pointer_type.clone(),
+                        symbol: n.as_ref().map(|n| field_symbol(n, field)),
+                        index: arg_id,
+                        specialized: None,
+                        default: None,
+                        interface: Some(tn.0.clone()),
+                        variadic: false,
+                    });
+                }
+
+                if let Some(n) = n {
+                    interface_parameters.insert(n, (tn.0.clone(), interface));
+                }
+
+                continue;
+            }
+
             if declared_pointer_types.contains_key(&tn.0) {
                 if t.array_specifier.is_some() {
                     return Err(Error::ArrayedTemplateParameter {
@@ -229,10 +1135,53 @@ pub fn parse_definition_as_template(
                         index: arg_id,
                     });
                 } else {
+                    // A template parameter marked with layout(specialize) binds the function it
+                    // is named after
+                    let specialize = q.as_ref().is_some_and(|q| q.has_layout_flag("specialize"));
+                    let specialized = match (known_functions, &n) {
+                        (Some(known_functions), Some(n)) if specialize => {
+                            if !known_functions.contains_key(n) {
+                                return Err(Error::UnknownSpecializedFunction {
+                                    name,
+                                    index: arg_id,
+                                    function: n.clone(),
+                                });
+                            }
+
+                            Some(n.clone())
+                        }
+                        (Some(_), None) if specialize => {
+                            return Err(Error::InvalidSpecialization {
+                                name,
+                                index: arg_id,
+                            });
+                        }
+                        _ => None,
+                    };
+
+                    // Parameter packs bind the trailing arguments, they can't be omitted
+                    let variadic = q.as_ref().is_some_and(|q| q.has_layout_flag("variadic"));
+                    let default = q
+                        .as_ref()
+                        .filter(|_| !variadic)
+                        .and_then(parse_default_qualifier);
+                    if default.is_none() && parameters.iter().any(|p| p.default.is_some()) {
+                        // Only trailing template parameters can be omitted
+                        return Err(Error::MissingDefaultParameter {
+                            name,
+                            index: arg_id,
+                        });
+                    }
+
                     parameters.push(TemplateParameter {
+                        kind: TemplateParameterKind::Function,
                         typename: tn.0.clone(),
                         symbol: n,
                         index: arg_id,
+                        specialized,
+                        default,
+                        interface: None,
+                        variadic,
                     });
 
                     continue;
@@ -243,18 +1192,35 @@ pub fn parse_definition_as_template(
         non_template_parameters.push(parameter);
     }
 
+    // Parameter packs bind the trailing template arguments
+    if let Some(pack) = parameters.iter().find(|p| p.variadic) {
+        if parameters.iter().any(|p| p.index > pack.index) {
+            return Err(Error::MisplacedParameterPack {
+                name,
+                index: pack.index,
+            });
+        }
+    }
+
     def.prototype.parameters.extend(non_template_parameters);
 
+    // Fields of interface parameters are accessed like struct fields in the body
+    expand_interface_parameters(&mut def.statement, interface_parameters);
+
     let def = Node::new(def, span);
 
-    if parameters.is_empty() {
+    if parameters.is_empty() && type_parameters.is_empty() {
         Ok(TryTemplate::Function(def.into()))
     } else {
         Ok(TryTemplate::Template(
             TemplateDefinition {
                 ast: def,
                 parameters,
+                type_parameters,
                 raw_prototype,
+                overload: 0,
+                pack_size: None,
+                generated: false,
             }
             .into(),
         ))
@@ -17,12 +17,26 @@ pub trait TransformUnit {
     /// Obtain a mutable reference to the template definition context
     fn global_scope_mut(&mut self) -> &mut GlobalScope;
 
+    /// Register the pointer types, templates and functions of the given declarations before
+    /// they are parsed
+    ///
+    /// This should be called with all the inputs before parsing any of them, otherwise the result
+    /// depends on the order of the declarations. See [GlobalScope::collect_declarations].
+    ///
+    /// # Parameters
+    ///
+    /// * `asts`: translation units which will be parsed by this transform unit
+    fn collect_declarations(&mut self, asts: &[&TranslationUnit]) -> Result<()> {
+        self.global_scope_mut()
+            .collect_declarations(asts.iter().flat_map(|ast| ast.0.iter()))
+    }
+
     /// Add a function declaration to the transform unit
     ///
     /// # Parameters
     ///
     /// * `def`: syntax tree for the function definition
-    fn push_function_declaration(&mut self, def: FunctionDefinition);
+    fn push_function_declaration(&mut self, def: FunctionDefinition) -> Result<()>;
 
     /// Parse a GLSLT declaration and add it to the transform unit
     ///
@@ -1,11 +1,12 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
-use glsl_lang::ast::*;
+use glsl_lang::{ast::*, visitor::*};
 
 use super::instantiate::InstantiateTemplate;
-use super::{FnHandle, FnRef, GlobalScope, ParsedDeclaration, TransformUnit};
+use super::{FnHandle, FnRef, GlobalScope, ParsedDeclaration, Scope, TransformUnit};
 
-use crate::{Result, TransformConfig};
+use crate::{glsl_ext::FunIdentifierExt, Result, TransformConfig};
 
 /// GLSLT template instantiation context
 #[derive(Default, Debug, Clone)]
@@ -14,6 +15,9 @@ pub struct Unit {
     global_scope: GlobalScope,
     /// Result of external declarations copied from input and generated through instantiation
     external_declarations: Vec<ExternalDeclaration>,
+    /// Functions of the input which have been collected but not declared yet, with all their
+    /// overloads
+    pending_functions: HashMap<SmolStr, Vec<FunctionPrototype>>,
 }
 
 impl Unit {
@@ -22,6 +26,7 @@ impl Unit {
         Self {
             global_scope: GlobalScope::new(),
             external_declarations: Vec::new(),
+            pending_functions: HashMap::new(),
         }
     }
 
@@ -34,6 +39,7 @@ impl Unit {
         Self {
             global_scope,
             external_declarations: Vec::new(),
+            pending_functions: HashMap::new(),
         }
     }
 
@@ -46,6 +52,52 @@ impl Unit {
         Self {
             global_scope: GlobalScope::with_config(config),
             external_declarations: Vec::new(),
+            pending_functions: HashMap::new(),
+        }
+    }
+
+    /// Declare the pending functions called by a definition before it is added to the output
+    ///
+    /// Since declarations are collected before being parsed, template instances can call
+    /// functions which are only defined later in the input.
+    fn declare_called_functions(&mut self, def: &FunctionDefinition) {
+        struct CalledFunctions<'u> {
+            pending_functions: &'u mut HashMap<SmolStr, Vec<FunctionPrototype>>,
+            prototypes: Vec<FunctionPrototype>,
+        }
+
+        impl Visitor for CalledFunctions<'_> {
+            fn visit_fun_identifier(&mut self, node: &FunIdentifier) -> Visit {
+                if let Some(ident) = node.as_ident_or_type_name() {
+                    if let Some(prototypes) = self.pending_functions.remove(ident) {
+                        self.prototypes.extend(prototypes);
+                    }
+                }
+
+                Visit::Children
+            }
+        }
+
+        // The function itself doesn't need a forward declaration
+        self.pending_functions.remove(&def.prototype.name.0);
+
+        let mut visitor = CalledFunctions {
+            pending_functions: &mut self.pending_functions,
+            prototypes: Vec::new(),
+        };
+        def.visit(&mut visitor);
+
+        for prototype in visitor.prototypes {
+            debug!("declared function before definition: {}", prototype.name.0);
+
+            let span = prototype.span;
+            self.external_declarations.push(ExternalDeclaration::new(
+                ExternalDeclarationData::Declaration(Declaration::new(
+                    DeclarationData::FunctionPrototype(prototype),
+                    span,
+                )),
+                span,
+            ));
         }
     }
 
@@ -54,8 +106,22 @@ impl Unit {
     /// All the declarations given as input will be included in-order in the output, with the
     /// template instantiations interleaved when needed.
     pub fn into_translation_unit(self) -> Result<TranslationUnit> {
+        let global_scope = self.global_scope;
+
         Ok(TranslationUnit(
-            self.external_declarations.into_iter().collect(),
+            self.external_declarations
+                .into_iter()
+                .filter(|decl| match &decl.content {
+                    // Forward declarations which turned out to be pointer types
+                    ExternalDeclarationData::Declaration(decl) => match &decl.content {
+                        DeclarationData::FunctionPrototype(prototype) => {
+                            !global_scope.is_promoted_prototype(prototype)
+                        }
+                        _ => true,
+                    },
+                    _ => true,
+                })
+                .collect(),
         ))
     }
 }
@@ -69,10 +135,43 @@ impl TransformUnit for Unit {
         &mut self.global_scope
     }
 
-    fn push_function_declaration(&mut self, def: FunctionDefinition) {
+    fn collect_declarations(&mut self, asts: &[&TranslationUnit]) -> Result<()> {
         self.global_scope
-            .known_functions_mut()
-            .insert(def.prototype.name.0.clone(), def.prototype.clone());
+            .collect_declarations(asts.iter().flat_map(|ast| ast.0.iter()))?;
+
+        // Non-template functions may be used before their definition
+        for decl in asts.iter().flat_map(|ast| ast.0.iter()) {
+            if !matches!(decl.content, ExternalDeclarationData::FunctionDefinition(_)) {
+                continue;
+            }
+
+            // Private functions are declared with their output name
+            let mut decl = decl.clone();
+            self.global_scope.rename_private_functions(&mut decl);
+
+            if let ExternalDeclarationData::FunctionDefinition(def) = &decl.content {
+                let name = &def.prototype.name.0;
+                if self.global_scope.get_templates(name).is_empty()
+                    && self
+                        .global_scope
+                        .get_template_specializations(name)
+                        .is_empty()
+                {
+                    self.pending_functions
+                        .entry(name.clone())
+                        .or_default()
+                        .push(def.prototype.clone());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn push_function_declaration(&mut self, def: FunctionDefinition) -> Result<()> {
+        self.declare_called_functions(&def);
+
+        self.global_scope.push_function_declaration(&def);
 
         // Add the definition to the declarations
         // TODO: Don't clone def.span?
@@ -81,6 +180,8 @@ impl TransformUnit for Unit {
             ExternalDeclarationData::FunctionDefinition(def),
             span,
         ));
+
+        Ok(())
     }
 
     fn parse_external_declaration(
@@ -88,7 +189,7 @@ impl TransformUnit for Unit {
         extdecl: ExternalDeclaration,
     ) -> Result<Option<FnHandle>> {
         let unparsed = match self.global_scope.parse_external_declaration(extdecl)? {
-            ParsedDeclaration::ConsumedAsType => {
+            ParsedDeclaration::ConsumedAsType | ParsedDeclaration::ConsumedAsPragma => {
                 return Ok(None);
             }
             ParsedDeclaration::ConsumedAsTemplate(r) => {
@@ -106,7 +207,7 @@ impl TransformUnit for Unit {
                     InstantiateTemplate::new(None).instantiate(&mut self.global_scope, def)?;
 
                 for d in decls {
-                    self.push_function_declaration(d);
+                    self.push_function_declaration(d)?;
                 }
 
                 let f = self.external_declarations.last().unwrap();
@@ -126,9 +227,17 @@ impl TransformUnit for Unit {
                     _ => unreachable!(),
                 }
             }
-            other => self
-                .external_declarations
-                .push(Node::new(other, extdecl.span)),
+            other => {
+                if let ExternalDeclarationData::Declaration(decl) = &other {
+                    if let DeclarationData::FunctionPrototype(prototype) = &decl.content {
+                        // Forward declarations in the input are enough
+                        self.pending_functions.remove(&prototype.name.0);
+                    }
+                }
+
+                self.external_declarations
+                    .push(Node::new(other, extdecl.span))
+            }
         }
 
         Ok(None)
@@ -37,3 +37,13 @@ static BUILTIN_FUNCTION_NAMES: &[&str] = &[
 pub fn is_builtin_glsl_function(name: &str) -> bool {
     BUILTIN_FUNCTION_NAMES.binary_search(&name).is_ok()
 }
+
+/// Return the positions of the `out` parameters of a built-in GLSL function
+pub fn builtin_glsl_output_parameters(name: &str) -> &'static [usize] {
+    match name {
+        "frexp" | "modf" => &[1],
+        "uaddCarry" | "usubBorrow" => &[2],
+        "imulExtended" | "umulExtended" => &[2, 3],
+        _ => &[],
+    }
+}
@@ -0,0 +1,87 @@
+//! Tests for lambda aliases
+
+mod common;
+
+#[test]
+fn alias_global_and_local() {
+    common::verify_both(
+        r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float opUnion(in sdf3d a, in sdf3d b, vec3 p) { return min(a(p), b(p)); }
+
+sdf3d bigSphere = sdSphere(_p / 2.) * 2.;
+
+void main() {
+    float r = 3.;
+    sdf3d scaled = sdSphere(_1 / r) * r;
+    float d = opUnion(bigSphere, scaled, vec3(1.));
+    float e = scaled(vec3(2.));
+}"#,
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float _glslt_opUnion_0(vec3 p, float _glslt_lp0) { return min(sdSphere(p / 2.) * 2., sdSphere(p / _glslt_lp0) * _glslt_lp0); }
+
+void main() {
+    float r = 3.;
+    float d = _glslt_opUnion_0(vec3(1.), r);
+    float e = sdSphere(vec3(2.) / r) * r;
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn alias_composition() {
+    common::verify_both(
+        r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float opUnion(in sdf3d a, in sdf3d b, vec3 p) { return min(a(p), b(p)); }
+
+float opScale(in sdf3d f, float s, vec3 p) { return f(p / s) * s; }
+
+void main() {
+    sdf3d big = opScale(sdSphere, 2., _1);
+    sdf3d both = opUnion(big, sdSphere, _p);
+    float d = both(vec3(1.));
+}"#,
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float _glslt_opScale_0(float s, vec3 p) { return sdSphere(p / s) * s; }
+
+float _glslt_opUnion_0(vec3 p) { return min(_glslt_opScale_0(2., p), sdSphere(p)); }
+
+void main() {
+    float d = _glslt_opUnion_0(vec3(1.));
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn alias_shadowed_by_parameter() {
+    common::verify_both(
+        r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+sdf3d f = sdSphere(_1 * 2.);
+
+float eval(in sdf3d f, vec3 p) { return f(p); }
+
+void main() {
+    float d = eval(sdSphere, vec3(1.));
+}"#,
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float _glslt_eval_0(vec3 p) { return sdSphere(p); }
+
+void main() {
+    float d = _glslt_eval_0(vec3(1.));
+}"#,
+        "main",
+    );
+}
@@ -135,3 +135,253 @@ void main() {
         "main",
     );
 }
+
+#[test]
+fn capture_write() {
+    common::verify_transform(
+        r#"void accfn(float x);
+
+void forEach(in accfn f) {
+    f(1.);
+    f(2.);
+}
+
+void store(float x, out float y) {
+    y = x;
+}
+
+void main() {
+    float total = 0.;
+    float ipart;
+    float last;
+    float scale = 2.;
+    forEach(total += _1 * scale);
+    forEach(modf(_1, ipart));
+    forEach(store(_1, last));
+}"#,
+        r#"void store(float x, out float y) {
+    y = x;
+}
+
+void _glslt_forEach_0(inout float _glslt_lp0, float _glslt_lp3) {
+    _glslt_lp0 += 1. * _glslt_lp3;
+    _glslt_lp0 += 2. * _glslt_lp3;
+}
+
+void _glslt_forEach_1(inout float _glslt_lp1) {
+    modf(1., _glslt_lp1);
+    modf(2., _glslt_lp1);
+}
+
+void _glslt_forEach_2(inout float _glslt_lp2) {
+    store(1., _glslt_lp2);
+    store(2., _glslt_lp2);
+}
+
+void main() {
+    float total = 0.;
+    float ipart;
+    float last;
+    float scale = 2.;
+    _glslt_forEach_0(total, scale);
+    _glslt_forEach_1(ipart);
+    _glslt_forEach_2(last);
+}"#,
+    );
+}
+
+#[test]
+fn capture_write_nested() {
+    common::verify_both(
+        r#"void accfn(float x);
+
+void forEach(in accfn f) {
+    f(1.);
+}
+
+void forEachTwice(in accfn f) {
+    forEach(f);
+    forEach(f);
+}
+
+void main() {
+    float total = 0.;
+    forEachTwice(total += _1);
+}"#,
+        r#"void _glslt_forEach_0(inout float _glslt_lp0) {
+    _glslt_lp0 += 1.;
+}
+
+void _glslt_forEachTwice_0(inout float _glslt_lp0) {
+    _glslt_forEach_0(_glslt_lp0);
+    _glslt_forEach_0(_glslt_lp0);
+}
+
+void main() {
+    float total = 0.;
+    _glslt_forEachTwice_0(total);
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn capture_write_read_only() {
+    use glslt::glsl_lang::parse::IntoParseBuilderExt;
+
+    for (src, name) in [
+        (
+            "uniform float total;\nvoid main() { forEach(total += _1); }",
+            "total",
+        ),
+        (
+            "uniform Params { float total; };\nvoid main() { forEach(total += _1); }",
+            "total",
+        ),
+        ("void main() { const float c = 1.; forEach(c += _1); }", "c"),
+    ] {
+        let src = format!(
+            "void accfn(float x);\nvoid forEach(in accfn f) {{ f(1.); }}\n{}",
+            src
+        );
+
+        let tu: glslt::glsl_lang::ast::TranslationUnit = src
+            .as_str()
+            .builder()
+            .context(&glslt::parse::make_parse_context(None))
+            .parse()
+            .expect("failed to parse source")
+            .0;
+
+        assert!(matches!(
+            glslt::transform(std::iter::once(&tu), Default::default()),
+            Err(glslt::Error::ReadOnlyCapture(n)) if n == name
+        ));
+    }
+}
+
+#[test]
+fn capture_block_scope() {
+    common::verify_both(
+        r#"int intfn(int x);
+
+int fnTemplate(in intfn callback) {
+    return callback(1);
+}
+
+int g = 2;
+
+void main() {
+    {
+        int g = 8;
+        fnTemplate(_1 + g);
+    }
+
+    fnTemplate(_1 * g);
+
+    for (int i = 0; i < 2; i++) {
+        fnTemplate(_1 - i);
+    }
+}"#,
+        r#"int g = 2;
+
+int _glslt_fnTemplate_0(int _glslt_lp0) {
+    return 1 + _glslt_lp0;
+}
+
+int _glslt_fnTemplate_1() {
+    return 1 * g;
+}
+
+int _glslt_fnTemplate_2(int _glslt_lp1) {
+    return 1 - _glslt_lp1;
+}
+
+void main() {
+    {
+        int g = 8;
+        _glslt_fnTemplate_0(g);
+    }
+
+    _glslt_fnTemplate_1();
+
+    for (int i = 0; i < 2; i++) {
+        _glslt_fnTemplate_2(i);
+    }
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn capture_sibling_types() {
+    common::verify_both(
+        r#"float floatfn(float x);
+
+float fnTemplate(in floatfn callback) {
+    return callback(1.);
+}
+
+void main() {
+    {
+        float d = 2.;
+        fnTemplate(_1 * float(d));
+    }
+
+    {
+        int d = 3;
+        fnTemplate(_1 * float(d));
+    }
+
+    while (bool d = true) {
+        fnTemplate(_1 * float(d));
+    }
+
+    switch (1) {
+        case 1:
+            vec2 d = vec2(1.);
+            fnTemplate(_1 * float(d));
+            break;
+    }
+}"#,
+        r#"float _glslt_fnTemplate_0(float _glslt_lp0) {
+    return 1. * float(_glslt_lp0);
+}
+
+float _glslt_fnTemplate_1(int _glslt_lp1) {
+    return 1. * float(_glslt_lp1);
+}
+
+float _glslt_fnTemplate_2(bool _glslt_lp2) {
+    return 1. * float(_glslt_lp2);
+}
+
+float _glslt_fnTemplate_3(vec2 _glslt_lp3) {
+    return 1. * float(_glslt_lp3);
+}
+
+void main() {
+    {
+        float d = 2.;
+        _glslt_fnTemplate_0(d);
+    }
+
+    {
+        int d = 3;
+        _glslt_fnTemplate_1(d);
+    }
+
+    while (bool d = true) {
+        _glslt_fnTemplate_2(d);
+    }
+
+    switch (1) {
+        case 1:
+            vec2 d = vec2(1.);
+            _glslt_fnTemplate_3(d);
+            break;
+    }
+}"#,
+        "main",
+    );
+}
@@ -133,14 +133,52 @@ fn to_string(tu: &TranslationUnit) -> String {
     s
 }
 
-fn parse(input: &str) -> glsl_lang::ast::TranslationUnit {
+pub fn parse(input: &str) -> glsl_lang::ast::TranslationUnit {
     use glsl_lang::parse::IntoParseBuilderExt;
     let (mut tu, _, lexer) = input
+        .builder()
+        .context(&glslt::parse::make_parse_context_with_functions(
+            None,
+            glslt::parse::defined_functions::<glsl_lang::lexer::min::str::Lexer>(input),
+        ))
+        .parse()
+        .expect("failed to parse source");
+    lexer.into_directives().inject(&mut tu);
+    tu
+}
+
+/// Write the given files to a temporary directory, and parse the first one with includes enabled
+#[allow(dead_code)]
+pub fn parse_files(name: &str, files: &[(&str, &str)]) -> TranslationUnit {
+    use glsl_lang::{lexer::full::fs::PreprocessorExt, parse::IntoParseBuilderExt};
+
+    let dir = std::env::temp_dir().join(format!("glslt-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    for (path, contents) in files {
+        std::fs::write(dir.join(path), contents).unwrap();
+    }
+
+    let mut processor = glsl_lang_pp::processor::fs::Processor::<glslt::parse::PragmaOnceFs>::new();
+    let state = glsl_lang_pp::processor::ProcessorState::builder()
+        .extension(
+            glsl_lang_pp::ext_name!("GL_GOOGLE_include_directive"),
+            glsl_lang_pp::processor::nodes::ExtensionBehavior::Enable,
+        )
+        .finish();
+    let (mut tu, _, lexer): (TranslationUnit, _, _) = processor
+        .open(dir.join(files[0].0))
+        .expect("failed to open input")
+        .with_state(state.clone())
         .builder()
         .context(&glslt::parse::make_parse_context(None))
         .parse()
         .expect("failed to parse source");
     lexer.into_directives().inject(&mut tu);
+    glslt::parse::resolve_imports(&mut processor, &state, &dir, &mut tu)
+        .expect("failed to resolve imports");
+
+    std::fs::remove_dir_all(&dir).unwrap();
     tu
 }
 
@@ -201,11 +239,16 @@ fn verify_transform_impl(
 
 #[allow(dead_code)]
 pub fn verify_transform(src: &str, expected: &str) {
-    let config = TransformConfig::default();
+    verify_transform_with_config(src, expected, TransformConfig::default());
+}
 
+#[allow(dead_code)]
+pub fn verify_transform_with_config(src: &str, expected: &str, config: TransformConfig) {
     verify_transform_impl(src, expected, &config, |src| {
         // Transform source
         let mut unit = glslt::transform::Unit::with_config(config.clone());
+        unit.collect_declarations(&[&src])
+            .expect("failed to collect declarations");
         for decl in src.0.into_iter() {
             let err = format!("failed to transform declaration: {:?}", decl);
             unit.parse_external_declaration(decl).expect(&err);
@@ -219,11 +262,21 @@ pub fn verify_transform(src: &str, expected: &str) {
 
 #[allow(dead_code)]
 pub fn verify_min_transform(src: &str, expected: &str, entry_point: &str) {
-    let config = TransformConfig::default();
+    verify_min_transform_with_config(src, expected, entry_point, TransformConfig::default());
+}
 
+#[allow(dead_code)]
+pub fn verify_min_transform_with_config(
+    src: &str,
+    expected: &str,
+    entry_point: &str,
+    config: TransformConfig,
+) {
     verify_transform_impl(src, expected, &config, |src| {
         // Transform source
         let mut unit = glslt::transform::MinUnit::with_config(config.clone());
+        unit.collect_declarations(&[&src])
+            .expect("failed to collect declarations");
         for decl in src.0.into_iter() {
             let err = format!("failed to transform declaration: {:?}", decl);
             unit.parse_external_declaration(decl).expect(&err);
@@ -241,3 +294,14 @@ pub fn verify_both(src: &str, expected: &str, entry_point: &str) {
     verify_transform(src, expected);
     verify_min_transform(src, expected, entry_point);
 }
+
+#[allow(dead_code)]
+pub fn verify_both_with_config(
+    src: &str,
+    expected: &str,
+    entry_point: &str,
+    config: TransformConfig,
+) {
+    verify_transform_with_config(src, expected, config.clone());
+    verify_min_transform_with_config(src, expected, entry_point, config);
+}
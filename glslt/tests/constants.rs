@@ -0,0 +1,156 @@
+//! Tests for constant template parameters
+
+mod common;
+
+#[test]
+fn constant_array_size() {
+    common::verify_both(
+        r#"float sum(layout(constant) int N, float x[N]) {
+    float s = 0.;
+    for (int i = 0; i < N; ++i) s += x[i];
+    return s;
+}
+
+void main() {
+    float x[3] = float[3](1., 2., 3.);
+    float s = sum(3, x);
+}"#,
+        r#"float _glslt_sum_0(float x[3]) {
+    float s = 0.;
+    for (int i = 0; i < 3; ++i) s += x[i];
+    return s;
+}
+
+void main() {
+    float x[3] = float[3](1., 2., 3.);
+    float s = _glslt_sum_0(x);
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn constant_with_function_parameter() {
+    common::verify_both(
+        r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float opRepeat(in sdf3d f, layout(constant) int N, vec3 p) {
+    float r = f(p);
+    for (int i = 1; i < N; ++i) r = min(r, f(p + float(i)));
+    return r;
+}
+
+void main() {
+    float a = opRepeat(sdSphere, 4, vec3(1.));
+    float b = opRepeat(sdSphere, 2, vec3(1.));
+    float c = opRepeat(sdSphere, 4, vec3(2.));
+}"#,
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float _glslt_opRepeat_0(vec3 p) {
+    float r = sdSphere(p);
+    for (int i = 1; i < 4; ++i) r = min(r, sdSphere(p + float(i)));
+    return r;
+}
+
+float _glslt_opRepeat_1(vec3 p) {
+    float r = sdSphere(p);
+    for (int i = 1; i < 2; ++i) r = min(r, sdSphere(p + float(i)));
+    return r;
+}
+
+void main() {
+    float a = _glslt_opRepeat_0(vec3(1.));
+    float b = _glslt_opRepeat_1(vec3(1.));
+    float c = _glslt_opRepeat_0(vec3(2.));
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn nested_constant() {
+    common::verify_both(
+        r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float opScale(in sdf3d f, layout(constant) float S, vec3 p) { return f(p / S) * S; }
+
+float opTwice(in sdf3d f, layout(constant) float S, vec3 p) { return opScale(f, S * 2., p); }
+
+void main() {
+    float d = opTwice(sdSphere, 1. + 1., vec3(1.));
+}"#,
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float _glslt_opScale_0(vec3 p) { return sdSphere(p / ((1. + 1.) * 2.)) * ((1. + 1.) * 2.); }
+
+float _glslt_opTwice_0(vec3 p) { return _glslt_opScale_0(p); }
+
+void main() {
+    float d = _glslt_opTwice_0(vec3(1.));
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn non_constant_argument() {
+    use glslt::glsl_lang::parse::IntoParseBuilderExt;
+
+    let tu: glslt::glsl_lang::ast::TranslationUnit =
+        r#"float sum(layout(constant) int N, float x[N]) {
+    float s = 0.;
+    for (int i = 0; i < N; ++i) s += x[i];
+    return s;
+}
+
+void main() {
+    int n = 3;
+    float x[3] = float[3](1., 2., 3.);
+    float s = sum(n, x);
+}"#
+        .builder()
+        .context(&glslt::parse::make_parse_context(None))
+        .parse()
+        .expect("failed to parse source")
+        .0;
+
+    assert!(matches!(
+        glslt::transform(std::iter::once(&tu), Default::default()),
+        Err(glslt::Error::NonConstantTemplateArgument { name, parameter })
+            if name == "sum" && parameter == "N"
+    ));
+}
+
+#[test]
+fn shadowed_constant() {
+    common::verify_both(
+        r#"float shadow(layout(constant) int N, float x) {
+    {
+        int N = 7;
+        x += float(N);
+    }
+    return x * float(N);
+}
+
+void main() {
+    float y = shadow(4, 1.);
+}"#,
+        r#"float _glslt_shadow_0(float x) {
+    {
+        int N = 7;
+        x += float(N);
+    }
+    return x * float(4);
+}
+
+void main() {
+    float y = _glslt_shadow_0(1.);
+}"#,
+        "main",
+    );
+}
@@ -0,0 +1,106 @@
+//! Tests for deduplication of identical template instances
+
+mod common;
+
+#[test]
+fn dedup_placeholders() {
+    common::verify_both(
+        r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p, float r) {
+    return length(p) - r;
+}
+
+float opElongate(in sdf3d primitive, in vec3 p, in vec3 h) {
+    vec3 q = p - clamp(p, -h, h);
+    return primitive(q);
+}
+
+void main() {
+    float a = opElongate(sdSphere(_1, 4.), vec3(1.), vec3(1., 2., 3.));
+    float b = opElongate(sdSphere(_p, 4.), vec3(2.), vec3(1., 2., 3.));
+}"#,
+        r#"float sdSphere(vec3 p, float r) {
+    return length(p) - r;
+}
+
+float _glslt_opElongate_0(in vec3 p, in vec3 h) {
+    vec3 q = p - clamp(p, -h, h);
+    return sdSphere(q, 4.);
+}
+
+void main() {
+    float a = _glslt_opElongate_0(vec3(1.), vec3(1., 2., 3.));
+    float b = _glslt_opElongate_0(vec3(2.), vec3(1., 2., 3.));
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn dedup_captures() {
+    common::verify_both(
+        r#"int intfn(int x);
+
+int fnTemplate(in intfn callback) {
+    return callback(1);
+}
+
+void main() {
+    int a = 1;
+    int b = 2;
+    fnTemplate(_1 * a);
+    fnTemplate(_1 * b);
+    fnTemplate(_1 + b);
+}"#,
+        r#"int _glslt_fnTemplate_0(int _glslt_lp0) {
+    return 1 * _glslt_lp0;
+}
+
+int _glslt_fnTemplate_1(int _glslt_lp1) {
+    return 1 + _glslt_lp1;
+}
+
+void main() {
+    int a = 1;
+    int b = 2;
+    _glslt_fnTemplate_0(a);
+    _glslt_fnTemplate_0(b);
+    _glslt_fnTemplate_1(b);
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn dedup_nested() {
+    common::verify_both(
+        r#"int IntCallback(int x);
+
+int innerTemplate(IntCallback cbi) {
+    return cbi(1);
+}
+
+int outerTemplate(IntCallback cbo) {
+    return innerTemplate(cbo);
+}
+
+void main() {
+    outerTemplate(2 * _1);
+    outerTemplate(2 * _x);
+}"#,
+        r#"int _glslt_innerTemplate_1() {
+    return 2 * 1;
+}
+
+int _glslt_outerTemplate_0() {
+    return _glslt_innerTemplate_1();
+}
+
+void main() {
+    _glslt_outerTemplate_0();
+    _glslt_outerTemplate_0();
+}"#,
+        "main",
+    );
+}
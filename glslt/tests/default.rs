@@ -0,0 +1,83 @@
+//! Tests for default values of template parameters
+
+mod common;
+
+#[test]
+fn default_function() {
+    common::verify_transform(
+        r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float sdBox(vec3 p) { return max(p.x, max(p.y, p.z)); }
+
+float opUnion(in sdf3d a, layout(default_fn = sdSphere) in sdf3d b, vec3 p) { return min(a(p), b(p)); }
+
+void main() {
+    float a = opUnion(sdBox, vec3(1.));
+    float b = opUnion(sdBox, sdSphere, vec3(2.));
+    float c = opUnion(sdBox, sdBox, vec3(3.));
+}"#,
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float sdBox(vec3 p) { return max(p.x, max(p.y, p.z)); }
+
+float _glslt_opUnion_0(vec3 p) { return min(sdBox(p), sdSphere(p)); }
+
+float _glslt_opUnion_1(vec3 p) { return min(sdBox(p), sdBox(p)); }
+
+void main() {
+    float a = _glslt_opUnion_0(vec3(1.));
+    float b = _glslt_opUnion_0(vec3(2.));
+    float c = _glslt_opUnion_1(vec3(3.));
+}"#,
+    );
+}
+
+#[test]
+fn default_lambda() {
+    common::verify_both(
+        r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float opScale(in sdf3d f, float s, vec3 p) { return f(p / s) * s; }
+
+float eval(layout(default_fn = opScale(sdSphere, 2., _1)) in sdf3d f, vec3 p) { return f(p); }
+
+void main() {
+    float a = eval(vec3(1.));
+}"#,
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float _glslt_opScale_0(float s, vec3 p) { return sdSphere(p / s) * s; }
+
+float _glslt_eval_0(vec3 p) { return _glslt_opScale_0(2., p); }
+
+void main() {
+    float a = _glslt_eval_0(vec3(1.));
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn default_not_trailing() {
+    use glslt::glsl_lang::parse::IntoParseBuilderExt;
+
+    let tu: glslt::glsl_lang::ast::TranslationUnit = r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float opUnion(layout(default_fn = sdSphere) in sdf3d a, in sdf3d b, vec3 p) { return min(a(p), b(p)); }"#
+        .builder()
+        .context(&glslt::parse::make_parse_context(None))
+        .parse()
+        .expect("failed to parse source")
+        .0;
+
+    assert!(matches!(
+        glslt::transform(std::iter::once(&tu), Default::default()),
+        Err(glslt::Error::MissingDefaultParameter { index: 1, .. })
+    ));
+}
@@ -0,0 +1,160 @@
+//! Tests for runtime dispatch over function tables
+
+mod common;
+
+#[test]
+fn dispatch_template_argument() {
+    common::verify_transform(
+        r#"float sdf3d(in vec3 p);
+
+uniform int shape;
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float sdBox(vec3 p, vec3 b) { return length(max(abs(p) - b, 0.)); }
+
+float opScale(in sdf3d f, float s, vec3 p) { return f(p / s) * s; }
+
+sdf3d shapes[] = { sdSphere, sdBox(_p, vec3(1.)) };
+
+void main() {
+    float d = opScale(shapes[shape], 2., vec3(1.));
+}"#,
+        r#"uniform int shape;
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float sdBox(vec3 p, vec3 b) { return length(max(abs(p) - b, 0.)); }
+
+float _glslt_dispatch_sdf3d_2_0(int _glslt_selector, in vec3 p) {
+    switch (_glslt_selector) {
+    case 0:
+        return sdSphere(p);
+    default:
+        return sdBox(p, vec3(1.));
+    }
+}
+
+float _glslt_opScale_0(float s, vec3 p) { return _glslt_dispatch_sdf3d_2_0(shape, p / s) * s; }
+
+void main() {
+    float d = _glslt_opScale_0(2., vec3(1.));
+}"#,
+    );
+}
+
+#[test]
+fn dispatch_local_table_call() {
+    common::verify_both(
+        r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float sdBox(vec3 p, vec3 b) { return length(max(abs(p) - b, 0.)); }
+
+void main() {
+    vec3 b = vec3(2.);
+    sdf3d shapes[] = { sdSphere, sdBox(_p, b) };
+    for (int i = 0; i < 2; ++i) {
+        float d = shapes[i](vec3(1.));
+    }
+}"#,
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float sdBox(vec3 p, vec3 b) { return length(max(abs(p) - b, 0.)); }
+
+float _glslt_dispatch_sdf3d_2_0(int _glslt_selector, in vec3 p, vec3 _glslt_lp0) {
+    switch (_glslt_selector) {
+    case 0:
+        return sdSphere(p);
+    default:
+        return sdBox(p, _glslt_lp0);
+    }
+}
+
+void main() {
+    vec3 b = vec3(2.);
+    for (int i = 0; i < 2; ++i) {
+        float d = _glslt_dispatch_sdf3d_2_0(i, vec3(1.), b);
+    }
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn dispatch_void_pointer_type() {
+    common::verify_both(
+        r#"void paint(inout vec3 c);
+
+void red(inout vec3 c) { c.r = 1.; }
+
+void blue(inout vec3 c) { c.b = 1.; }
+
+paint painters[] = { red, blue };
+
+void main() {
+    vec3 c = vec3(0.);
+    painters[int(c.g)](c);
+}"#,
+        r#"void red(inout vec3 c) { c.r = 1.; }
+
+void blue(inout vec3 c) { c.b = 1.; }
+
+void _glslt_dispatch_paint_2_0(int _glslt_selector, inout vec3 c) {
+    switch (_glslt_selector) {
+    case 0:
+        red(c);
+        break;
+    default:
+        blue(c);
+        break;
+    }
+}
+
+void main() {
+    vec3 c = vec3(0.);
+    _glslt_dispatch_paint_2_0(int(c.g), c);
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn dispatch_user_template_same_name() {
+    common::verify_transform(
+        r#"float sdf3d(in vec3 p);
+
+uniform int shape;
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float dispatch_sdf3d_2(in sdf3d a, in sdf3d b, vec3 p) { return min(a(p), b(p)); }
+
+sdf3d shapes[] = { sdSphere, length(_p) };
+
+void main() {
+    float d = shapes[shape](vec3(1.));
+    float e = dispatch_sdf3d_2(sdSphere, length(_p), vec3(2.));
+}"#,
+        r#"uniform int shape;
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float _glslt_dispatch_sdf3d_2_0(int _glslt_selector, in vec3 p) {
+    switch (_glslt_selector) {
+    case 0:
+        return sdSphere(p);
+    default:
+        return length(p);
+    }
+}
+
+float _glslt_dispatch_sdf3d_2_1(vec3 p) { return min(sdSphere(p), length(p)); }
+
+void main() {
+    float d = _glslt_dispatch_sdf3d_2_0(shape, vec3(1.));
+    float e = _glslt_dispatch_sdf3d_2_1(vec3(2.));
+}"#,
+    );
+}
@@ -0,0 +1,143 @@
+//! Tests for duplicate definitions across input files
+
+mod common;
+
+use common::parse_files;
+
+use glslt::glsl_lang::{ast::TranslationUnit, parse::IntoParseBuilderExt};
+
+fn parse(src: &str) -> TranslationUnit {
+    src.builder()
+        .context(&glslt::parse::make_parse_context(None))
+        .parse()
+        .expect("failed to parse source")
+        .0
+}
+
+#[test]
+fn duplicate_function_across_files() {
+    let tu = parse_files(
+        "duplicate-function",
+        &[
+            (
+                "main.glsl",
+                r#"#include "a.glsl"
+#include "b.glsl"
+
+void main() {
+    float d = sdSphere(vec3(1.));
+}
+"#,
+            ),
+            (
+                "a.glsl",
+                "float sdSphere(vec3 p) { return length(p) - 1.; }\n",
+            ),
+            (
+                "b.glsl",
+                "float sdSphere(vec3 p) { return length(p) - 2.; }\n",
+            ),
+        ],
+    );
+
+    assert!(matches!(
+        glslt::transform(std::iter::once(&tu), Default::default()),
+        Err(glslt::Error::DuplicateDefinition { name, span: Some(span), previous_span: Some(previous_span) })
+            if name == "sdSphere" && span.source_id() != previous_span.source_id()
+    ));
+
+    assert!(matches!(
+        glslt::transform_min(
+            std::iter::once(&tu),
+            std::iter::once("main"),
+            Default::default()
+        ),
+        Err(glslt::Error::DuplicateDefinition { name, .. }) if name == "sdSphere"
+    ));
+}
+
+#[test]
+fn same_input_twice() {
+    let a = parse("float sdSphere(vec3 p) { return length(p) - 1.; }");
+
+    assert!(matches!(
+        glslt::transform(vec![&a, &a].into_iter(), Default::default()),
+        Err(glslt::Error::DuplicateDefinition { name, .. }) if name == "sdSphere"
+    ));
+}
+
+#[test]
+fn same_struct_twice() {
+    // Redefining a struct in a single parse is a syntax error, so it can only come from inputs
+    // parsed separately
+    let a = parse("struct S { float x; };");
+
+    assert!(matches!(
+        glslt::transform(vec![&a, &a].into_iter(), Default::default()),
+        Err(glslt::Error::DuplicateDefinition { name, .. }) if name == "S"
+    ));
+}
+
+#[test]
+fn overloads_across_inputs() {
+    let a = parse("float f(float x) { return x; }");
+    let b = parse("vec2 f(vec2 x) { return x; }");
+
+    glslt::transform(vec![&a, &b].into_iter(), Default::default())
+        .expect("failed to transform overloads");
+}
+
+#[test]
+fn pragma_once() {
+    let tu = parse_files(
+        "pragma-once",
+        &[
+            (
+                "main.glsl",
+                r#"#include "lib.glsl"
+#include "lib.glsl"
+
+void main() {
+    float d = sdSphere(vec3(1.));
+}
+"#,
+            ),
+            (
+                "lib.glsl",
+                r#"#pragma once
+float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+"#,
+            ),
+        ],
+    );
+
+    let tu = glslt::transform(std::iter::once(&tu), Default::default())
+        .expect("failed to transform source");
+
+    let mut s = String::new();
+    glslt::glsl_lang::transpiler::glsl::show_translation_unit(&mut s, &tu, Default::default())
+        .unwrap();
+
+    assert_eq!(s.matches("float sdSphere").count(), 1, "{}", s);
+}
+
+#[test]
+fn duplicate_function_same_file() {
+    let tu = parse(
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float sdSphere(vec3 p) { return length(p) - 2.; }
+
+void main() {
+    float d = sdSphere(vec3(1.));
+}"#,
+    );
+
+    assert!(matches!(
+        glslt::transform(std::iter::once(&tu), Default::default()),
+        Err(glslt::Error::DuplicateDefinition { name, span: Some(span), previous_span: Some(previous_span) })
+            if name == "sdSphere" && span.start() != previous_span.start()
+    ));
+}
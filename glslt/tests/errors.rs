@@ -0,0 +1,101 @@
+//! Tests for inputs which used to make the transform panic
+
+mod common;
+
+use glslt::glsl_lang::{ast::TranslationUnit, parse::IntoParseBuilderExt};
+
+fn parse(src: &str) -> TranslationUnit {
+    src.builder()
+        .context(&glslt::parse::make_parse_context(None))
+        .parse()
+        .expect("failed to parse source")
+        .0
+}
+
+#[test]
+fn local_struct_declaration() {
+    common::verify_both(
+        r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float eval(in sdf3d f, vec3 p) { return f(p); }
+
+void main() {
+    struct S { float x; };
+    float d = eval(sdSphere, vec3(1.));
+}"#,
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float _glslt_eval_0(vec3 p) { return sdSphere(p); }
+
+void main() {
+    struct S { float x; };
+    float d = _glslt_eval_0(vec3(1.));
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn extra_lambda_arguments() {
+    let tu = parse(
+        r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float eval(in sdf3d f, vec3 p) { return f(p, 2.); }
+
+void main() {
+    float d = eval(sdSphere(_1 * _2), vec3(1.));
+}"#,
+    );
+
+    // The generated code is invalid GLSL, but the transform must not panic
+    let _ = glslt::transform(std::iter::once(&tu), Default::default());
+}
+
+#[test]
+fn recursive_function() {
+    let tu = parse(
+        r#"float fact(int n) { return n <= 1 ? 1. : float(n) * fact(n - 1); }
+
+void main() {
+    float x = fact(5);
+}"#,
+    );
+
+    assert!(matches!(
+        glslt::transform_min(
+            std::iter::once(&tu),
+            std::iter::once("main"),
+            Default::default()
+        ),
+        Err(glslt::Error::RecursiveFunction { name, span: Some(_) }) if name == "fact"
+    ));
+}
+
+#[test]
+fn redefined_function() {
+    let tu = parse(
+        r#"void f() {}
+
+struct S { float x; };
+
+void f() { S s; }
+
+void main() {
+    f();
+}"#,
+    );
+
+    // Redefinitions are reported instead of replacing the previous definition
+    assert!(matches!(
+        glslt::transform_min(
+            std::iter::once(&tu),
+            std::iter::once("main"),
+            Default::default(),
+        ),
+        Err(glslt::Error::DuplicateDefinition { name, .. }) if name == "f"
+    ));
+}
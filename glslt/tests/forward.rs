@@ -0,0 +1,118 @@
+//! Tests for forward declarations of functions
+
+mod common;
+
+use glslt::{TransformConfig, TransformConfigBuilder};
+
+fn preserve_config() -> TransformConfig {
+    let mut builder = TransformConfigBuilder::default();
+    builder.preserve_forward_declarations(true);
+    builder.build()
+}
+
+#[test]
+fn forward_declaration_preserved() {
+    common::verify_transform_with_config(
+        r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p);
+float sdSphere(vec3 p);
+
+float opUnion(in sdf3d a, in sdf3d b, vec3 p) { return min(a(p), b(p)); }
+
+float sdBox(vec3 p) { return length(max(abs(p) - 1., 0.)); }
+
+void main() {
+    float d = opUnion(sdSphere, sdBox, vec3(1.));
+}
+
+float sdSphere(vec3 p) { return length(p) - 1.; }"#,
+        r#"float sdSphere(vec3 p);
+float sdSphere(vec3 p);
+
+float sdBox(vec3 p) { return length(max(abs(p) - 1., 0.)); }
+
+float _glslt_opUnion_0(vec3 p) { return min(sdSphere(p), sdBox(p)); }
+
+void main() {
+    float d = _glslt_opUnion_0(vec3(1.));
+}
+
+float sdSphere(vec3 p) { return length(p) - 1.; }"#,
+        preserve_config(),
+    );
+}
+
+#[test]
+fn forward_declaration_min() {
+    common::verify_min_transform_with_config(
+        r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p);
+
+float opUnion(in sdf3d a, in sdf3d b, vec3 p) { return min(a(p), b(p)); }
+
+float sdBox(vec3 p) { return length(max(abs(p) - 1., 0.)); }
+
+void main() {
+    float d = opUnion(sdSphere, sdBox, vec3(1.));
+}
+
+float sdSphere(vec3 p) { return length(p) - 1.; }"#,
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float sdBox(vec3 p) { return length(max(abs(p) - 1., 0.)); }
+
+float _glslt_opUnion_0(vec3 p) { return min(sdSphere(p), sdBox(p)); }
+
+void main() {
+    float d = _glslt_opUnion_0(vec3(1.));
+}"#,
+        "main",
+        preserve_config(),
+    );
+}
+
+#[test]
+fn explicit_pointer_type() {
+    common::verify_transform_with_config(
+        r#"layout(pointer) float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+sdf3d unit = sdSphere(_1);
+
+float eval(in sdf3d f, vec3 p) { return f(p); }
+
+void main() {
+    float d = eval(unit, vec3(1.));
+}"#,
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float _glslt_eval_0(vec3 p) { return sdSphere(p); }
+
+void main() {
+    float d = _glslt_eval_0(vec3(1.));
+}"#,
+        preserve_config(),
+    );
+}
+
+#[test]
+fn forward_declaration_default_mode() {
+    // Without preserve_forward_declarations, the prototype is consumed as a pointer type
+    common::verify_transform(
+        r#"float sdSphere(vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+void main() {
+    float d = sdSphere(vec3(1.));
+}"#,
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+void main() {
+    float d = sdSphere(vec3(1.));
+}"#,
+    );
+}
@@ -0,0 +1,219 @@
+//! Tests for namespaced library imports
+
+mod common;
+
+use common::{parse, parse_files};
+
+#[test]
+fn same_names_in_two_libraries() {
+    let tu = parse_files(
+        "imports",
+        &[
+            (
+                "main.glsl",
+                r#"#pragma glslt import "a.glsl" as a
+#pragma glslt import "b.glsl" as b
+
+void main() {
+    float d = a.opUnion(a.sdSphere, a.sdSphere, vec3(1.));
+    float e = b_opUnion(b.sdBox, vec3(1.));
+}
+"#,
+            ),
+            (
+                "a.glsl",
+                r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float opUnion(in sdf3d f, in sdf3d g, vec3 p) { return min(f(p), g(p)); }
+"#,
+            ),
+            (
+                "b.glsl",
+                r#"#pragma once
+float sdf3d(in vec3 p);
+
+struct Box { vec3 size; };
+
+float sdBox(vec3 p) { Box box = Box(vec3(1.)); return length(max(abs(p) - box.size, 0.)); }
+
+float opUnion(in sdf3d f, vec3 p) { return min(f(p), 0.); }
+"#,
+            ),
+        ],
+    );
+
+    let s = transform_to_string(&[tu]);
+
+    assert!(s.contains("float a_sdSphere(vec3 p)"), "{}", s);
+    assert!(s.contains("struct b_Box"), "{}", s);
+    assert!(s.contains("box.size"), "{}", s);
+    assert!(s.contains("float d = _glslt_a_opUnion_"), "{}", s);
+    assert!(s.contains("float e = _glslt_b_opUnion_"), "{}", s);
+    assert!(s.contains("min(a_sdSphere(p), a_sdSphere(p))"), "{}", s);
+    assert!(s.contains("min(b_sdBox(p), 0.)"), "{}", s);
+    assert!(!s.contains("pragma"), "{}", s);
+}
+
+fn transform_to_string(units: &[glslt::glsl_lang::ast::TranslationUnit]) -> String {
+    let tu =
+        glslt::transform(units.iter(), Default::default()).expect("failed to transform source");
+
+    let mut s = String::new();
+    glslt::glsl_lang::transpiler::glsl::show_translation_unit(&mut s, &tu, Default::default())
+        .unwrap();
+    s
+}
+
+#[test]
+fn namespaced_units() {
+    let s = transform_to_string(&[
+        parse(
+            r#"#pragma glslt namespace(sdf)
+#define SQ(x) ((x) * (x))
+float sdf3d(in vec3 p);
+
+struct Hit { float opUnion; };
+
+float sdSphere(vec3 p) { return sqrt(SQ(p.x)) - 1.; }
+
+float opUnion(in sdf3d f, in sdf3d g, vec3 p) { Hit h = Hit(min(f(p), g(p))); return h.opUnion; }
+#pragma glslt end_namespace
+"#,
+        ),
+        parse(
+            r#"void main() {
+    float d = sdf.opUnion(sdf.sdSphere, sdf.sdSphere, vec3(1.));
+    {
+        vec3 sdf = vec3(2.);
+        d += sdf.x;
+    }
+}
+"#,
+        ),
+    ]);
+
+    assert!(s.contains("float sdf_sdSphere(vec3 p)"), "{}", s);
+    assert!(s.contains("sqrt(p.x * p.x)"), "{}", s);
+    assert!(s.contains("struct sdf_Hit"), "{}", s);
+    assert!(s.contains("sdf_Hit h = sdf_Hit("), "{}", s);
+    assert!(s.contains("h.opUnion"), "{}", s);
+    assert!(s.contains("float d = _glslt_sdf_opUnion_"), "{}", s);
+    assert!(s.contains("min(sdf_sdSphere(p), sdf_sdSphere(p))"), "{}", s);
+    assert!(s.contains("d += sdf.x"), "{}", s);
+    assert!(!s.contains("pragma"), "{}", s);
+}
+
+#[test]
+fn nested_namespaces() {
+    let s = transform_to_string(&[parse(
+        r#"#pragma glslt namespace(sdf)
+#pragma glslt namespace(util)
+float sq(float x) { return x * x; }
+#pragma glslt end_namespace
+float sdSphere(vec3 p) { return util.sq(length(p)) - 1.; }
+#pragma glslt end_namespace
+
+void main() {
+    float d = sdf.sdSphere(vec3(1.)) + sdf_util.sq(2.);
+}
+"#,
+    )]);
+
+    assert!(s.contains("float sdf_util_sq(float x)"), "{}", s);
+    assert!(s.contains("return sdf_util_sq(length(p)) - 1."), "{}", s);
+    assert!(
+        s.contains("float d = sdf_sdSphere(vec3(1.)) + sdf_util_sq(2.)"),
+        "{}",
+        s
+    );
+}
+
+#[test]
+fn unknown_qualified_name() {
+    let result = glslt::transform(
+        [
+            parse("#pragma glslt namespace(sdf)\nfloat sdSphere(vec3 p) { return length(p); }\n#pragma glslt end_namespace\n"),
+            parse("void main() { float d = sdf.sdBox(vec3(1.)); }"),
+        ]
+        .iter(),
+        Default::default(),
+    );
+
+    assert!(
+        matches!(
+            result,
+            Err(glslt::Error::UnknownQualifiedName { ref namespace, ref name, .. })
+                if namespace == "sdf" && name == "sdBox"
+        ),
+        "{:?}",
+        result
+    );
+}
+
+#[test]
+fn malformed_import() {
+    for directive in [
+        "#pragma glslt import sdf.glsl as sdf",
+        "#pragma glslt import \"sdf.glsl\"",
+        "#pragma glslt import \"sdf.glsl\" as 2d",
+        "#pragma glslt import \"sdf.glsl\"as sdf",
+        "#pragma glslt namespace(2d)",
+        "#pragma glslt end_namespace",
+    ] {
+        let result = glslt::transform(std::iter::once(&parse(directive)), Default::default());
+        assert!(
+            matches!(result, Err(glslt::Error::InvalidPragma { .. })),
+            "{}: {:?}",
+            directive,
+            result
+        );
+    }
+
+    let result = glslt::transform(
+        std::iter::once(&parse("#pragma glslt import <sdf.glsl> as sdf")),
+        Default::default(),
+    );
+    assert!(
+        matches!(result, Err(glslt::Error::UnresolvedImport { ref path, .. }) if path == "sdf.glsl"),
+        "{:?}",
+        result
+    );
+}
+
+#[test]
+fn same_library_in_two_namespaces() {
+    let tu = parse_files(
+        "imports-twice",
+        &[
+            (
+                "main.glsl",
+                r#"#pragma glslt import "lib.glsl" as a
+#pragma glslt import "lib.glsl" as b
+#pragma glslt import "lib.glsl" as a
+
+void main() {
+    float d = a.sdSphere(vec3(1.)) + b.sdSphere(vec3(2.));
+}
+"#,
+            ),
+            (
+                "lib.glsl",
+                r#"#pragma once
+float sdSphere(vec3 p) { return length(p) - 1.; }
+"#,
+            ),
+        ],
+    );
+
+    let s = transform_to_string(&[tu]);
+
+    assert_eq!(s.matches("float a_sdSphere(vec3 p)").count(), 1, "{}", s);
+    assert_eq!(s.matches("float b_sdSphere(vec3 p)").count(), 1, "{}", s);
+    assert!(
+        s.contains("float d = a_sdSphere(vec3(1.)) + b_sdSphere(vec3(2.))"),
+        "{}",
+        s
+    );
+}
@@ -0,0 +1,133 @@
+//! Tests for inlining of template instances
+
+mod common;
+
+use glslt::TransformConfigBuilder;
+
+fn inline_config() -> glslt::TransformConfig {
+    let mut builder = TransformConfigBuilder::default();
+    builder.inline_threshold(8);
+    builder.build()
+}
+
+#[test]
+fn inline_lambda() {
+    common::verify_both_with_config(
+        r#"float fnT(float x);
+
+float apply(in fnT f, float x) { return f(x); }
+
+void main() {
+    float a = 2.;
+    gl_FragColor = vec4(2. * apply(_1 + a, a * 3.));
+}"#,
+        r#"void main() {
+    float a = 2.;
+    gl_FragColor = vec4(2. * (a * 3. + a));
+}"#,
+        "main",
+        inline_config(),
+    );
+}
+
+#[test]
+fn inline_side_effects() {
+    common::verify_both_with_config(
+        r#"float fnT(float x);
+
+float apply(in fnT f, float x) { return f(x); }
+
+float applyTwice(in fnT f, float x) {
+    float y = f(x);
+    return f(y);
+}
+
+void main() {
+    float a = 2.;
+    gl_FragColor = vec4(apply(_1 + a, a++), applyTwice(_1 * 2., a));
+}"#,
+        r#"float _glslt_apply_0(float x, float _glslt_lp0) { return x + _glslt_lp0; }
+
+float _glslt_applyTwice_0(float x) {
+    float y = x * 2.;
+    return y * 2.;
+}
+
+void main() {
+    float a = 2.;
+    gl_FragColor = vec4(_glslt_apply_0(a++, a), _glslt_applyTwice_0(a));
+}"#,
+        "main",
+        inline_config(),
+    );
+}
+
+#[test]
+fn inline_shadowed_global() {
+    common::verify_both_with_config(
+        r#"float fnT(float x);
+
+float scale(in fnT f, float x) { return f(x) * g; }
+
+void main() {
+    float g = 2.;
+    gl_FragColor = vec4(scale(_1, g));
+}"#,
+        r#"float _glslt_scale_0(float x) { return x * g; }
+
+void main() {
+    float g = 2.;
+    gl_FragColor = vec4(_glslt_scale_0(g));
+}"#,
+        "main",
+        inline_config(),
+    );
+}
+
+#[test]
+fn inline_written_parameter() {
+    common::verify_both_with_config(
+        r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p, float r) { return length(p) - r; }
+
+float apply(in sdf3d f, vec3 p) { return f(p *= 2.); }
+
+void main() {
+    vec3 q = vec3(1.);
+    gl_FragColor = vec4(apply(sdSphere(_1, 1.), q));
+}"#,
+        r#"float sdSphere(vec3 p, float r) { return length(p) - r; }
+
+float _glslt_apply_0(vec3 p) { return sdSphere(p *= 2., 1.); }
+
+void main() {
+    vec3 q = vec3(1.);
+    gl_FragColor = vec4(_glslt_apply_0(q));
+}"#,
+        "main",
+        inline_config(),
+    );
+}
+
+#[test]
+fn inline_implicit_conversion() {
+    common::verify_both_with_config(
+        r#"float fnT(float x);
+
+float apply(in fnT f, float x) { return f(x); }
+
+void main() {
+    gl_FragColor = vec4(apply(_1 / 2, 3), apply(int(_1), 1.));
+}"#,
+        r#"float _glslt_apply_0(float x) { return x / 2; }
+
+float _glslt_apply_1(float x) { return int(x); }
+
+void main() {
+    gl_FragColor = vec4(_glslt_apply_0(3), _glslt_apply_1(1.));
+}"#,
+        "main",
+        inline_config(),
+    );
+}
@@ -0,0 +1,183 @@
+//! Tests for interfaces, i.e. structs of function pointers
+
+mod common;
+
+#[test]
+fn interface_argument() {
+    common::verify_both(
+        r#"vec3 shadeFn(vec3 p, vec3 n);
+vec3 normalFn(vec3 p);
+
+struct Material { shadeFn shade; normalFn normal; };
+
+vec3 phong(vec3 p, vec3 n) { return n * 0.5 + 0.5; }
+
+vec3 flatNormal(vec3 p) { return vec3(0., 1., 0.); }
+
+vec3 render(in Material m, vec3 p) { return m.shade(p, m.normal(p)); }
+
+void main() {
+    vec3 c = render(Material(phong, flatNormal), vec3(1.));
+}"#,
+        r#"vec3 phong(vec3 p, vec3 n) { return n * 0.5 + 0.5; }
+
+vec3 flatNormal(vec3 p) { return vec3(0., 1., 0.); }
+
+vec3 _glslt_render_0(vec3 p) { return phong(p, flatNormal(p)); }
+
+void main() {
+    vec3 c = _glslt_render_0(vec3(1.));
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn interface_forwarding() {
+    common::verify_both(
+        r#"vec3 shadeFn(vec3 p, vec3 n);
+vec3 normalFn(vec3 p);
+
+struct Material { shadeFn shade; normalFn normal; };
+
+vec3 phong(vec3 p, vec3 n) { return n * 0.5 + 0.5; }
+
+vec3 flatNormal(vec3 p) { return vec3(0., 1., 0.); }
+
+vec3 render(in Material m, vec3 p) { return m.shade(p, m.normal(p)); }
+
+vec3 twice(in Material m, vec3 p) { return render(m, p) * 2.; }
+
+void main() {
+    float k = 2.;
+    vec3 c = twice(Material(phong, flatNormal(_p) * k), vec3(1.));
+}"#,
+        r#"vec3 phong(vec3 p, vec3 n) { return n * 0.5 + 0.5; }
+
+vec3 flatNormal(vec3 p) { return vec3(0., 1., 0.); }
+
+vec3 _glslt_render_0(vec3 p, float _glslt_lp0) { return phong(p, flatNormal(p) * _glslt_lp0); }
+
+vec3 _glslt_twice_0(vec3 p, float _glslt_lp0) { return _glslt_render_0(p, _glslt_lp0) * 2.; }
+
+void main() {
+    float k = 2.;
+    vec3 c = _glslt_twice_0(vec3(1.), k);
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn interface_field_argument() {
+    common::verify_both(
+        r#"vec3 shadeFn(vec3 p, vec3 n);
+vec3 normalFn(vec3 p);
+
+struct Material { shadeFn shade; normalFn normal; };
+
+struct Light { vec3 position; };
+
+vec3 phong(vec3 p, vec3 n) { return n * 0.5 + 0.5; }
+
+vec3 flatNormal(vec3 p) { return vec3(0., 1., 0.); }
+
+vec3 bump(in normalFn n, vec3 p) { return normalize(n(p) + 0.1); }
+
+vec3 render(in Material m, Light l) { return m.shade(l.position, bump(m.normal, l.position)); }
+
+void main() {
+    vec3 c = render(Material(phong, flatNormal), Light(vec3(1.)));
+}"#,
+        r#"struct Light { vec3 position; };
+
+vec3 phong(vec3 p, vec3 n) { return n * 0.5 + 0.5; }
+
+vec3 flatNormal(vec3 p) { return vec3(0., 1., 0.); }
+
+vec3 _glslt_bump_0(vec3 p) { return normalize(flatNormal(p) + 0.1); }
+
+vec3 _glslt_render_0(Light l) { return phong(l.position, _glslt_bump_0(l.position)); }
+
+void main() {
+    vec3 c = _glslt_render_0(Light(vec3(1.)));
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn interface_invalid_argument() {
+    use glslt::glsl_lang::parse::IntoParseBuilderExt;
+
+    let tu: glslt::glsl_lang::ast::TranslationUnit = r#"vec3 shadeFn(vec3 p, vec3 n);
+vec3 normalFn(vec3 p);
+
+struct Material { shadeFn shade; normalFn normal; };
+
+vec3 phong(vec3 p, vec3 n) { return n * 0.5 + 0.5; }
+
+vec3 render(in Material m, vec3 p) { return m.shade(p, m.normal(p)); }
+
+void main() {
+    vec3 c = render(phong, vec3(1.));
+}"#
+    .builder()
+    .context(&glslt::parse::make_parse_context(None))
+    .parse()
+    .expect("failed to parse source")
+    .0;
+
+    assert!(matches!(
+        glslt::transform(std::iter::once(&tu), Default::default()),
+        Err(glslt::Error::InvalidInterfaceArgument { interface, index: 0, .. }) if interface == "Material"
+    ));
+}
+
+#[test]
+fn interface_shadowed_parameter() {
+    common::verify_both(
+        r#"vec3 shadeFn(vec3 p, vec3 n);
+vec3 normalFn(vec3 p);
+
+struct Material { shadeFn shade; normalFn normal; };
+
+struct Surf { vec3 p; };
+
+vec3 phong(vec3 p, vec3 n) { return n * 0.5 + 0.5; }
+
+vec3 flatNormal(vec3 p) { return vec3(0., 1., 0.); }
+
+vec3 render(in Material m, vec3 p) {
+    {
+        Surf m = Surf(p);
+        Surf s = m;
+        p = s.p;
+    }
+    return m.shade(p, m.normal(p));
+}
+
+void main() {
+    vec3 c = render(Material(phong, flatNormal), vec3(1.));
+}"#,
+        r#"struct Surf { vec3 p; };
+
+vec3 phong(vec3 p, vec3 n) { return n * 0.5 + 0.5; }
+
+vec3 flatNormal(vec3 p) { return vec3(0., 1., 0.); }
+
+vec3 _glslt_render_0(vec3 p) {
+    {
+        Surf m = Surf(p);
+        Surf s = m;
+        p = s.p;
+    }
+    return phong(p, flatNormal(p));
+}
+
+void main() {
+    vec3 c = _glslt_render_0(vec3(1.));
+}"#,
+        "main",
+    );
+}
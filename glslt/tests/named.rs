@@ -0,0 +1,165 @@
+//! Tests for named template arguments
+
+mod common;
+
+#[test]
+fn named_reorder() {
+    common::verify_both(
+        r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float sdBox(vec3 p) { return max(p.x, max(p.y, p.z)); }
+
+float opBlend(in sdf3d a, in sdf3d b, vec3 p) { return min(a(p), b(p) * 2.); }
+
+void main() {
+    float x = opBlend(b = sdBox, a = sdSphere, vec3(1.));
+    float y = opBlend(sdSphere, vec3(2.), b = sdBox);
+}"#,
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float sdBox(vec3 p) { return max(p.x, max(p.y, p.z)); }
+
+float _glslt_opBlend_0(vec3 p) { return min(sdSphere(p), sdBox(p) * 2.); }
+
+void main() {
+    float x = _glslt_opBlend_0(vec3(1.));
+    float y = _glslt_opBlend_0(vec3(2.));
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn named_with_defaults() {
+    common::verify_transform(
+        r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float sdBox(vec3 p) { return max(p.x, max(p.y, p.z)); }
+
+float opUnion(
+    in sdf3d a,
+    layout(default_fn = sdSphere) in sdf3d b,
+    layout(default_fn = sdBox) in sdf3d c,
+    vec3 p
+) {
+    return min(a(p), min(b(p), c(p)));
+}
+
+void main() {
+    float x = opUnion(sdBox, c = sdSphere, vec3(1.));
+}"#,
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float sdBox(vec3 p) { return max(p.x, max(p.y, p.z)); }
+
+float _glslt_opUnion_0(vec3 p) { return min(sdBox(p), min(sdSphere(p), sdSphere(p))); }
+
+void main() {
+    float x = _glslt_opUnion_0(vec3(1.));
+}"#,
+    );
+}
+
+#[test]
+fn named_local_assignment() {
+    common::verify_both(
+        r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float eval(in sdf3d f, vec3 p) { return f(p); }
+
+void main() {
+    vec3 p;
+    float x = eval(sdSphere, p = vec3(1.));
+}"#,
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float _glslt_eval_0(vec3 p) { return sdSphere(p); }
+
+void main() {
+    vec3 p;
+    float x = _glslt_eval_0(p = vec3(1.));
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn named_global_assignment() {
+    common::verify_transform(
+        r#"float sdf3d(in vec3 p);
+
+vec3 q;
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float eval(in sdf3d f, vec3 p) { return f(p); }
+
+void main() {
+    float x = eval(sdSphere, q = vec3(1.));
+}"#,
+        r#"vec3 q;
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float _glslt_eval_0(vec3 p) { return sdSphere(p); }
+
+void main() {
+    float x = _glslt_eval_0(q = vec3(1.));
+}"#,
+    );
+}
+
+fn transform_err(src: &str) -> glslt::Error {
+    use glslt::glsl_lang::parse::IntoParseBuilderExt;
+
+    let tu: glslt::glsl_lang::ast::TranslationUnit = src
+        .builder()
+        .context(&glslt::parse::make_parse_context(None))
+        .parse()
+        .expect("failed to parse source")
+        .0;
+
+    glslt::transform(std::iter::once(&tu), Default::default()).expect_err("transform should fail")
+}
+
+#[test]
+fn named_unknown() {
+    assert!(matches!(
+        transform_err(
+            r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float eval(in sdf3d f, vec3 p) { return f(p); }
+
+void main() {
+    float x = eval(g = sdSphere, vec3(1.));
+}"#
+        ),
+        glslt::Error::UnknownTemplateArgument { argument, .. } if argument == "g"
+    ));
+}
+
+#[test]
+fn named_duplicate() {
+    assert!(matches!(
+        transform_err(
+            r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float eval(in sdf3d f, vec3 p) { return f(p); }
+
+void main() {
+    float x = eval(f = sdSphere, f = sdSphere, vec3(1.));
+}"#
+        ),
+        glslt::Error::DuplicateTemplateArgument { argument, .. } if argument == "f"
+    ));
+}
@@ -226,3 +226,53 @@ void main() {
         "main",
     );
 }
+
+#[test]
+fn nested_error_backtrace() {
+    use glslt::glsl_lang::parse::IntoParseBuilderExt;
+
+    let tu: glslt::glsl_lang::ast::TranslationUnit = r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float eval(in sdf3d h, vec3 p) { return h(p); }
+
+float opA(in sdf3d f, vec3 p) { return eval(g = f, p); }
+
+float opB(in sdf3d f, vec3 p) { return opA(f, p * 2.); }
+
+float opC(in sdf3d f, vec3 p) { return opB(f, p + 1.); }
+
+void main() {
+    float d = opC(sdSphere(_1 / 3.), vec3(1.));
+}"#
+    .builder()
+    .context(&glslt::parse::make_parse_context(None))
+    .parse()
+    .expect("failed to parse source")
+    .0;
+
+    match glslt::transform(std::iter::once(&tu), Default::default()) {
+        Err(glslt::Error::Instantiation { error, backtrace }) => {
+            assert!(matches!(
+                *error,
+                glslt::Error::UnknownTemplateArgument { ref argument, .. } if argument == "g"
+            ));
+
+            let notes: Vec<_> = backtrace
+                .iter()
+                .map(|note| (note.template.as_str(), note.arguments.join(", ")))
+                .collect();
+            assert_eq!(
+                notes,
+                [
+                    ("opA", "f, p * 2.".to_owned()),
+                    ("opB", "f, p + 1.".to_owned()),
+                    ("opC", "sdSphere(_1 / 3.), vec3(1.)".to_owned()),
+                ]
+            );
+            assert!(backtrace.iter().all(|note| note.span.is_some()));
+        }
+        other => panic!("expected an instantiation error, got {:?}", other),
+    }
+}
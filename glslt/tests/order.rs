@@ -0,0 +1,124 @@
+//! Tests for the independence of the transform from the declaration order
+
+mod common;
+
+#[test]
+fn nested_template_after_use() {
+    common::verify_both(
+        r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+void main() {
+    float d = opTwice(sdSphere, vec3(1.));
+}
+
+float opTwice(in sdf3d f, vec3 p) { return opScale(f, opScale(f, p)); }
+
+float opScale(in sdf3d f, vec3 p) { return f(p / 2.) * 2.; }"#,
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float _glslt_opScale_0(vec3 p) { return sdSphere(p / 2.) * 2.; }
+
+float _glslt_opTwice_0(vec3 p) { return _glslt_opScale_0(_glslt_opScale_0(p)); }
+
+void main() {
+    float d = _glslt_opTwice_0(vec3(1.));
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn template_after_use() {
+    common::verify_both(
+        r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+void main() {
+    float d = opScale(sdSphere, vec3(1.));
+}
+
+float opScale(in sdf3d f, vec3 p) { return f(p / 2.) * 2.; }"#,
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float _glslt_opScale_0(vec3 p) { return sdSphere(p / 2.) * 2.; }
+
+void main() {
+    float d = _glslt_opScale_0(vec3(1.));
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn function_argument_after_use() {
+    common::verify_transform(
+        r#"float sdf3d(in vec3 p);
+
+float opScale(in sdf3d f, vec3 p) { return f(p / 2.) * 2.; }
+
+void main() {
+    float d = opScale(sdSphere, vec3(1.));
+}
+
+float sdSphere(vec3 p) { return length(p) - 1.; }"#,
+        r#"float sdSphere(vec3 p);
+
+float _glslt_opScale_0(vec3 p) { return sdSphere(p / 2.) * 2.; }
+
+void main() {
+    float d = _glslt_opScale_0(vec3(1.));
+}
+
+float sdSphere(vec3 p) { return length(p) - 1.; }"#,
+    );
+}
+
+#[test]
+fn function_argument_after_use_min() {
+    common::verify_min_transform(
+        r#"float sdf3d(in vec3 p);
+
+float opScale(in sdf3d f, vec3 p) { return f(p / 2.) * 2.; }
+
+void main() {
+    float d = opScale(sdSphere, vec3(1.));
+}
+
+float sdSphere(vec3 p) { return length(p) - 1.; }"#,
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float _glslt_opScale_0(vec3 p) { return sdSphere(p / 2.) * 2.; }
+
+void main() {
+    float d = _glslt_opScale_0(vec3(1.));
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn specialization_before_generic() {
+    common::verify_transform(
+        r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float opScale(layout(specialize) in sdf3d sdSphere, vec3 p) { return length(p / 2.) * 2. - 1.; }
+
+float opScale(in sdf3d f, vec3 p) { return f(p / 2.) * 2.; }
+
+void main() {
+    float d = opScale(sdSphere, vec3(1.));
+}"#,
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float _glslt_opScale_0(vec3 p) { return length(p / 2.) * 2. - 1.; }
+
+void main() {
+    float d = _glslt_opScale_0(vec3(1.));
+}"#,
+    );
+}
@@ -0,0 +1,250 @@
+//! Tests for overloaded templates and pointer types
+
+mod common;
+
+#[test]
+fn overload_by_parameter_type() {
+    common::verify_both(
+        r#"float sdf2d(in vec2 p);
+float sdf3d(in vec3 p);
+
+float opScale(in sdf2d f, vec2 p) { return f(p / 2.) * 2.; }
+
+float opScale(in sdf3d f, vec3 p) { return f(p / 3.) * 3.; }
+
+void main() {
+    vec2 p2 = vec2(1.);
+    float a = opScale(length(_1), p2);
+    float b = opScale(length(_1), vec3(1.));
+}"#,
+        r#"float _glslt_opScale_0(vec2 p) { return length(p / 2.) * 2.; }
+
+float _glslt_opScale_1(vec3 p) { return length(p / 3.) * 3.; }
+
+void main() {
+    vec2 p2 = vec2(1.);
+    float a = _glslt_opScale_0(p2);
+    float b = _glslt_opScale_1(vec3(1.));
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn overload_by_parameter_count() {
+    common::verify_both(
+        r#"float sdf3d(in vec3 p);
+
+float opRound(in sdf3d f, vec3 p) { return f(p) - 0.1; }
+
+float opRound(in sdf3d f, vec3 p, float r) { return f(p) - r; }
+
+void main() {
+    float a = opRound(length(_1), vec3(1.));
+    float b = opRound(length(_1), vec3(1.), 0.5);
+}"#,
+        r#"float _glslt_opRound_0(vec3 p) { return length(p) - 0.1; }
+
+float _glslt_opRound_1(vec3 p, float r) { return length(p) - r; }
+
+void main() {
+    float a = _glslt_opRound_0(vec3(1.));
+    float b = _glslt_opRound_1(vec3(1.), 0.5);
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn overload_pointer_type() {
+    use glslt::glsl_lang::parse::IntoParseBuilderExt;
+
+    // Overloads of the same pointer type may also come from different translation units
+    let parse = |src: &str| -> glslt::glsl_lang::ast::TranslationUnit {
+        src.builder()
+            .context(&glslt::parse::make_parse_context(None))
+            .parse()
+            .expect("failed to parse source")
+            .0
+    };
+
+    let a = parse(
+        r#"float sdf(in vec2 p);
+
+float eval2(in sdf f) { return f(vec2(1.)); }"#,
+    );
+
+    let b = parse(
+        r#"float sdf(in vec3 p, float t);
+
+float eval3(in sdf f) { return f(vec3(1.), 2.); }
+
+void main() {
+    float a = eval2(length(_p));
+    float b = eval3(length(_p) * _t);
+}"#,
+    );
+
+    // Identical redeclarations are accepted
+    let c = parse("float sdf(in vec2 p);");
+
+    let mut s = String::new();
+    glslt::glsl_lang::transpiler::glsl::show_translation_unit(
+        &mut s,
+        &glslt::transform(vec![&a, &c, &b].into_iter(), Default::default())
+            .expect("failed to transform source"),
+        Default::default(),
+    )
+    .unwrap();
+
+    assert!(s.contains("return length(vec2(1.));"), "{}", s);
+    assert!(s.contains("return length(vec3(1.)) * 2.;"), "{}", s);
+}
+
+#[test]
+fn overload_pointer_type_min() {
+    use glslt::glsl_lang::parse::IntoParseBuilderExt;
+
+    let parse = |src: &str| -> glslt::glsl_lang::ast::TranslationUnit {
+        src.builder()
+            .context(&glslt::parse::make_parse_context(None))
+            .parse()
+            .expect("failed to parse source")
+            .0
+    };
+
+    let a = parse(
+        r#"float sdf(in vec2 p);
+
+float eval2(in sdf f) { return f(vec2(1.)); }"#,
+    );
+
+    let b = parse(
+        r#"float sdf(in vec3 q);
+
+float eval3(in sdf f) { return f(vec3(2.)); }
+
+void main() {
+    float a = eval2(length(_p));
+    float b = eval3(length(_q) * 2.);
+}"#,
+    );
+
+    let mut s = String::new();
+    glslt::glsl_lang::transpiler::glsl::show_translation_unit(
+        &mut s,
+        &glslt::transform_min(
+            vec![&a, &b].into_iter(),
+            std::iter::once("main"),
+            Default::default(),
+        )
+        .expect("failed to transform source"),
+        Default::default(),
+    )
+    .unwrap();
+
+    assert!(s.contains("return length(vec2(1.));"), "{}", s);
+    assert!(s.contains("return length(vec3(2.)) * 2.;"), "{}", s);
+}
+
+#[test]
+fn overload_ambiguous() {
+    use glslt::glsl_lang::parse::IntoParseBuilderExt;
+
+    let tu: glslt::glsl_lang::ast::TranslationUnit = r#"float sdf2d(in vec2 p);
+
+float opScale(in sdf2d f, vec2 p) { return f(p / 2.) * 2.; }
+
+float opScale(in sdf2d f, vec3 p) { return f(p.xy / 3.) * 3.; }
+
+void main() {
+    float a = opScale(length(_1), unknown);
+}"#
+    .builder()
+    .context(&glslt::parse::make_parse_context(None))
+    .parse()
+    .expect("failed to parse source")
+    .0;
+
+    assert!(matches!(
+        glslt::transform(std::iter::once(&tu), Default::default()),
+        Err(glslt::Error::AmbiguousTemplateCall(_))
+    ));
+}
+
+#[test]
+fn function_overloads_kept() {
+    common::verify_min_transform(
+        r#"float f(float x) { return x * 2.; }
+
+vec2 f(vec2 v) { return vec2(f(v.x), f(v.y)); }
+
+float g(float x) { return x; }
+
+void main() {
+    vec2 v = f(vec2(1.));
+}"#,
+        r#"float f(float x) { return x * 2.; }
+
+vec2 f(vec2 v) { return vec2(f(v.x), f(v.y)); }
+
+void main() {
+    vec2 v = f(vec2(1.));
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn function_overloads_chain_order() {
+    common::verify_min_transform(
+        r#"float f(float x) { return x * 2.; }
+
+vec2 f(vec2 v) { return vec2(f(v.x), v.y); }
+
+vec3 f(vec3 v) { return vec3(f(v.xy), v.z); }
+
+void main() {
+    vec3 v = f(vec3(1.));
+}"#,
+        r#"float f(float x) { return x * 2.; }
+
+vec2 f(vec2 v) { return vec2(f(v.x), v.y); }
+
+vec3 f(vec3 v) { return vec3(f(v.xy), v.z); }
+
+void main() {
+    vec3 v = f(vec3(1.));
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn overload_by_function_argument() {
+    common::verify_transform(
+        r#"float sdf2d(in vec2 p);
+float sdf3d(in vec3 p);
+
+float sdCircle(vec2 p) { return length(p) - 1.; }
+float sdCircle(vec3 p) { return length(p.xy) - 1.; }
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float opEval(in sdf2d f) { return f(vec2(0.)); }
+
+float opEval(in sdf3d f) { return f(vec3(0.)); }
+
+void main() {
+    float a = opEval(sdSphere);
+}"#,
+        r#"float sdCircle(vec2 p) { return length(p) - 1.; }
+float sdCircle(vec3 p) { return length(p.xy) - 1.; }
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float _glslt_opEval_0() { return sdSphere(vec3(0.)); }
+
+void main() {
+    float a = _glslt_opEval_0();
+}"#,
+    );
+}
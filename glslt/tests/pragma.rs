@@ -0,0 +1,146 @@
+//! Tests for in-source configuration directives
+
+use glslt::glsl_lang::{ast::TranslationUnit, parse::IntoParseBuilderExt};
+
+fn parse(src: &str) -> TranslationUnit {
+    let (mut tu, _, lexer) = src
+        .builder()
+        .context(&glslt::parse::make_parse_context(None))
+        .parse()
+        .expect("failed to parse source");
+    lexer.into_directives().inject(&mut tu);
+    tu
+}
+
+fn transform(src: &str) -> String {
+    let tu = glslt::transform(std::iter::once(&parse(src)), Default::default())
+        .expect("failed to transform source");
+
+    let mut s = String::new();
+    glslt::glsl_lang::transpiler::glsl::show_translation_unit(&mut s, &tu, Default::default())
+        .unwrap();
+    s
+}
+
+const SOURCE: &str = r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float opScale(in sdf3d f, vec3 p) { return f(p / 2.) * 2.; }
+
+float unused() { return 0.; }
+
+void main() {
+    float d = opScale(sdSphere, vec3(1.));
+}
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    fragColor = vec4(opScale(sdSphere, vec3(fragCoord, 0.)));
+}
+"#;
+
+#[test]
+fn prefix() {
+    let s = transform(&format!("#pragma glslt prefix(_p_)\n{}", SOURCE));
+
+    assert!(s.contains("float _p_opScale_"), "{}", s);
+    assert!(!s.contains("_glslt_"), "{}", s);
+    assert!(!s.contains("pragma"), "{}", s);
+}
+
+#[test]
+fn prefix_without_collect() {
+    use glslt::transform::{TransformUnit, Unit};
+
+    let mut unit = Unit::new();
+    for extdecl in parse(&format!("#pragma glslt prefix(_p_)\n{}", SOURCE)).0 {
+        unit.parse_external_declaration(extdecl)
+            .expect("failed to transform source");
+    }
+
+    let tu = unit.into_translation_unit().unwrap();
+    let mut s = String::new();
+    glslt::glsl_lang::transpiler::glsl::show_translation_unit(&mut s, &tu, Default::default())
+        .unwrap();
+
+    assert!(s.contains("float _p_opScale_"), "{}", s);
+    assert!(!s.contains("_glslt_"), "{}", s);
+}
+
+#[test]
+fn minify() {
+    let s = transform(&format!("#pragma glslt minify\n{}", SOURCE));
+
+    assert!(s.contains("void main()"), "{}", s);
+    assert!(!s.contains("mainImage"), "{}", s);
+    assert!(!s.contains("unused"), "{}", s);
+    assert!(!s.contains("pragma"), "{}", s);
+}
+
+#[test]
+fn keep() {
+    let s = transform(&format!("#pragma glslt keep(mainImage)\n{}", SOURCE));
+
+    assert!(s.contains("void mainImage("), "{}", s);
+    assert!(!s.contains("void main()"), "{}", s);
+    assert!(!s.contains("unused"), "{}", s);
+}
+
+#[test]
+fn keep_with_entry_points() {
+    let tu = glslt::transform_min(
+        std::iter::once(&parse(&format!("#pragma glslt keep(unused)\n{}", SOURCE))),
+        std::iter::once("main"),
+        Default::default(),
+    )
+    .expect("failed to transform source");
+
+    let mut s = String::new();
+    glslt::glsl_lang::transpiler::glsl::show_translation_unit(&mut s, &tu, Default::default())
+        .unwrap();
+
+    assert!(s.contains("void main()"), "{}", s);
+    assert!(s.contains("float unused()"), "{}", s);
+    assert!(!s.contains("mainImage"), "{}", s);
+}
+
+#[test]
+fn inline_threshold() {
+    let s = transform(&format!("#pragma glslt inline_threshold(16)\n{}", SOURCE));
+
+    assert!(
+        s.contains("float d = sdSphere(vec3(1.) / 2.) * 2.;"),
+        "{}",
+        s
+    );
+}
+
+#[test]
+fn other_pragmas_kept() {
+    let s = transform(&format!("#pragma optimize(off)\n{}", SOURCE));
+
+    assert!(s.contains("#pragma optimize(off)"), "{}", s);
+}
+
+#[test]
+fn invalid_pragma() {
+    for pragma in [
+        "#pragma glslt prefix",
+        "#pragma glslt prefix(a, b)",
+        "#pragma glslt keep()",
+        "#pragma glslt inline_threshold(many)",
+        "#pragma glslt minify(main)",
+        "#pragma glslt unknown",
+    ] {
+        let src = parse(&format!("{}\n{}", pragma, SOURCE));
+
+        assert!(
+            matches!(
+                glslt::transform(std::iter::once(&src), Default::default()),
+                Err(glslt::Error::InvalidPragma { .. })
+            ),
+            "{}",
+            pragma
+        );
+    }
+}
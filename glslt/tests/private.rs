@@ -0,0 +1,130 @@
+//! Tests for file-private functions
+
+mod common;
+
+#[test]
+fn private_function() {
+    common::verify_both(
+        r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+layout(private) float hash(vec3 p) { return fract(sin(dot(p, vec3(12.9898, 78.233, 45.164))) * 43758.5453); }
+
+float opNoise(in sdf3d f, vec3 p) { return f(p) + hash(p); }
+
+void main() {
+    float d = opNoise(sdSphere, vec3(1.));
+}"#,
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float _glslt_f0_hash(vec3 p) { return fract(sin(dot(p, vec3(12.9898, 78.233, 45.164))) * 43758.5453); }
+
+float _glslt_opNoise_0(vec3 p) { return sdSphere(p) + _glslt_f0_hash(p); }
+
+void main() {
+    float d = _glslt_opNoise_0(vec3(1.));
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn private_template_argument() {
+    common::verify_both(
+        r#"float sdf3d(in vec3 p);
+
+layout(private) float sdUnit(vec3 p) { return length(p) - 1.; }
+
+float opScale(in sdf3d f, vec3 p) { return f(p / 2.) * 2.; }
+
+float sdBig(vec3 p) { return opScale(sdUnit, p); }
+
+void main() {
+    float sdUnit = 1.;
+    float d = sdBig(vec3(sdUnit));
+}"#,
+        r#"float _glslt_f0_sdUnit(vec3 p) { return length(p) - 1.; }
+
+float _glslt_opScale_0(vec3 p) { return _glslt_f0_sdUnit(p / 2.) * 2.; }
+
+float sdBig(vec3 p) { return _glslt_opScale_0(p); }
+
+void main() {
+    float sdUnit = 1.;
+    float d = sdBig(vec3(sdUnit));
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn private_shadowed_in_sibling_block() {
+    common::verify_both(
+        r#"layout(private) float hash(float x) { return fract(sin(x) * 43758.5453); }
+
+void main() {
+    float d = 0.;
+    if (d > 1.) {
+        float hash = 2.;
+        d = hash;
+    } else {
+        d = hash(d);
+    }
+    d += hash(1.);
+}"#,
+        r#"float _glslt_f0_hash(float x) { return fract(sin(x) * 43758.5453); }
+
+void main() {
+    float d = 0.;
+    if (d > 1.) {
+        float hash = 2.;
+        d = hash;
+    } else {
+        d = _glslt_f0_hash(d);
+    }
+    d += _glslt_f0_hash(1.);
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn private_functions_across_files() {
+    let tu = common::parse_files(
+        "private",
+        &[
+            (
+                "main.glsl",
+                r#"#include "lib.glsl"
+
+float hash(float x) { return x * 2.; }
+
+void main() {
+    float d = noise(hash(1.));
+}
+"#,
+            ),
+            (
+                "lib.glsl",
+                r#"layout(private) float hash(float x) { return fract(sin(x) * 43758.5453); }
+
+float noise(float x) { return hash(floor(x)); }
+"#,
+            ),
+        ],
+    );
+
+    let tu = glslt::transform(std::iter::once(&tu), Default::default())
+        .expect("failed to transform source");
+
+    let mut s = String::new();
+    glslt::glsl_lang::transpiler::glsl::show_translation_unit(&mut s, &tu, Default::default())
+        .unwrap();
+
+    assert!(s.contains("float _glslt_f1_hash(float x)"), "{}", s);
+    assert!(s.contains("return _glslt_f1_hash(floor(x));"), "{}", s);
+    assert!(s.contains("float hash(float x)"), "{}", s);
+    assert!(s.contains("noise(hash(1.))"), "{}", s);
+    assert!(!s.contains("private"), "{}", s);
+}
@@ -0,0 +1,92 @@
+//! Tests for recursive template instantiations
+
+use glslt::glsl_lang::{ast::TranslationUnit, parse::IntoParseBuilderExt};
+use glslt::{TransformConfig, TransformConfigBuilder};
+
+fn transform(src: &str, config: TransformConfig) -> glslt::Result<TranslationUnit> {
+    let tu: TranslationUnit = src
+        .builder()
+        .context(&glslt::parse::make_parse_context(None))
+        .parse()
+        .expect("failed to parse source")
+        .0;
+
+    glslt::transform(std::iter::once(&tu), config)
+}
+
+#[test]
+fn recursive_same_arguments() {
+    let result = transform(
+        r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float opLoop(in sdf3d f, vec3 p) { return opLoop(f, p); }
+
+void main() {
+    float d = opLoop(sdSphere, vec3(1.));
+}"#,
+        Default::default(),
+    );
+
+    match result.as_ref().map_err(glslt::Error::root_cause) {
+        Err(glslt::Error::RecursiveTemplate { chain }) => {
+            assert!(chain.len() >= 2);
+            assert!(chain.iter().all(|name| name == "opLoop"));
+        }
+        other => panic!("expected RecursiveTemplate, got {:?}", other),
+    }
+}
+
+#[test]
+fn recursive_growing_arguments() {
+    let src = r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float opGrow(in sdf3d f, vec3 p) { return opGrow(f(_1 * 2.), p); }
+
+void main() {
+    float d = opGrow(sdSphere, vec3(1.));
+}"#;
+
+    assert!(matches!(
+        transform(src, Default::default()).as_ref().map_err(glslt::Error::root_cause),
+        Err(glslt::Error::InstantiationDepthExceeded { limit, chain })
+            if *limit == glslt::DEFAULT_MAX_INSTANTIATION_DEPTH && chain.len() == limit + 1
+    ));
+
+    let mut builder = TransformConfigBuilder::default();
+    builder.max_instantiation_depth(4);
+
+    let error = transform(src, builder.build()).expect_err("transform should fail");
+    assert_eq!(
+        error.root_cause().to_string(),
+        "template instantiation depth exceeds 4: opGrow -> opGrow -> opGrow -> opGrow -> opGrow"
+    );
+}
+
+#[test]
+fn nested_depth_limit() {
+    let src = r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float opUnion(in sdf3d a, in sdf3d b, vec3 p) { return min(a(p), b(p)); }
+
+float opSelf(in sdf3d f, vec3 p) { return opUnion(f, f, p); }
+
+void main() {
+    float d = opSelf(sdSphere, vec3(1.));
+}"#;
+
+    transform(src, Default::default()).expect("failed to transform");
+
+    let mut builder = TransformConfigBuilder::default();
+    builder.max_instantiation_depth(1);
+
+    assert!(matches!(
+        transform(src, builder.build()).as_ref().map_err(glslt::Error::root_cause),
+        Err(glslt::Error::InstantiationDepthExceeded { limit: 1, chain }) if chain == &["opSelf", "opUnion"]
+    ));
+}
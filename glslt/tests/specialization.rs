@@ -0,0 +1,122 @@
+//! Tests for explicit template specializations
+
+mod common;
+
+#[test]
+fn specialization_partial() {
+    common::verify_transform(
+        r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float sdBox(vec3 p) { return max(p.x, max(p.y, p.z)); }
+
+float opBlend(in sdf3d a, in sdf3d b, vec3 p) { return min(a(p), b(p)); }
+
+float opBlend(layout(specialize) in sdf3d sdBox, in sdf3d b, vec3 p) { return max(sdBox(p), b(p)); }
+
+void main() {
+    float x = opBlend(sdSphere, sdSphere, vec3(1.));
+    float y = opBlend(sdBox, sdSphere, vec3(1.));
+    float z = opBlend(sdBox(_1 * 2.), sdSphere, vec3(1.));
+}"#,
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float sdBox(vec3 p) { return max(p.x, max(p.y, p.z)); }
+
+float _glslt_opBlend_0(vec3 p) { return min(sdSphere(p), sdSphere(p)); }
+
+float _glslt_opBlend_1(vec3 p) { return max(sdBox(p), sdSphere(p)); }
+
+float _glslt_opBlend_2(vec3 p) { return min(sdBox(p * 2.), sdSphere(p)); }
+
+void main() {
+    float x = _glslt_opBlend_0(vec3(1.));
+    float y = _glslt_opBlend_1(vec3(1.));
+    float z = _glslt_opBlend_2(vec3(1.));
+}"#,
+    );
+}
+
+#[test]
+fn specialization_nested() {
+    common::verify_both(
+        r#"float sdf3d(in vec3 p);
+
+float sdBox(vec3 p) { return max(p.x, max(p.y, p.z)); }
+
+float opScale(in sdf3d f, vec3 p) { return f(p / 2.) * 2.; }
+
+float opScale(layout(specialize) in sdf3d sdBox, vec3 p) { return sdBox(p / 2.) * 2. + 1.; }
+
+float opOuter(in sdf3d g, vec3 p) { return opScale(g, p); }
+
+void main() {
+    float x = opOuter(sdBox, vec3(1.));
+}"#,
+        r#"float sdBox(vec3 p) { return max(p.x, max(p.y, p.z)); }
+
+float _glslt_opScale_1(vec3 p) { return sdBox(p / 2.) * 2. + 1.; }
+
+float _glslt_opOuter_0(vec3 p) { return _glslt_opScale_1(p); }
+
+void main() {
+    float x = _glslt_opOuter_0(vec3(1.));
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn parameter_named_after_function() {
+    // Without layout(specialize), a parameter named after a function is a regular parameter
+    common::verify_transform(
+        r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float sdBox(vec3 p) { return max(p.x, max(p.y, p.z)); }
+
+float opScale(in sdf3d f, vec3 p) { return f(p / 2.) * 2.; }
+
+float opScale(in sdf3d sdBox, vec3 p, float s) { return sdBox(p / s) * s; }
+
+void main() {
+    float x = opScale(sdBox, vec3(1.));
+    float y = opScale(sdSphere, vec3(1.), 3.);
+}"#,
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float sdBox(vec3 p) { return max(p.x, max(p.y, p.z)); }
+
+float _glslt_opScale_0(vec3 p) { return sdBox(p / 2.) * 2.; }
+
+float _glslt_opScale_1(vec3 p, float s) { return sdSphere(p / s) * s; }
+
+void main() {
+    float x = _glslt_opScale_0(vec3(1.));
+    float y = _glslt_opScale_1(vec3(1.), 3.);
+}"#,
+    );
+}
+
+#[test]
+fn specialization_unknown_function() {
+    use glslt::glsl_lang::parse::IntoParseBuilderExt;
+
+    let tu: glslt::glsl_lang::ast::TranslationUnit = r#"float sdf3d(in vec3 p);
+
+float opScale(in sdf3d f, vec3 p) { return f(p / 2.) * 2.; }
+
+float opScale(layout(specialize) in sdf3d sdBox, vec3 p) { return sdBox(p); }"#
+        .builder()
+        .context(&glslt::parse::make_parse_context(None))
+        .parse()
+        .expect("failed to parse source")
+        .0;
+
+    assert!(matches!(
+        glslt::transform(std::iter::once(&tu), Default::default()),
+        Err(glslt::Error::UnknownSpecializedFunction { function, .. }) if function == "sdBox"
+    ));
+}
@@ -0,0 +1,144 @@
+//! Tests for type template parameters
+
+mod common;
+
+#[test]
+fn type_parameter_inferred() {
+    common::verify_transform(
+        r#"layout(typename) void genType();
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+genType smin(genType a, genType b, float k) {
+    genType h = clamp(0.5 + 0.5 * (b - a) / k, 0., 1.);
+    return mix(b, a, h) - k * h * (1. - h);
+}
+
+void main() {
+    vec2 u = vec2(1.);
+    vec2 w = smin(u, vec2(2.), .1);
+    float x = smin(sdSphere(vec3(1.)), 2., .2);
+}"#,
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+vec2 _glslt_smin_0(vec2 a, vec2 b, float k) {
+    vec2 h = clamp(0.5 + 0.5 * (b - a) / k, 0., 1.);
+    return mix(b, a, h) - k * h * (1. - h);
+}
+
+float _glslt_smin_1(float a, float b, float k) {
+    float h = clamp(0.5 + 0.5 * (b - a) / k, 0., 1.);
+    return mix(b, a, h) - k * h * (1. - h);
+}
+
+void main() {
+    vec2 u = vec2(1.);
+    vec2 w = _glslt_smin_0(u, vec2(2.), .1);
+    float x = _glslt_smin_1(sdSphere(vec3(1.)), 2., .2);
+}"#,
+    );
+}
+
+#[test]
+fn type_parameter_with_function_parameter() {
+    common::verify_transform(
+        r#"layout(typename) void genType();
+
+float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+genType twice(genType x) { return x * 2.; }
+
+float opScaled(in sdf3d f, vec3 p) { return twice(f(p)); }
+
+void main() {
+    float d = opScaled(sdSphere, vec3(1.));
+}"#,
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float _glslt_twice_0(float x) { return x * 2.; }
+
+float _glslt_opScaled_0(vec3 p) { return _glslt_twice_0(sdSphere(p)); }
+
+void main() {
+    float d = _glslt_opScaled_0(vec3(1.));
+}"#,
+    );
+}
+
+fn transform_err(src: &str) -> glslt::Error {
+    use glslt::glsl_lang::parse::IntoParseBuilderExt;
+
+    let tu: glslt::glsl_lang::ast::TranslationUnit = src
+        .builder()
+        .context(&glslt::parse::make_parse_context(None))
+        .parse()
+        .expect("failed to parse source")
+        .0;
+
+    glslt::transform(std::iter::once(&tu), Default::default()).expect_err("transform should fail")
+}
+
+#[test]
+fn type_parameter_used_right_after_declaration() {
+    use glslt::glsl_lang::parse::IntoParseBuilderExt;
+
+    let parse = |src: &str| {
+        src.builder::<glslt::glsl_lang::ast::TranslationUnit>()
+            .context(&glslt::parse::make_parse_context(None))
+            .parse()
+            .map(|_| ())
+    };
+
+    // The token after the declaration is read before the type parameter is declared
+    assert!(parse(
+        r#"layout(typename) void genType();
+genType twice(genType x) { return x * 2.; }"#
+    )
+    .is_err());
+
+    // Any other declaration in between is enough
+    assert!(parse(
+        r#"layout(typename) void genType();
+float sdf3d(in vec3 p);
+genType twice(genType x) { return x * 2.; }"#
+    )
+    .is_ok());
+}
+
+#[test]
+fn type_parameter_unresolved() {
+    assert!(matches!(
+        transform_err(
+            r#"layout(typename) void genType();
+
+float one() { return 1.; }
+
+genType zero() { return genType(0.); }
+
+void main() {
+    float x = zero();
+}"#
+        ),
+        glslt::Error::UnresolvedTypeParameter { parameter, .. } if parameter == "genType"
+    ));
+}
+
+#[test]
+fn type_parameter_conflicting() {
+    assert!(matches!(
+        transform_err(
+            r#"layout(typename) void genType();
+
+float one() { return 1.; }
+
+genType add(genType a, genType b) { return a + b; }
+
+void main() {
+    float x = add(1., vec2(2.));
+}"#
+        ),
+        glslt::Error::ConflictingTypeParameter { parameter, .. } if parameter == "genType"
+    ));
+}
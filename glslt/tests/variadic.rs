@@ -0,0 +1,292 @@
+//! Tests for variadic template parameters
+
+mod common;
+
+#[test]
+fn variadic_fold() {
+    common::verify_both(
+        r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float sdBox(vec3 p) { return length(max(abs(p) - 1., 0.)); }
+
+float opUnion(layout(variadic) in sdf3d shapes, vec3 p) {
+    float d = shapes[0](p);
+    for (int i = 1; i < shapes.length(); ++i) d = min(d, shapes[i](p));
+    return d;
+}
+
+void main() {
+    float r = 2.;
+    float a = opUnion(sdSphere, sdBox, sdSphere(_p / r) * r, vec3(1.));
+    float b = opUnion(sdSphere, sdBox, vec3(1.));
+}"#,
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float sdBox(vec3 p) { return length(max(abs(p) - 1., 0.)); }
+
+float _glslt_opUnion_0(vec3 p, float _glslt_lp0) {
+    float d = sdSphere(p);
+    {
+        d = min(d, sdBox(p));
+        d = min(d, sdSphere(p / _glslt_lp0) * _glslt_lp0);
+    }
+    return d;
+}
+
+float _glslt_opUnion_1(vec3 p) {
+    float d = sdSphere(p);
+    {
+        d = min(d, sdBox(p));
+    }
+    return d;
+}
+
+void main() {
+    float r = 2.;
+    float a = _glslt_opUnion_0(vec3(1.), r);
+    float b = _glslt_opUnion_1(vec3(1.));
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn variadic_forwarding() {
+    common::verify_both(
+        r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float sdBox(vec3 p) { return length(max(abs(p) - 1., 0.)); }
+
+float opUnion(layout(variadic) in sdf3d shapes, vec3 p) {
+    float d = 1e10;
+    for (int i = 0; i < shapes.length(); i++) d = min(d, shapes[i](p));
+    return d;
+}
+
+float opScaledUnion(float s, layout(variadic) in sdf3d shapes, vec3 p) {
+    return opUnion(shapes, p / s) * s;
+}
+
+void main() {
+    float c = opScaledUnion(2., sdSphere, sdBox, vec3(1.));
+}"#,
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float sdBox(vec3 p) { return length(max(abs(p) - 1., 0.)); }
+
+float _glslt_opUnion_0(vec3 p) {
+    float d = 1e10;
+    {
+        d = min(d, sdSphere(p));
+        d = min(d, sdBox(p));
+    }
+    return d;
+}
+
+float _glslt_opScaledUnion_0(float s, vec3 p) { return _glslt_opUnion_0(p / s) * s; }
+
+void main() {
+    float c = _glslt_opScaledUnion_0(2., vec3(1.));
+}"#,
+        "main",
+    );
+}
+
+fn transform_err(src: &str) -> glslt::Error {
+    use glslt::glsl_lang::parse::IntoParseBuilderExt;
+
+    let tu: glslt::glsl_lang::ast::TranslationUnit = src
+        .builder()
+        .context(&glslt::parse::make_parse_context(None))
+        .parse()
+        .expect("failed to parse source")
+        .0;
+
+    glslt::transform(std::iter::once(&tu), Default::default()).expect_err("transform should fail")
+}
+
+#[test]
+fn variadic_not_last() {
+    assert!(matches!(
+        transform_err(
+            r#"float sdf3d(in vec3 p);
+
+float opUnion(layout(variadic) in sdf3d shapes, in sdf3d last, vec3 p) { return min(shapes[0](p), last(p)); }"#
+        ),
+        glslt::Error::MisplacedParameterPack { index: 0, .. }
+    ));
+}
+
+#[test]
+fn variadic_runtime_index() {
+    assert!(matches!(
+        transform_err(
+            r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float opSelect(int j, layout(variadic) in sdf3d shapes, vec3 p) { return shapes[j](p); }
+
+void main() {
+    float d = opSelect(0, sdSphere, sdSphere, vec3(1.));
+}"#
+        ),
+        glslt::Error::InvalidPackExpansion { parameter, .. } if parameter == "shapes"
+    ));
+}
+
+#[test]
+fn variadic_shadowed_loop_variable() {
+    common::verify_both(
+        r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float sdBox(vec3 p) { return length(max(abs(p) - 1., 0.)); }
+
+float opUnion(layout(variadic) in sdf3d shapes, vec3 p) {
+    float d = 1e10;
+    for (int i = 0; i < shapes.length(); ++i) {
+        float s = shapes[i](p);
+        for (int i = 0; i < 2; ++i) s *= 0.5;
+        d = min(d, s + float(i));
+    }
+    return d;
+}
+
+void main() {
+    float d = opUnion(sdSphere, sdBox, vec3(1.));
+}"#,
+        r#"float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float sdBox(vec3 p) { return length(max(abs(p) - 1., 0.)); }
+
+float _glslt_opUnion_0(vec3 p) {
+    float d = 1e10;
+    {
+        {
+            float s = sdSphere(p);
+            for (int i = 0; i < 2; ++i) s *= 0.5;
+            d = min(d, s + float(0));
+        }
+        {
+            float s = sdBox(p);
+            for (int i = 0; i < 2; ++i) s *= 0.5;
+            d = min(d, s + float(1));
+        }
+    }
+    return d;
+}
+
+void main() {
+    float d = _glslt_opUnion_0(vec3(1.));
+}"#,
+        "main",
+    );
+}
+
+#[test]
+fn variadic_break_in_unrolled_loop() {
+    assert!(matches!(
+        transform_err(
+            r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float opFirstHit(layout(variadic) in sdf3d shapes, vec3 p) {
+    float d = 1e10;
+    for (int i = 0; i < shapes.length(); ++i) {
+        d = shapes[i](p);
+        if (d < 0.) break;
+    }
+    return d;
+}
+
+void main() {
+    float d = opFirstHit(sdSphere, sdSphere, vec3(1.));
+}"#
+        ),
+        glslt::Error::InvalidPackExpansion { parameter, .. } if parameter == "shapes"
+    ));
+}
+
+#[test]
+fn variadic_written_loop_variable() {
+    for body in ["i += 1;", "skip(i);"] {
+        let src = format!(
+            r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) {{ return length(p) - 1.; }}
+
+void skip(inout int i) {{ i++; }}
+
+float opUnion(layout(variadic) in sdf3d shapes, vec3 p) {{
+    float d = 1e10;
+    for (int i = 0; i < shapes.length(); ++i) {{
+        d = min(d, shapes[i](p));
+        {}
+    }}
+    return d;
+}}
+
+void main() {{
+    float d = opUnion(sdSphere, sdSphere, vec3(1.));
+}}"#,
+            body
+        );
+
+        assert!(matches!(
+            transform_err(&src),
+            glslt::Error::InvalidPackExpansion { parameter, .. } if parameter == "shapes"
+        ));
+    }
+}
+
+#[test]
+fn variadic_negative_loop_start() {
+    assert!(matches!(
+        transform_err(
+            r#"float sdf3d(in vec3 p);
+
+float sdSphere(vec3 p) { return length(p) - 1.; }
+
+float opUnion(layout(variadic) in sdf3d shapes, vec3 p) {
+    float d = 1e10;
+    for (int i = -1; i < shapes.length(); ++i) {
+        d = min(d, shapes[0](p) + float(i));
+    }
+    return d;
+}
+
+void main() {
+    float d = opUnion(sdSphere, sdSphere, vec3(1.));
+}"#
+        ),
+        glslt::Error::InvalidPackExpansion { parameter, .. } if parameter == "shapes"
+    ));
+}
+
+#[test]
+fn variadic_overload_not_viable() {
+    common::verify_both(
+        r#"float sdf3d(in vec3 p);
+
+float f(vec3 p) { return length(p); }
+
+float f(layout(variadic) in sdf3d s, vec3 p) { return s[0](p); }
+
+void main() {
+    float d = f(vec3(1.));
+}"#,
+        r#"float f(vec3 p) { return length(p); }
+
+void main() {
+    float d = f(vec3(1.));
+}"#,
+        "main",
+    );
+}
@@ -26,6 +26,12 @@
 //!   -I <INCLUDE>               System include paths
 //!   -K, --keep-fns <KEEP_FNS>  List of symbols to keep for minifying mode
 //!   -p, --prefix <PREFIX>      Identifier prefix for generated code
+//!       --inline-threshold <INLINE_THRESHOLD>
+//!                              Maximum size of template instances to inline at call sites
+//!       --max-instantiation-depth <MAX_INSTANTIATION_DEPTH>
+//!                              Maximum number of nested template instantiations
+//!       --preserve-forward-declarations
+//!                              Keep function prototypes as forward declarations unless they are used as pointer types
 //!   -h, --help                 Print help information
 //! ```
 //!
@@ -42,6 +48,11 @@
 
 use glslt::api::cli::*;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    glslt::api::cli::main(Opts::parse())
+fn main() {
+    // Report errors using their Display implementation, which includes template instantiation
+    // backtraces
+    if let Err(error) = glslt::api::cli::main(Opts::parse()) {
+        eprintln!("Error: {}", error);
+        std::process::exit(1);
+    }
 }